@@ -10,6 +10,8 @@
 #![warn(unused)]
 #![allow(missing_docs)]
 
+use genie_lang::LangFile;
+use genie_scx::{StringImport, StringKey};
 use std::io::{Read, Seek, Write};
 
 mod read;
@@ -97,6 +99,93 @@ where
 
         Ok(())
     }
+
+    /// Collect all the texts shown to players in this campaign into a string table, for
+    /// translation.
+    ///
+    /// The campaign name uses the key `campaign.name`, and the name of each scenario uses
+    /// `{index}.name`. The keys for texts in each scenario are those from
+    /// `Scenario::export_strings()`, prefixed with the scenario's index, like
+    /// `0.scenario.description`.
+    pub fn export_strings(&mut self) -> Result<LangFile, ReadCampaignError> {
+        let mut lang = LangFile::new();
+        lang.insert(StringKey::from("campaign.name"), self.name().to_string());
+        for i in 0..self.len() {
+            if let Some(name) = self.get_name(i) {
+                lang.insert(StringKey::from(format!("{}.name", i)), name.to_string());
+            }
+            for (key, string) in self.by_index(i)?.export_strings() {
+                lang.insert(StringKey::from(format!("{}.{}", i, key)), string);
+            }
+        }
+        Ok(lang)
+    }
+
+    /// Write a translated copy of this campaign to an output stream. `lang` uses the keys from
+    /// `export_strings()`; texts that are missing from `lang` are left as they are.
+    ///
+    /// Returns the new string table entries that the scenarios refer to. With
+    /// `StringImport::StringTable`, the scenarios get consecutive string table keys, so the
+    /// result can be shipped as a single string table.
+    pub fn write_translated<W: Write>(
+        &mut self,
+        output: &mut W,
+        lang: &LangFile,
+        mut import: StringImport,
+    ) -> Result<LangFile, WriteCampaignError> {
+        let translate = |key: String, original: &str| {
+            lang.get(&StringKey::from(key))
+                .map_or(original, String::as_str)
+                .to_string()
+        };
+
+        let (max_campaign_name, max_scenario_name) = write::max_name_lengths(self.version());
+        let check_length = |name: &str, max: usize| {
+            if name.len() > max {
+                Err(WriteCampaignError::NameTooLongError(name.len(), max))
+            } else {
+                Ok(())
+            }
+        };
+
+        let name = translate("campaign.name".to_string(), self.name());
+        check_length(&name, max_campaign_name)?;
+        let mut writer = CampaignWriter::new(&name, output).version(self.version());
+        let mut table = LangFile::new();
+        for i in 0..self.len() {
+            let mut scenario = self.by_index(i)?;
+            let name = self
+                .get_name(i)
+                .ok_or(WriteCampaignError::NotFoundError(i))?;
+            let name = translate(format!("{}.name", i), name);
+            check_length(&name, max_scenario_name)?;
+            let filename = self
+                .get_filename(i)
+                .ok_or(WriteCampaignError::NotFoundError(i))?
+                .to_string();
+
+            let prefix = format!("{}.", i);
+            let mut scenario_lang = LangFile::new();
+            for (key, string) in lang.iter() {
+                if let Some(key) = key.to_string().strip_prefix(&prefix) {
+                    scenario_lang.insert(StringKey::from(key), string.clone());
+                }
+            }
+
+            let scenario_table = scenario.import_strings(&scenario_lang, import);
+            if let StringImport::StringTable { first_id } = &mut import {
+                *first_id = first_id.saturating_add(scenario_table.len() as u32);
+            }
+            table.extend(scenario_table);
+            let mut bytes = vec![];
+            scenario.write_to(&mut bytes)?;
+            writer.add_raw(&name, &filename, bytes);
+        }
+
+        let _output = writer.flush()?;
+
+        Ok(table)
+    }
 }
 
 #[cfg(test)]
@@ -152,4 +241,39 @@ mod tests {
         assert_eq!(written_cpx.by_index_raw(0)?, incpx.by_index_raw(0)?);
         Ok(())
     }
+
+    #[test]
+    fn translate_campaign() -> anyhow::Result<()> {
+        let instream = File::open("./test/campaigns/Armies at War A Combat Showcase.cpn")?;
+        let mut outstream = vec![];
+        let mut incpx = Campaign::from(instream)?;
+        let mut lang = incpx.export_strings()?;
+        assert!(lang.contains_key(&StringKey::from("0.scenario.description")));
+        for string in lang.values_mut() {
+            *string = string.to_uppercase();
+        }
+        let table = incpx.write_translated(&mut outstream, &lang, StringImport::Inline)?;
+        assert!(table.is_empty());
+
+        let mut written_cpx = Campaign::from(Cursor::new(outstream))?;
+        assert_eq!(written_cpx.name(), incpx.name().to_uppercase());
+        assert_eq!(written_cpx.len(), incpx.len());
+        assert_eq!(written_cpx.get_filename(0), incpx.get_filename(0));
+        assert_eq!(written_cpx.export_strings()?, lang);
+        Ok(())
+    }
+
+    #[test]
+    fn translate_campaign_long_name() -> anyhow::Result<()> {
+        let instream = File::open("./test/campaigns/Armies at War A Combat Showcase.cpn")?;
+        let mut incpx = Campaign::from(instream)?;
+        let mut lang = LangFile::new();
+        lang.insert(StringKey::from("0.name"), "Art of War ".repeat(30));
+        let result = incpx.write_translated(&mut vec![], &lang, StringImport::Inline);
+        assert!(matches!(
+            result,
+            Err(WriteCampaignError::NameTooLongError(330, 254))
+        ));
+        Ok(())
+    }
 }
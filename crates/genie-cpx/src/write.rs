@@ -1,4 +1,6 @@
-use crate::{CPXVersion, CampaignHeader, ScenarioMeta, AOE1_DE, AOE2_DE, AOE_AOK};
+use crate::{
+    CPXVersion, CampaignHeader, ReadCampaignError, ScenarioMeta, AOE1_DE, AOE2_DE, AOE_AOK,
+};
 use byteorder::{WriteBytesExt, LE};
 use genie_scx::{DLCPackage, Result as SCXResult, Scenario};
 use std::io::{self, Write};
@@ -13,6 +15,28 @@ pub enum WriteCampaignError {
     /// the scenario file exists but could not be parsed.
     #[error("missing scenario data for index {}", .0)]
     NotFoundError(usize),
+    /// A scenario could not be written.
+    #[error("{}", .0)]
+    WriteSCXError(#[from] genie_scx::Error),
+    /// A scenario from the original campaign file could not be read.
+    #[error("{}", .0)]
+    ReadCampaignError(#[from] ReadCampaignError),
+    /// A campaign or scenario name is too long to be stored in the campaign file version.
+    #[error("name is {} bytes long, but the campaign file version supports up to {}", .0, .1)]
+    NameTooLongError(usize, usize),
+}
+
+/// Get the maximum length in bytes of the campaign name and of scenario names in a campaign file
+/// version.
+pub(crate) fn max_name_lengths(version: CPXVersion) -> (usize, usize) {
+    let variable_str = u16::MAX as usize - 1;
+    if version == AOE2_DE {
+        (255, variable_str)
+    } else if version == AOE1_DE {
+        (variable_str, variable_str)
+    } else {
+        (255, 254)
+    }
 }
 
 fn write_variable_str<W: Write>(output: &mut W, value: &str) -> io::Result<()> {
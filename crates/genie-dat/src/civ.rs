@@ -5,52 +5,11 @@ use crate::GameVersion;
 use arrayvec::ArrayString;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use encoding_rs::WINDOWS_1252;
-use genie_support::{fallible_try_from, infallible_try_into, read_opt_u16};
+use genie_support::read_opt_u16;
+pub use genie_support::CivilizationID;
 use std::convert::TryInto;
 use std::io::{Read, Result, Write};
 
-/// An ID identifying a civilization
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub struct CivilizationID(u8);
-
-impl From<u8> for CivilizationID {
-    fn from(n: u8) -> Self {
-        CivilizationID(n)
-    }
-}
-
-impl From<CivilizationID> for u8 {
-    fn from(n: CivilizationID) -> Self {
-        n.0
-    }
-}
-
-impl From<CivilizationID> for u16 {
-    fn from(n: CivilizationID) -> Self {
-        n.0.into()
-    }
-}
-
-impl From<CivilizationID> for u32 {
-    fn from(n: CivilizationID) -> Self {
-        n.0.into()
-    }
-}
-
-impl From<CivilizationID> for usize {
-    fn from(n: CivilizationID) -> Self {
-        n.0.into()
-    }
-}
-
-infallible_try_into!(CivilizationID, i16);
-infallible_try_into!(CivilizationID, i32);
-fallible_try_from!(CivilizationID, i8);
-fallible_try_from!(CivilizationID, i16);
-fallible_try_from!(CivilizationID, u16);
-fallible_try_from!(CivilizationID, i32);
-fallible_try_from!(CivilizationID, u32);
-
 type CivName = ArrayString<20>;
 
 /// Information about a civilization.
@@ -1,38 +1,10 @@
 use crate::FileVersion;
 use arrayvec::ArrayString;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{fallible_try_from, fallible_try_into, infallible_try_into};
+pub use genie_support::SoundID;
 use std::convert::TryInto;
 use std::io::{Read, Result, Write};
 
-/// An ID identifying a sound.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub struct SoundID(u16);
-impl From<u16> for SoundID {
-    fn from(n: u16) -> Self {
-        SoundID(n)
-    }
-}
-
-impl From<SoundID> for u16 {
-    fn from(n: SoundID) -> Self {
-        n.0
-    }
-}
-
-impl From<SoundID> for usize {
-    fn from(n: SoundID) -> Self {
-        n.0.into()
-    }
-}
-
-fallible_try_into!(SoundID, i16);
-infallible_try_into!(SoundID, i32);
-infallible_try_into!(SoundID, u32);
-fallible_try_from!(SoundID, i16);
-fallible_try_from!(SoundID, i32);
-fallible_try_from!(SoundID, u32);
-
 /// A "conceptual" sound, consisting of one or a group of sound files.
 ///
 /// Items can be picked depending on the player's civilization, and depending on the probabilities
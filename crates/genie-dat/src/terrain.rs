@@ -184,6 +184,18 @@ impl TerrainPassGraphic {
 }
 
 impl TerrainRestriction {
+    /// Get the passability of a terrain for units with this restriction. Units cannot move onto
+    /// or be placed on terrains with a passability of 0.
+    pub fn passability(&self, terrain: impl Into<TerrainID>) -> Option<f32> {
+        let index: usize = terrain.into().into();
+        self.passability.get(index).copied()
+    }
+
+    /// Can units with this restriction move onto the given terrain?
+    pub fn is_passable(&self, terrain: impl Into<TerrainID>) -> bool {
+        self.passability(terrain).map_or(false, |value| value > 0.0)
+    }
+
     pub fn read_from(
         mut input: impl Read,
         version: FileVersion,
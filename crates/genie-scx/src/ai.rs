@@ -1,9 +1,23 @@
-use crate::Result;
+use crate::format::SCXFormat;
+use crate::setup::player_index;
+use crate::types::SCXVersion;
+use crate::{Error, LostInformation, Result, VersionBundle};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{write_i32_str, DecodeStringError, ReadStringError, ReadStringsExt};
+use genie_support::{encode_str, write_i32_str, ReadStringsExt};
 use std::convert::TryFrom;
 use std::io::{Read, Write};
 
+/// Errors that may occur while changing the AI files in a scenario.
+#[derive(Debug, thiserror::Error)]
+pub enum AIError {
+    /// The player number is not a valid player in this scenario.
+    #[error("player {} does not exist in this scenario", .0)]
+    InvalidPlayer(u8),
+    /// The AI file can not be stored in the scenario's game version.
+    #[error("the scenario's game version can not store the {}", .0)]
+    Unsupported(LostInformation),
+}
+
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, num_enum::IntoPrimitive, num_enum::TryFromPrimitive,
 )]
@@ -73,32 +87,29 @@ pub struct AIErrorInfo {
     error_code: AIErrorCode,
 }
 
-fn parse_bytes(bytes: &[u8]) -> std::result::Result<String, ReadStringError> {
-    let mut bytes = bytes.to_vec();
-    if let Some(end) = bytes.iter().position(|&byte| byte == 0) {
-        bytes.truncate(end);
-    }
-    if bytes.is_empty() {
-        Ok("<empty>".to_string())
-    } else {
-        String::from_utf8(bytes).map_err(|_| ReadStringError::DecodeStringError(DecodeStringError))
+/// Write a string into a fixed size, zero-terminated buffer.
+///
+/// Strings that do not fit are an error rather than being cut off, because a cut in the middle of
+/// a multi-byte character would garble it.
+fn write_fixed_str(mut output: impl Write, string: &str, buffer: &mut [u8]) -> Result<()> {
+    let bytes = encode_str(string)?;
+    let max = buffer.len() - 1;
+    if bytes.len() > max {
+        return Err(Error::StringTooLongError(bytes.len(), max));
     }
+    buffer[..bytes.len()].copy_from_slice(&bytes);
+    output.write_all(buffer)?;
+    Ok(())
 }
 
 impl AIErrorInfo {
     /// Read AI error information from an input stream.
     pub fn read_from(mut input: impl Read) -> Result<Self> {
-        // TODO support non UTF8 encoding
-        let mut filename_bytes = [0; 257];
-        input.read_exact(&mut filename_bytes)?;
+        let filename = input.read_str(257)?.unwrap_or_default();
         let line_number = input.read_i32::<LE>()?;
-        let mut description_bytes = [0; 128];
-        input.read_exact(&mut description_bytes)?;
+        let description = input.read_str(128)?.unwrap_or_default();
         let error_code = AIErrorCode::try_from(input.read_u32::<LE>()?)?;
 
-        let filename = parse_bytes(&filename_bytes)?;
-        let description = parse_bytes(&description_bytes)?;
-
         Ok(AIErrorInfo {
             filename,
             line_number,
@@ -109,24 +120,52 @@ impl AIErrorInfo {
 
     /// Write AI error information to an output stream.
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
-        // TODO support non UTF8 encoding
-        let mut filename_bytes = [0; 257];
-        filename_bytes[..self.filename.len()].copy_from_slice(self.filename.as_bytes());
-        output.write_all(&filename_bytes)?;
-
+        write_fixed_str(&mut output, &self.filename, &mut [0; 257])?;
         output.write_i32::<LE>(self.line_number)?;
-
-        let mut description_bytes = [0; 128];
-        description_bytes[..self.description.len()].copy_from_slice(self.description.as_bytes());
-        output.write_all(&description_bytes)?;
-
+        write_fixed_str(&mut output, &self.description, &mut [0; 128])?;
         output.write_u32::<LE>(self.error_code.into())?;
 
         Ok(())
     }
+
+    /// Get all the strings in the error information, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        [&mut self.filename, &mut self.description].into_iter()
+    }
 }
 
-#[derive(Debug, Clone)]
+/// The kinds of AI files that can be embedded for each player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AIScriptKind {
+    /// A build list, used by Age of Empires 1 AIs.
+    BuildList,
+    /// A city plan, used by Age of Empires 1 AIs.
+    CityPlan,
+    /// An AI rules script, containing the strategy of the AI.
+    Rules,
+}
+
+impl AIScriptKind {
+    /// All kinds of AI files, in the order they are stored in.
+    pub const ALL: [AIScriptKind; 3] = [Self::BuildList, Self::CityPlan, Self::Rules];
+
+    /// Get the file extension that the game uses for this kind of AI file.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::BuildList => "ai",
+            Self::CityPlan => "cty",
+            Self::Rules => "per",
+        }
+    }
+
+    /// Can this kind of AI file be stored in scenarios of the given version?
+    pub fn is_supported(self, version: &VersionBundle) -> bool {
+        self != Self::Rules || version.supports_ai_rules()
+    }
+}
+
+/// An AI file embedded in a scenario.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AIFile {
     filename: String,
     content: String,
@@ -135,12 +174,8 @@ pub struct AIFile {
 impl AIFile {
     /// Read an embedded AI file from an input stream.
     pub fn read_from(mut input: impl Read) -> Result<Self> {
-        let filename = input
-            .read_u32_length_prefixed_str()?
-            .expect("missing ai file name");
-        let content = input
-            .read_u32_length_prefixed_str()?
-            .expect("empty ai file?");
+        let filename = input.read_u32_length_prefixed_str()?.unwrap_or_default();
+        let content = input.read_u32_length_prefixed_str()?.unwrap_or_default();
 
         Ok(Self { filename, content })
     }
@@ -151,6 +186,29 @@ impl AIFile {
         write_i32_str(&mut output, &self.content)?;
         Ok(())
     }
+
+    /// Create an AI file with the given file name and script.
+    pub fn new(filename: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            filename: filename.into(),
+            content: content.into(),
+        }
+    }
+
+    /// Get the file name of this AI file.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Get the script in this AI file.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Get all the strings in this AI file, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        [&mut self.filename, &mut self.content].into_iter()
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -160,6 +218,11 @@ pub struct AIInfo {
 }
 
 impl AIInfo {
+    /// Does this contain any embedded AI files?
+    pub(crate) fn has_files(&self) -> bool {
+        !self.files.is_empty()
+    }
+
     pub fn read_from(mut input: impl Read) -> Result<Option<Self>> {
         let has_ai_files = input.read_u32::<LE>()? != 0;
         let has_error = input.read_u32::<LE>()? != 0;
@@ -193,7 +256,8 @@ impl AIInfo {
             output.write_u32::<LE>(0)?;
         }
 
-        if !self.files.is_empty() {
+        // The file count is read whenever there are files or error information.
+        if !self.files.is_empty() || self.error.is_some() {
             output.write_u32::<LE>(self.files.len() as u32)?;
             for file in &self.files {
                 file.write_to(&mut output)?;
@@ -202,4 +266,209 @@ impl AIInfo {
 
         Ok(())
     }
+
+    /// Get the embedded AI files.
+    pub(crate) fn files(&self) -> &[AIFile] {
+        &self.files
+    }
+
+    /// Get the embedded AI files, mutably.
+    pub(crate) fn files_mut(&mut self) -> &mut Vec<AIFile> {
+        &mut self.files
+    }
+
+    /// Get the strings in the embedded AI files and the error information, for converting them
+    /// between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.error
+            .iter_mut()
+            .flat_map(AIErrorInfo::strings_mut)
+            .chain(self.files.iter_mut().flat_map(AIFile::strings_mut))
+    }
+}
+
+pub(crate) fn player_script(format: &SCXFormat, player: u8, kind: AIScriptKind) -> Option<AIFile> {
+    let index = player_index(format, player)?;
+    format.tribe_scen.base.ai_script(index, kind)
+}
+
+pub(crate) fn set_player_script(
+    format: &mut SCXFormat,
+    version: &VersionBundle,
+    player: u8,
+    kind: AIScriptKind,
+    file: Option<AIFile>,
+) -> std::result::Result<Option<AIFile>, AIError> {
+    let index = player_index(format, player).ok_or(AIError::InvalidPlayer(player))?;
+    if file.is_some() && !kind.is_supported(version) {
+        return Err(AIError::Unsupported(LostInformation::AIFiles));
+    }
+    Ok(format.tribe_scen.base.set_ai_script(index, kind, file))
+}
+
+pub(crate) fn files(format: &SCXFormat) -> &[AIFile] {
+    format.ai_info.as_ref().map_or(&[], AIInfo::files)
+}
+
+/// Add an embedded AI file, replacing the one with the same file name. File names are compared
+/// case-insensitively, like on Windows.
+pub(crate) fn set_file(
+    format: &mut SCXFormat,
+    format_version: SCXVersion,
+    file: AIFile,
+) -> std::result::Result<Option<AIFile>, AIError> {
+    if !format_version.supports_ai_files() {
+        return Err(AIError::Unsupported(LostInformation::AIFiles));
+    }
+    let files = format
+        .ai_info
+        .get_or_insert_with(Default::default)
+        .files_mut();
+    match files
+        .iter_mut()
+        .find(|existing| existing.filename.eq_ignore_ascii_case(&file.filename))
+    {
+        Some(existing) => Ok(Some(std::mem::replace(existing, file))),
+        None => {
+            files.push(file);
+            Ok(None)
+        }
+    }
+}
+
+pub(crate) fn remove_file(format: &mut SCXFormat, filename: &str) -> Option<AIFile> {
+    let files = format.ai_info.as_mut()?.files_mut();
+    let index = files
+        .iter()
+        .position(|file| file.filename.eq_ignore_ascii_case(filename))?;
+    Some(files.remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AIErrorCode, AIErrorInfo, AIFile, AIScriptKind};
+    use crate::{AIError, Error, Scenario};
+    use encoding_rs::WINDOWS_1251;
+    use std::fs::File;
+
+    #[test]
+    fn replace_and_remove_scripts() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/A New Emporer.scn")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let rules = scen.player_ai_script(2, AIScriptKind::Rules).unwrap();
+        assert_eq!(rules.filename(), "Passive");
+
+        let replacement = AIFile::new("Aggressive", "(defrule (true) => (attack-now))");
+        let previous = scen.set_player_ai_script(2, AIScriptKind::Rules, replacement.clone())?;
+        assert_eq!(previous, Some(rules));
+        let removed = scen.remove_player_ai_script(6, AIScriptKind::BuildList)?;
+        assert_eq!(removed.unwrap().filename(), "Cavalry Bronze");
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(
+            scen.player_ai_script(2, AIScriptKind::Rules),
+            Some(replacement)
+        );
+        assert_eq!(scen.player_ai_script(6, AIScriptKind::BuildList), None);
+
+        let mut f = File::open("test/scenarios/Dawn of a New Age.scn")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert!(matches!(
+            scen.set_player_ai_script(1, AIScriptKind::Rules, AIFile::new("a", "b")),
+            Err(AIError::Unsupported(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn bundled_files() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.ai_files().len(), 2);
+        let previous = scen.set_ai_file(AIFile::new("CONST.PER2", "(defconst x 1)"))?;
+        assert_eq!(previous.unwrap().filename(), "const.per2");
+        assert!(scen
+            .remove_ai_file("testHarnessResourceHandling.per2")
+            .is_some());
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(
+            scen.ai_files(),
+            &[AIFile::new("CONST.PER2", "(defconst x 1)")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn script_encoding() -> anyhow::Result<()> {
+        let script = "; Стратегия для игрока 2\n(defrule (true) => (disable-self))";
+        let mut f = File::open("test/scenarios/Jeremiah Johnson (Update).scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.set_encoding(WINDOWS_1251);
+        scen.set_player_ai_script(2, AIScriptKind::Rules, AIFile::new("Стратегия", script))?;
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.encoding(), WINDOWS_1251);
+        let file = scen.player_ai_script(2, AIScriptKind::Rules).unwrap();
+        assert_eq!(file.filename(), "Стратегия");
+        assert_eq!(file.content(), script);
+        Ok(())
+    }
+
+    #[test]
+    fn error_info_strings_must_fit() -> anyhow::Result<()> {
+        let mut info = AIErrorInfo {
+            filename: "ai.per".to_string(),
+            line_number: 3,
+            description: "x".repeat(127),
+            error_code: AIErrorCode::MissingArrow,
+        };
+        let mut out = vec![];
+        info.write_to(&mut out)?;
+        assert_eq!(out.len(), 257 + 4 + 128 + 4);
+        let read = AIErrorInfo::read_from(&out[..])?;
+        assert_eq!(read.filename, "ai.per");
+        assert_eq!(read.description, info.description);
+
+        info.description.push('x');
+        assert!(matches!(
+            info.write_to(&mut vec![]),
+            Err(Error::StringTooLongError(128, 127))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn remove_last_file_with_error_info() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.format.ai_info.as_mut().unwrap().error = Some(AIErrorInfo {
+            filename: "const.per2".to_string(),
+            line_number: 1,
+            description: "missing arrow".to_string(),
+            error_code: AIErrorCode::MissingArrow,
+        });
+        let filenames: Vec<_> = scen
+            .ai_files()
+            .iter()
+            .map(|file| file.filename().to_string())
+            .collect();
+        for filename in filenames {
+            assert!(scen.remove_ai_file(&filename).is_some());
+        }
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert!(scen.ai_files().is_empty());
+        let error = scen.format.ai_info.unwrap().error.unwrap();
+        assert_eq!(error.description, "missing arrow");
+        Ok(())
+    }
 }
@@ -2,9 +2,13 @@
 
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use rgb::RGBA8;
+use rgb::{RGB8, RGBA8};
+use std::collections::BTreeMap;
 use std::io::{Read, Write};
 
+/// The number of palette entries stored with every bitmap, even if it does not use a palette.
+const PALETTE_SIZE: usize = 256;
+
 /// Bitmap header info.
 #[derive(Debug, Default, Clone)]
 pub struct BitmapInfo {
@@ -23,6 +27,30 @@ pub struct BitmapInfo {
 }
 
 impl BitmapInfo {
+    /// Create a header for an uncompressed, bottom-up bitmap.
+    fn new(width: u32, height: u32, bit_count: u16, mut colors: Vec<RGBA8>) -> Self {
+        let clr_used = if bit_count <= 8 {
+            colors.len() as u32
+        } else {
+            0
+        };
+        colors.resize(PALETTE_SIZE, RGBA8::default());
+        Self {
+            size: 40,
+            width: width as i32,
+            height: height as i32,
+            planes: 1,
+            bit_count,
+            compression: 0,
+            size_image: (stride(width, bit_count) * height as usize) as u32,
+            xpels_per_meter: 0,
+            ypels_per_meter: 0,
+            clr_used,
+            clr_important: 0,
+            colors,
+        }
+    }
+
     /// Read a bitmap header info structure from a byte stream.
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let mut bitmap = BitmapInfo {
@@ -40,10 +68,11 @@ impl BitmapInfo {
             ..Default::default()
         };
 
-        for _ in 0..256 {
-            let r = input.read_u8()?;
-            let g = input.read_u8()?;
+        // Palette entries are stored in BGR order.
+        for _ in 0..PALETTE_SIZE {
             let b = input.read_u8()?;
+            let g = input.read_u8()?;
+            let r = input.read_u8()?;
             let a = input.read_u8()?;
             bitmap.colors.push(RGBA8 { r, g, b, a });
         }
@@ -51,9 +80,8 @@ impl BitmapInfo {
         Ok(bitmap)
     }
 
-    #[allow(unused)]
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
-        assert_eq!(self.colors.len(), 256);
+        assert_eq!(self.colors.len(), PALETTE_SIZE);
 
         output.write_u32::<LE>(self.size)?;
         output.write_i32::<LE>(self.width)?;
@@ -67,9 +95,9 @@ impl BitmapInfo {
         output.write_u32::<LE>(self.clr_used)?;
         output.write_u32::<LE>(self.clr_important)?;
         for color in &self.colors {
-            output.write_u8(color.r)?;
-            output.write_u8(color.g)?;
             output.write_u8(color.b)?;
+            output.write_u8(color.g)?;
+            output.write_u8(color.r)?;
             output.write_u8(color.a)?;
         }
 
@@ -77,8 +105,15 @@ impl BitmapInfo {
     }
 }
 
+/// Get the number of bytes in a row of pixels. Rows are padded to 4 bytes.
+fn stride(width: u32, bit_count: u16) -> usize {
+    ((width as usize * usize::from(bit_count) + 31) / 32) * 4
+}
+
 /// A Genie-style bitmap file: a typical BMP with some metadata.
-#[derive(Debug)]
+///
+/// AoE1 and AoE2 scenarios can contain one of these, shown on the instructions screen.
+#[derive(Debug, Clone)]
 pub struct Bitmap {
     own_memory: u32,
     width: u32,
@@ -89,6 +124,166 @@ pub struct Bitmap {
 }
 
 impl Bitmap {
+    /// Create a bitmap with 24 bits per pixel from RGB pixels, stored row by row from the top
+    /// left.
+    ///
+    /// Returns None if the width or height is 0: scenarios can not store empty bitmaps.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the number of pixels does not match the width and height.
+    pub fn from_rgb(width: u32, height: u32, pixels: &[RGB8]) -> Option<Self> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        assert_eq!(pixels.len(), (width * height) as usize);
+        let stride = stride(width, 24);
+        let mut data = vec![0; stride * height as usize];
+        for (row, out) in pixels
+            .chunks_exact(width as usize)
+            .rev()
+            .zip(data.chunks_exact_mut(stride))
+        {
+            for (pixel, out) in row.iter().zip(out.chunks_exact_mut(3)) {
+                out.copy_from_slice(&[pixel.b, pixel.g, pixel.r]);
+            }
+        }
+        Some(Self::new(
+            width,
+            height,
+            BitmapInfo::new(width, height, 24, vec![]),
+            data,
+        ))
+    }
+
+    /// Create a bitmap with 8 bits per pixel from RGB pixels, stored row by row from the top left.
+    ///
+    /// If the image has more than 256 different colours, it is quantized to a palette of 256
+    /// colours using median cut.
+    ///
+    /// Returns None if the width or height is 0: scenarios can not store empty bitmaps.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the number of pixels does not match the width and height.
+    pub fn from_rgb_palettized(width: u32, height: u32, pixels: &[RGB8]) -> Option<Self> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        assert_eq!(pixels.len(), (width * height) as usize);
+        let (palette, indices) = quantize(pixels);
+        let stride = stride(width, 8);
+        let mut data = vec![0; stride * height as usize];
+        for (row, out) in indices
+            .chunks_exact(width as usize)
+            .rev()
+            .zip(data.chunks_exact_mut(stride))
+        {
+            out[..row.len()].copy_from_slice(row);
+        }
+        let colors = palette
+            .into_iter()
+            .map(|color| RGBA8::new(color.r, color.g, color.b, 0))
+            .collect();
+        Some(Self::new(
+            width,
+            height,
+            BitmapInfo::new(width, height, 8, colors),
+            data,
+        ))
+    }
+
+    fn new(width: u32, height: u32, info: BitmapInfo, pixels: Vec<u8>) -> Self {
+        Self {
+            own_memory: 1,
+            width,
+            height,
+            orientation: 0xFFFF,
+            info,
+            pixels,
+        }
+    }
+
+    /// Get the width of the bitmap in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the bitmap in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the number of bits used for every pixel.
+    pub fn bit_count(&self) -> u16 {
+        self.info.bit_count
+    }
+
+    /// Does this bitmap store palette indices instead of colours?
+    pub fn is_palettized(&self) -> bool {
+        self.info.bit_count <= 8
+    }
+
+    /// Decode the bitmap to RGB pixels, stored row by row from the top left.
+    ///
+    /// Supports uncompressed bitmaps with 1, 4, 8, 24, or 32 bits per pixel. Returns None for
+    /// other formats.
+    pub fn to_rgb(&self) -> Option<Vec<RGB8>> {
+        let bit_count = self.info.bit_count;
+        if self.info.compression != 0 || !matches!(bit_count, 1 | 4 | 8 | 24 | 32) {
+            return None;
+        }
+        let stride = stride(self.width, bit_count);
+        let width = self.width as usize;
+        let mut rows: Vec<&[u8]> = self
+            .pixels
+            .chunks_exact(stride)
+            .take(self.height as usize)
+            .collect();
+        if rows.len() != self.height as usize {
+            return None;
+        }
+        // Bitmaps with a positive height are stored from the bottom up.
+        if self.info.height > 0 {
+            rows.reverse();
+        }
+
+        let mut pixels = Vec::with_capacity(width * rows.len());
+        for row in rows {
+            match bit_count {
+                24 | 32 => {
+                    let bytes_per_pixel = usize::from(bit_count / 8);
+                    for pixel in row.chunks_exact(bytes_per_pixel).take(width) {
+                        pixels.push(RGB8::new(pixel[2], pixel[1], pixel[0]));
+                    }
+                }
+                _ => {
+                    let pixels_per_byte = 8 / usize::from(bit_count);
+                    let mask = (1u16 << bit_count) - 1;
+                    for x in 0..width {
+                        let byte = row[x / pixels_per_byte];
+                        let shift = 8 - usize::from(bit_count) * (x % pixels_per_byte + 1);
+                        let index = (u16::from(byte) >> shift) & mask;
+                        let color = self.info.colors[usize::from(index)];
+                        pixels.push(RGB8::new(color.r, color.g, color.b));
+                    }
+                }
+            }
+        }
+        Some(pixels)
+    }
+
+    /// Convert the bitmap to one with 8 bits per pixel, quantizing the colours if necessary.
+    ///
+    /// Returns None if the bitmap can not be decoded.
+    pub fn to_palettized(&self) -> Option<Self> {
+        if self.info.bit_count == 8 {
+            return Some(self.clone());
+        }
+        let pixels = self.to_rgb()?;
+        Self::from_rgb_palettized(self.width, self.height, &pixels)
+    }
+
     pub fn read_from(mut input: impl Read) -> Result<Option<Self>> {
         let own_memory = input.read_u32::<LE>()?;
         let width = input.read_u32::<LE>()?;
@@ -97,8 +292,8 @@ impl Bitmap {
 
         if width > 0 && height > 0 {
             let info = BitmapInfo::read_from(&mut input)?;
-            let aligned_width = height * ((width + 3) & !3);
-            let mut pixels = vec![0u8; aligned_width as usize];
+            let size = height as usize * stride(width, info.bit_count);
+            let mut pixels = vec![0u8; size];
             input.read_exact(&mut pixels)?;
             Ok(Some(Bitmap {
                 own_memory,
@@ -113,7 +308,6 @@ impl Bitmap {
         }
     }
 
-    #[allow(unused)]
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
         output.write_u32::<LE>(self.own_memory)?;
         output.write_u32::<LE>(self.width)?;
@@ -133,3 +327,256 @@ impl Bitmap {
         Ok(())
     }
 }
+
+/// A group of colours that will share a palette entry.
+struct ColorBox {
+    /// The colours in the box, with the number of pixels that use them.
+    colors: Vec<(RGB8, u32)>,
+}
+
+impl ColorBox {
+    fn weight(&self) -> u64 {
+        self.colors.iter().map(|&(_, count)| u64::from(count)).sum()
+    }
+
+    /// Get the channel with the largest range of values, and the size of that range.
+    fn widest_channel(&self) -> (usize, u8) {
+        let mut min = [u8::MAX; 3];
+        let mut max = [u8::MIN; 3];
+        for (color, _) in &self.colors {
+            for (channel, value) in [color.r, color.g, color.b].into_iter().enumerate() {
+                min[channel] = min[channel].min(value);
+                max[channel] = max[channel].max(value);
+            }
+        }
+        (0..3)
+            .map(|channel| (channel, max[channel] - min[channel]))
+            .max_by_key(|&(_, range)| range)
+            .unwrap_or((0, 0))
+    }
+
+    /// Split the box at the weighted median of its widest channel.
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (channel, _) = self.widest_channel();
+        let key = |color: &RGB8| [color.r, color.g, color.b][channel];
+        self.colors.sort_by_key(|(color, _)| key(color));
+        let half = self.weight() / 2;
+        let mut total = 0;
+        let mut at = 1;
+        for (index, &(_, count)) in self.colors.iter().enumerate() {
+            total += u64::from(count);
+            if total >= half {
+                at = index + 1;
+                break;
+            }
+        }
+        let at = at.clamp(1, self.colors.len() - 1);
+        let rest = self.colors.split_off(at);
+        (self, ColorBox { colors: rest })
+    }
+
+    /// Get the average colour of the box, weighted by pixel count.
+    fn average(&self) -> RGB8 {
+        let weight = self.weight().max(1);
+        let mut sum = [0u64; 3];
+        for (color, count) in &self.colors {
+            sum[0] += u64::from(color.r) * u64::from(*count);
+            sum[1] += u64::from(color.g) * u64::from(*count);
+            sum[2] += u64::from(color.b) * u64::from(*count);
+        }
+        RGB8::new(
+            (sum[0] / weight) as u8,
+            (sum[1] / weight) as u8,
+            (sum[2] / weight) as u8,
+        )
+    }
+}
+
+/// Reduce an image to at most 256 colours. Returns the palette and the palette index of every
+/// pixel.
+///
+/// Colours are visited in sorted order, so the same image always gets the same palette.
+fn quantize(pixels: &[RGB8]) -> (Vec<RGB8>, Vec<u8>) {
+    let mut histogram = BTreeMap::new();
+    for pixel in pixels {
+        *histogram.entry(*pixel).or_insert(0u32) += 1;
+    }
+
+    let palette: Vec<RGB8> = if histogram.len() <= PALETTE_SIZE {
+        histogram.keys().copied().collect()
+    } else {
+        let mut boxes = vec![ColorBox {
+            colors: histogram
+                .iter()
+                .map(|(&color, &count)| (color, count))
+                .collect(),
+        }];
+        while boxes.len() < PALETTE_SIZE {
+            let next = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, color_box)| color_box.colors.len() > 1)
+                .max_by_key(|(_, color_box)| {
+                    color_box.weight() * u64::from(color_box.widest_channel().1)
+                })
+                .map(|(index, _)| index);
+            let index = match next {
+                Some(index) => index,
+                None => break,
+            };
+            let (left, right) = boxes.swap_remove(index).split();
+            boxes.push(left);
+            boxes.push(right);
+        }
+        boxes.iter().map(ColorBox::average).collect()
+    };
+
+    let distance = |a: RGB8, b: RGB8| {
+        let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2);
+        d(a.r, b.r) + d(a.g, b.g) + d(a.b, b.b)
+    };
+    let lookup: BTreeMap<RGB8, u8> = histogram
+        .keys()
+        .map(|&color| {
+            let index = (0..palette.len())
+                .min_by_key(|&index| distance(color, palette[index]))
+                .unwrap_or(0);
+            (color, index as u8)
+        })
+        .collect();
+    let indices = pixels.iter().map(|pixel| lookup[pixel]).collect();
+    (palette, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scenario;
+    use std::fs::File;
+
+    fn gradient(width: u32, height: u32) -> Vec<RGB8> {
+        let mut pixels = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(RGB8::new((x * 8) as u8, (y * 8) as u8, ((x + y) * 4) as u8));
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn rgb_round_trip() -> anyhow::Result<()> {
+        let pixels = gradient(13, 7);
+        let bitmap = Bitmap::from_rgb(13, 7, &pixels).unwrap();
+        assert!(!bitmap.is_palettized());
+        let mut out = vec![];
+        bitmap.write_to(&mut out)?;
+        let bitmap = Bitmap::read_from(&out[..])?.unwrap();
+        assert_eq!(bitmap.to_rgb(), Some(pixels));
+        Ok(())
+    }
+
+    #[test]
+    fn palettized_round_trip() -> anyhow::Result<()> {
+        // 5×3 = 15 colours, few enough to keep them all.
+        let pixels = gradient(5, 3);
+        let bitmap = Bitmap::from_rgb_palettized(5, 3, &pixels).unwrap();
+        assert_eq!(bitmap.bit_count(), 8);
+        let mut out = vec![];
+        bitmap.write_to(&mut out)?;
+        let bitmap = Bitmap::read_from(&out[..])?.unwrap();
+        assert_eq!(bitmap.to_rgb(), Some(pixels));
+        Ok(())
+    }
+
+    #[test]
+    fn quantize_many_colors() {
+        let pixels = gradient(32, 32);
+        let bitmap = Bitmap::from_rgb(32, 32, &pixels)
+            .unwrap()
+            .to_palettized()
+            .unwrap();
+        assert_eq!(bitmap.bit_count(), 8);
+        let decoded = bitmap.to_rgb().unwrap();
+        for (original, quantized) in pixels.iter().zip(decoded.iter()) {
+            let error = |a: u8, b: u8| (i32::from(a) - i32::from(b)).abs();
+            assert!(error(original.r, quantized.r) <= 24);
+            assert!(error(original.g, quantized.g) <= 24);
+            assert!(error(original.b, quantized.b) <= 24);
+        }
+    }
+
+    #[test]
+    fn quantize_is_reproducible() -> anyhow::Result<()> {
+        let pixels = gradient(32, 32);
+        let write = || -> anyhow::Result<Vec<u8>> {
+            let mut out = vec![];
+            Bitmap::from_rgb_palettized(32, 32, &pixels)
+                .unwrap()
+                .write_to(&mut out)?;
+            Ok(out)
+        };
+        assert_eq!(write()?, write()?);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_scenario_bitmap() -> anyhow::Result<()> {
+        let scen = Scenario::read_from(File::open("test/scenarios/A New Emporer.scn")?)?;
+        let bitmap = scen.bitmap().unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (628, 278));
+        assert!(bitmap.is_palettized());
+        let pixels = bitmap.to_rgb().unwrap();
+        assert_eq!(pixels.len(), 628 * 278);
+        Ok(())
+    }
+
+    #[test]
+    fn set_scenario_bitmap() -> anyhow::Result<()> {
+        let pixels = gradient(32, 32);
+
+        let mut scen = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        scen.set_bitmap_rgb(32, 32, &pixels);
+        assert!(scen.bitmap().unwrap().is_palettized());
+
+        let mut scen = Scenario::read_from(File::open("test/scenarios/layertest.aoe2scenario")?)?;
+        scen.set_bitmap_rgb(32, 32, &pixels);
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.bitmap().unwrap().to_rgb(), Some(pixels.clone()));
+
+        // Writing to an older version quantizes the picture.
+        let mut out = vec![];
+        scen.write_to_version(&mut out, &crate::VersionBundle::hd_edition())?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert!(scen.bitmap().unwrap().is_palettized());
+        Ok(())
+    }
+
+    #[test]
+    fn empty_scenario_bitmap() -> anyhow::Result<()> {
+        assert!(Bitmap::from_rgb(0, 5, &[]).is_none());
+        assert!(Bitmap::from_rgb_palettized(5, 0, &[]).is_none());
+
+        for path in [
+            "test/scenarios/Age of Heroes b1-3-5.scx",
+            "test/scenarios/layertest.aoe2scenario",
+        ] {
+            let mut scen = Scenario::read_from(File::open(path)?)?;
+            let num_objects = scen.objects().count();
+            scen.set_bitmap_rgb(32, 32, &gradient(32, 32));
+            scen.set_bitmap_rgb(0, 5, &[]);
+            assert!(scen.bitmap().is_none());
+            scen.set_bitmap_rgb(5, 0, &[]);
+            assert!(scen.bitmap().is_none());
+
+            let mut out = vec![];
+            scen.write_to(&mut out)?;
+            let scen = Scenario::read_from(&out[..])?;
+            assert!(scen.bitmap().is_none());
+            assert_eq!(scen.objects().count(), num_objects);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use super::{ConversionNote, ConvertError, IdMapConverter};
+use crate::{Scenario, VersionBundle};
+use std::collections::BTreeMap;
+
+/// Convert an AoE1 or Rise of Rome scenario to an AoC one.
+///
+/// AoE1 units, buildings, terrains, and civilizations are replaced by their closest AoC
+/// equivalents. The built-in table is available separately as [`IdMapConverter::aoe_to_aoc`],
+/// and can be replaced or extended, for example to target a mod that adds AoE1 units to AoE2.
+///
+/// IDs that are not in the table are left unchanged, and reported as
+/// [`ConversionNote::UnmappedUnit`], [`ConversionNote::UnmappedTerrain`], and
+/// [`ConversionNote::UnmappedCivilization`]. IDs that AoC inherited from AoE1 are listed in the
+/// table with the same ID on both sides, so they are not reported.
+///
+/// ## Usage
+///
+/// ```rust,no_run
+/// # use genie_scx::Scenario;
+/// use genie_scx::convert::AoEToAoC;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut scenario = Scenario::read_from(std::fs::File::open("scenario.scn")?)?;
+/// # let output = std::fs::File::create("scenario.scx")?;
+/// let notes = AoEToAoC::default().convert(&mut scenario)?;
+/// for note in notes {
+///     println!("{}", note);
+/// }
+/// scenario.write_to(output)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AoEToAoC {
+    version: VersionBundle,
+    ids: IdMapConverter,
+}
+
+impl Default for AoEToAoC {
+    fn default() -> Self {
+        Self::new(VersionBundle::aoc())
+    }
+}
+
+impl AoEToAoC {
+    /// Create a converter that targets the given game versions, using the built-in table.
+    pub fn new(version: VersionBundle) -> Self {
+        Self {
+            version,
+            ids: IdMapConverter::aoe_to_aoc(),
+        }
+    }
+
+    /// Use a different ID table.
+    pub fn with_ids(mut self, ids: IdMapConverter) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Convert a scenario in-place.
+    pub fn convert(&self, scen: &mut Scenario) -> Result<Vec<ConversionNote>, ConvertError> {
+        if !scen.version().is_aoe() {
+            return Err(ConvertError::InvalidVersion);
+        }
+
+        let mut notes = vec![];
+        self.note_unmapped(scen, &mut notes);
+        self.ids.note_changes(scen, &mut notes);
+        self.ids.convert(scen)?;
+
+        scen.version = self.version.clone();
+        notes.extend(
+            scen.lost_information(&self.version)
+                .into_iter()
+                .map(ConversionNote::Lost),
+        );
+
+        Ok(notes)
+    }
+
+    /// Report the objects, tiles, and player civilizations that are not in the ID table.
+    fn note_unmapped(&self, scen: &Scenario, notes: &mut Vec<ConversionNote>) {
+        let mut units = BTreeMap::new();
+        for object in scen.objects() {
+            if self.ids.unit(object.object_type).is_none() {
+                *units.entry(u16::from(object.object_type)).or_insert(0) += 1;
+            }
+        }
+        for (unit_type, count) in units {
+            notes.push(ConversionNote::UnmappedUnit {
+                unit_type: unit_type.into(),
+                count,
+            });
+        }
+
+        let mut terrains = BTreeMap::new();
+        for tile in scen.map().tiles() {
+            if self.ids.terrain(tile.terrain).is_none() {
+                *terrains.entry(tile.terrain).or_insert(0) += 1;
+            }
+        }
+        for (terrain, count) in terrains {
+            notes.push(ConversionNote::UnmappedTerrain { terrain, count });
+        }
+
+        let civilizations = scen.format.tribe_scen.player_civilizations();
+        for (player, (active, civilization)) in (1..).zip(civilizations) {
+            if active && !self.ids.has_civ(civilization) {
+                notes.push(ConversionNote::UnmappedCivilization {
+                    player,
+                    civilization,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn convert_ror_to_aoc() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/A New Emporer.scn")?;
+        let original = Scenario::read_from(&mut f)?;
+        let mut scen = original.clone();
+        let converter = AoEToAoC::default();
+        let notes = converter.convert(&mut scen)?;
+
+        let ids = IdMapConverter::aoe_to_aoc();
+        for (old, new) in original.objects().zip(scen.objects()) {
+            let expected = ids.unit(old.object_type).unwrap_or(old.object_type);
+            assert_eq!(new.object_type, expected);
+            if ids.unit(old.object_type).is_none() {
+                assert!(notes.iter().any(|note| matches!(
+                    note,
+                    ConversionNote::UnmappedUnit { unit_type, .. } if *unit_type == old.object_type
+                )));
+            }
+        }
+        assert!(!notes.iter().any(|note| matches!(
+            note,
+            ConversionNote::ApproximatedUnit { from, to, .. } if from == to
+        )));
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert!(scen.version().is_aoc());
+        assert_eq!(scen.objects().count(), original.objects().count());
+        Ok(())
+    }
+
+    #[test]
+    fn convert_civilizations() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/ The Destruction of Rome.scn")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let mut ids = IdMapConverter::new();
+        for civ in 0..=16 {
+            ids.map_civ(civ.into(), 1.into());
+        }
+        AoEToAoC::default().with_ids(ids).convert(&mut scen)?;
+        assert!(scen
+            .format
+            .tribe_scen
+            .player_civilizations()
+            .all(|(_, civilization)| civilization == 1));
+        Ok(())
+    }
+
+    #[test]
+    fn convert_rejects_aoc() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert!(matches!(
+            AoEToAoC::default().convert(&mut scen),
+            Err(ConvertError::InvalidVersion)
+        ));
+        Ok(())
+    }
+}
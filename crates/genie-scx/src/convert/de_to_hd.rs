@@ -0,0 +1,475 @@
+use super::{
+    remove_trigger_items, ConversionNote, ConvertError, IdMapConverter, DE_ENABLE_DISABLE_OBJECT,
+    DE_ENABLE_DISABLE_TECHNOLOGY, HD_DISABLE_TECH, HD_DISABLE_UNIT, HD_ENABLE_TECH, HD_ENABLE_UNIT,
+};
+#[cfg(feature = "validate")]
+use crate::validate::{has_unit_type, is_unknown_tech, is_unknown_unit_type};
+use crate::{Scenario, VersionBundle};
+#[cfg(feature = "validate")]
+use genie_dat::DatFile;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// The last condition type that has the same meaning in AoC, HD Edition, and Definitive Edition
+/// (Difficulty Level). HD Edition and DE both added new types after it, but with different IDs.
+const MAX_CONDITION_TYPE: i32 = 19;
+/// The last effect type that has the same meaning in AoC, HD Edition, and Definitive Edition
+/// (Stop Unit).
+const MAX_EFFECT_TYPE: i32 = 29;
+/// The last unit type that AoC defines.
+const MAX_AOC_UNIT_TYPE: u16 = 865;
+/// The last terrain that AoC defines.
+const MAX_AOC_TERRAIN: u8 = 41;
+/// The last unit type that HD Edition defines. HD Edition reserves some of the later IDs, but
+/// DE uses them for its own units.
+const MAX_HD_UNIT_TYPE: u16 = 1224;
+/// The last terrain that HD Edition defines. The later slots are placeholders.
+const MAX_HD_TERRAIN: u8 = 67;
+/// The number of properties stored for conditions before Definitive Edition.
+const NUM_CONDITION_PROPERTIES: usize = 18;
+/// The number of properties stored for effects before Definitive Edition.
+const NUM_EFFECT_PROPERTIES: usize = 24;
+
+/// Convert a Definitive Edition scenario to one that can be played in HD Edition or AoC.
+///
+/// Units and terrains that only exist in DE are replaced by similar ones, and layered terrain is
+/// painted onto the base terrain. DE's Enable/Disable Object and Enable/Disable Technology
+/// effects are replaced by their HD Edition equivalents when converting to HD Edition, and the
+/// other trigger conditions and effects that the older games do not know are removed. Triggers
+/// that lose a condition are disabled, because they would otherwise fire too early. Everything
+/// that had to be dropped or approximated is returned as a list of [`ConversionNote`]s.
+///
+/// The ID table is available separately as [`IdMapConverter::de_to_hd`], and can be replaced
+/// to support other mods.
+///
+/// DE adds many units and terrains that are not in the built-in table. [`DEToHD::convert`]
+/// leaves those unchanged, and reports the ones that the target game does not have as
+/// [`ConversionNote::UnmappedUnit`] and [`ConversionNote::UnmappedTerrain`]. Use
+/// [`DEToHD::convert_with_data`] with the data file of the target game to remove objects,
+/// terrains, and trigger references that the target game does not define instead.
+///
+/// ## Usage
+///
+/// ```rust,no_run
+/// # use genie_scx::Scenario;
+/// use genie_scx::convert::DEToHD;
+/// use genie_scx::VersionBundle;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut scenario = Scenario::read_from(std::fs::File::open("scenario.aoe2scenario")?)?;
+/// # let output = std::fs::File::create("scenario.scx")?;
+/// let notes = DEToHD::new(VersionBundle::aoc()).convert(&mut scenario)?;
+/// for note in notes {
+///     println!("{}", note);
+/// }
+/// scenario.write_to(output)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DEToHD {
+    version: VersionBundle,
+    ids: IdMapConverter,
+    fallback_terrain: u8,
+}
+
+impl Default for DEToHD {
+    fn default() -> Self {
+        Self::new(VersionBundle::hd_edition())
+    }
+}
+
+impl DEToHD {
+    /// Create a converter that targets the given game versions, using the built-in ID table.
+    pub fn new(version: VersionBundle) -> Self {
+        Self {
+            version,
+            ids: IdMapConverter::de_to_hd(),
+            fallback_terrain: 0,
+        }
+    }
+
+    /// Use a different ID table.
+    pub fn with_ids(mut self, ids: IdMapConverter) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Set the terrain that replaces terrains which do not exist in the target game, when
+    /// converting with a data file. Defaults to 0 (Grass).
+    pub fn with_fallback_terrain(mut self, terrain: u8) -> Self {
+        self.fallback_terrain = terrain;
+        self
+    }
+
+    /// Convert a scenario in-place.
+    pub fn convert(&self, scen: &mut Scenario) -> Result<Vec<ConversionNote>, ConvertError> {
+        let notes = self.convert_ids(scen, false)?;
+        Ok(self.finish(scen, notes))
+    }
+
+    /// Convert a scenario in-place, and remove anything that is not defined in the data file of
+    /// the target game.
+    #[cfg(feature = "validate")]
+    pub fn convert_with_data(
+        &self,
+        scen: &mut Scenario,
+        dat: &DatFile,
+    ) -> Result<Vec<ConversionNote>, ConvertError> {
+        let mut notes = self.convert_ids(scen, true)?;
+        self.remove_unknown(scen, dat, &mut notes);
+        Ok(self.finish(scen, notes))
+    }
+
+    /// Convert the terrain, object, and trigger IDs. Unmapped IDs are only reported if they will
+    /// not be removed using a data file afterwards.
+    fn convert_ids(
+        &self,
+        scen: &mut Scenario,
+        with_data: bool,
+    ) -> Result<Vec<ConversionNote>, ConvertError> {
+        if !scen.version().is_age2_de() {
+            return Err(ConvertError::InvalidVersion);
+        }
+
+        let mut notes = vec![];
+        flatten_layered_terrain(scen, &mut notes);
+        if !with_data {
+            self.note_unmapped(scen, &mut notes);
+        }
+        self.ids.note_changes(scen, &mut notes);
+        self.ids.convert(scen)?;
+
+        let is_hd_edition = self.version.is_hd_edition();
+        remove_trigger_items(
+            scen,
+            &mut notes,
+            |condition| condition.condition_type() <= MAX_CONDITION_TYPE,
+            |effect| {
+                effect.effect_type() <= MAX_EFFECT_TYPE
+                    || (is_hd_edition && is_enable_disable(effect.effect_type()))
+            },
+        );
+        if is_hd_edition {
+            convert_trigger_types(scen);
+        }
+        if let Some(triggers) = scen.triggers_mut() {
+            for trigger in triggers.triggers_unordered_mut() {
+                for condition in trigger.conditions_unordered_mut() {
+                    condition.resize_properties(NUM_CONDITION_PROPERTIES);
+                }
+                for effect in trigger.effects_unordered_mut() {
+                    effect.resize_properties(NUM_EFFECT_PROPERTIES);
+                }
+            }
+        }
+
+        Ok(notes)
+    }
+
+    /// Switch the scenario to the target version, and report what it can not store.
+    fn finish(&self, scen: &mut Scenario, mut notes: Vec<ConversionNote>) -> Vec<ConversionNote> {
+        scen.version = self.version.clone();
+        let lost = scen.lost_information(&self.version);
+        scen.format.tribe_scen.truncate_disabled(self.version.data);
+        notes.extend(lost.into_iter().map(ConversionNote::Lost));
+        notes
+    }
+
+    /// Report the objects and tiles that are not in the ID table, and that the target game does
+    /// not define.
+    fn note_unmapped(&self, scen: &Scenario, notes: &mut Vec<ConversionNote>) {
+        let (max_unit_type, max_terrain) = if self.version.is_hd_edition() {
+            (MAX_HD_UNIT_TYPE, MAX_HD_TERRAIN)
+        } else {
+            (MAX_AOC_UNIT_TYPE, MAX_AOC_TERRAIN)
+        };
+
+        let mut units = BTreeMap::new();
+        for object in scen.objects() {
+            let unit_type = u16::from(object.object_type);
+            if unit_type > max_unit_type && self.ids.unit(object.object_type).is_none() {
+                *units.entry(unit_type).or_insert(0) += 1;
+            }
+        }
+        for (unit_type, count) in units {
+            notes.push(ConversionNote::UnmappedUnit {
+                unit_type: unit_type.into(),
+                count,
+            });
+        }
+
+        let mut terrains = BTreeMap::new();
+        for tile in scen.map().tiles() {
+            if tile.terrain > max_terrain && self.ids.terrain(tile.terrain).is_none() {
+                *terrains.entry(tile.terrain).or_insert(0) += 1;
+            }
+        }
+        for (terrain, count) in terrains {
+            notes.push(ConversionNote::UnmappedTerrain { terrain, count });
+        }
+    }
+
+    /// Remove objects, terrains, trigger conditions and effects, and disabled IDs that are not
+    /// defined in a data file.
+    #[cfg(feature = "validate")]
+    fn remove_unknown(&self, scen: &mut Scenario, dat: &DatFile, notes: &mut Vec<ConversionNote>) {
+        let mut removed_objects = BTreeMap::new();
+        scen.spatial.clear();
+        for objects in scen.format.player_objects.iter_mut() {
+            objects.retain(|object| {
+                let known = has_unit_type(dat, object.object_type);
+                if !known {
+                    *removed_objects
+                        .entry(u16::from(object.object_type))
+                        .or_insert(0) += 1;
+                }
+                known
+            });
+        }
+        for (unit_type, count) in removed_objects {
+            notes.push(ConversionNote::RemovedObjects {
+                unit_type: unit_type.into(),
+                count,
+            });
+        }
+
+        let mut replaced_terrains = BTreeMap::new();
+        for tile in scen.map_mut().tiles_mut() {
+            if dat.get_terrain(tile.terrain).is_none() {
+                *replaced_terrains.entry(tile.terrain).or_insert(0) += 1;
+                tile.terrain = self.fallback_terrain;
+            }
+        }
+        for (from, count) in replaced_terrains {
+            notes.push(ConversionNote::ApproximatedTerrain {
+                from,
+                to: self.fallback_terrain,
+                count,
+            });
+        }
+
+        remove_trigger_items(
+            scen,
+            notes,
+            |condition| {
+                !is_unknown_unit_type(dat, condition.raw_unit_type())
+                    && !is_unknown_tech(dat, condition.technology_id())
+            },
+            |effect| {
+                !is_unknown_unit_type(dat, effect.raw_unit_type())
+                    && !is_unknown_tech(dat, effect.technology_id())
+            },
+        );
+
+        let removed = scen.format.tribe_scen.retain_disabled(
+            |tech| !is_unknown_tech(dat, tech),
+            |unit_type| !is_unknown_unit_type(dat, unit_type),
+        );
+        if removed > 0 {
+            notes.push(ConversionNote::RemovedDisabledIds(removed));
+        }
+    }
+}
+
+/// Is this one of DE's Enable/Disable Object and Enable/Disable Technology effects?
+fn is_enable_disable(effect_type: i32) -> bool {
+    effect_type == DE_ENABLE_DISABLE_OBJECT || effect_type == DE_ENABLE_DISABLE_TECHNOLOGY
+}
+
+/// Replace DE's Enable/Disable Object and Enable/Disable Technology effects by the separate
+/// enable and disable effects of HD Edition.
+fn convert_trigger_types(scen: &mut Scenario) {
+    let triggers = match scen.triggers_mut() {
+        Some(triggers) => triggers,
+        None => return,
+    };
+    for trigger in triggers.triggers_unordered_mut() {
+        for effect in trigger.effects_unordered_mut() {
+            let new_type = match (effect.effect_type(), effect.enabled()) {
+                (DE_ENABLE_DISABLE_TECHNOLOGY, true) => HD_ENABLE_TECH,
+                (DE_ENABLE_DISABLE_TECHNOLOGY, false) => HD_DISABLE_TECH,
+                (DE_ENABLE_DISABLE_OBJECT, true) => HD_ENABLE_UNIT,
+                (DE_ENABLE_DISABLE_OBJECT, false) => HD_DISABLE_UNIT,
+                _ => continue,
+            };
+            effect.set_effect_type(new_type);
+        }
+    }
+}
+
+/// Paint layered terrain onto the base terrain, because older games only have a single layer.
+fn flatten_layered_terrain(scen: &mut Scenario, notes: &mut Vec<ConversionNote>) {
+    let mut count = 0;
+    for tile in scen.map_mut().tiles_mut() {
+        if let Some(layered) = tile.layered_terrain.take() {
+            if let Ok(layered) = u8::try_from(layered) {
+                tile.terrain = layered;
+            }
+            count += 1;
+        }
+    }
+    if count > 0 {
+        notes.push(ConversionNote::FlattenedLayeredTerrain(count));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn convert_de_to_hd() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Hotkey Trainer Buildings.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let notes = DEToHD::default().convert(&mut scen)?;
+
+        assert!(notes.contains(&ConversionNote::ApproximatedUnit {
+            from: 1251.into(),
+            to: 82.into(),
+            count: 2
+        }));
+        assert!(notes.contains(&ConversionNote::UnmappedUnit {
+            unit_type: 1358.into(),
+            count: 1
+        }));
+        assert!(notes.contains(&ConversionNote::RemovedEffect {
+            trigger: 0,
+            effect_type: 52
+        }));
+        let triggers = scen.triggers().unwrap();
+        let mut enable_disable_units = 0;
+        for trigger in triggers.triggers_unordered() {
+            assert!(trigger
+                .conditions()
+                .all(|condition| condition.condition_type() <= MAX_CONDITION_TYPE));
+            for effect in trigger.effects() {
+                match effect.effect_type() {
+                    HD_ENABLE_UNIT | HD_DISABLE_UNIT => enable_disable_units += 1,
+                    effect_type => assert!(effect_type <= MAX_EFFECT_TYPE),
+                }
+            }
+        }
+        assert_eq!(enable_disable_units, 20);
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert!(scen.version().is_hd_edition());
+        Ok(())
+    }
+
+    #[test]
+    fn convert_de_to_aoc() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let tile = scen
+            .map_mut()
+            .tiles_mut()
+            .find(|tile| tile.terrain == 6)
+            .unwrap();
+        tile.terrain = 73;
+        tile.layered_terrain = None;
+        let notes = DEToHD::new(VersionBundle::aoc()).convert(&mut scen)?;
+
+        assert!(notes.contains(&ConversionNote::ApproximatedTerrain {
+            from: 73,
+            to: 34,
+            count: 1
+        }));
+        // Includes the layered terrain that was painted onto the base terrain.
+        assert!(notes.contains(&ConversionNote::UnmappedTerrain {
+            terrain: 42,
+            count: 588
+        }));
+        assert!(notes.contains(&ConversionNote::UnmappedUnit {
+            unit_type: 1305.into(),
+            count: 4
+        }));
+        assert!(!notes.iter().any(|note| matches!(
+            note,
+            ConversionNote::UnmappedTerrain { terrain, .. } if *terrain <= MAX_AOC_TERRAIN
+        )));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "validate")]
+    fn convert_de_to_aoc_with_data() -> anyhow::Result<()> {
+        let dat = DatFile::read_from(File::open("../genie-dat/fixtures/aoc1.0c.dat")?)?;
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let notes = DEToHD::new(VersionBundle::aoc()).convert_with_data(&mut scen, &dat)?;
+
+        assert!(notes.contains(&ConversionNote::FlattenedLayeredTerrain(2327)));
+        assert_eq!(scen.validate(&dat), vec![]);
+        assert!(scen
+            .map()
+            .tiles()
+            .all(|tile| tile.layered_terrain.is_none()));
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert!(scen.version().is_aoc());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "validate")]
+    fn convert_with_data_keeps_object_classes() -> anyhow::Result<()> {
+        let dat = DatFile::read_from(File::open("../genie-dat/fixtures/aoc1.0c.dat")?)?;
+        let mut f = File::open("test/scenarios/Hotkey Trainer Buildings.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+
+        // Keep only the items that AoC can store, and make them all apply to buildings.
+        let (index, trigger) = scen
+            .triggers_mut()
+            .unwrap()
+            .triggers_unordered_mut()
+            .enumerate()
+            .find(|(_, trigger)| {
+                trigger
+                    .conditions_unordered()
+                    .any(|condition| condition.condition_type() <= MAX_CONDITION_TYPE)
+            })
+            .unwrap();
+        trigger.retain_conditions(|condition| condition.condition_type() <= MAX_CONDITION_TYPE);
+        trigger.retain_effects(|effect| effect.effect_type() <= MAX_EFFECT_TYPE);
+        for condition in trigger.conditions_unordered_mut() {
+            condition.set_raw_unit_type(-1);
+            condition.set_technology_id(-1);
+            condition.set_raw_object_type(2);
+        }
+        for effect in trigger.effects_unordered_mut() {
+            effect.set_raw_unit_type(-1);
+            effect.set_technology_id(-1);
+            effect.set_raw_object_type(2);
+        }
+        let counts = (
+            trigger.conditions_unordered().count(),
+            trigger.effects_unordered().count(),
+        );
+        let notes = DEToHD::new(VersionBundle::aoc()).convert_with_data(&mut scen, &dat)?;
+        let trigger = scen
+            .triggers()
+            .unwrap()
+            .triggers_unordered()
+            .nth(index)
+            .unwrap();
+        assert_eq!(
+            (
+                trigger.conditions_unordered().count(),
+                trigger.effects_unordered().count()
+            ),
+            counts
+        );
+        assert!(!notes.iter().any(|note| match note {
+            ConversionNote::RemovedCondition { trigger, .. }
+            | ConversionNote::RemovedEffect { trigger, .. }
+            | ConversionNote::DisabledTrigger(trigger) => *trigger == index,
+            _ => false,
+        }));
+        Ok(())
+    }
+}
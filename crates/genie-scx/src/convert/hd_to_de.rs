@@ -0,0 +1,189 @@
+use super::{
+    remove_trigger_items, ConversionNote, ConvertError, IdMapConverter, DE_ENABLE_DISABLE_OBJECT,
+    DE_ENABLE_DISABLE_TECHNOLOGY, HD_DISABLE_TECH, HD_DISABLE_UNIT, HD_ENABLE_TECH, HD_ENABLE_UNIT,
+};
+use crate::{Scenario, VersionBundle};
+use std::ops::RangeInclusive;
+
+/// Condition types that only exist in HD Edition. DE uses the same IDs for different conditions.
+const HD_CONDITION_TYPES: RangeInclusive<i32> = 20..=23;
+/// Effect types that only exist in HD Edition. DE uses the same IDs for different effects.
+const HD_EFFECT_TYPES: RangeInclusive<i32> = 30..=36;
+/// The number of properties stored for conditions in Definitive Edition.
+const NUM_CONDITION_PROPERTIES: usize = 21;
+/// The number of properties stored for effects in Definitive Edition.
+const NUM_EFFECT_PROPERTIES: usize = 46;
+
+/// Convert an AoK, AoC, UserPatch, or HD Edition scenario to a Definitive Edition one.
+///
+/// No unit, terrain, tech, or sound IDs are remapped: Definitive Edition kept the IDs of AoC and
+/// HD Edition, and only added new ones after them. Use [`HDToDE::with_ids`] with an
+/// [`IdMapConverter`] table to convert scenarios made for mods that moved units. HD Edition's Enable/Disable Tech and
+/// Enable/Disable Unit effects are replaced by their DE equivalents, and the other trigger
+/// conditions and effects that only exist in HD Edition are removed. Triggers that lose a
+/// condition are disabled, because they would otherwise fire too early.
+///
+/// The water definition and colour mood are set to the defaults of the DE editor. Embedded AI
+/// files and the instructions bitmap are kept.
+///
+/// ## Usage
+///
+/// ```rust,no_run
+/// # use genie_scx::Scenario;
+/// use genie_scx::convert::HDToDE;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut scenario = Scenario::read_from(std::fs::File::open("scenario.scx")?)?;
+/// # let output = std::fs::File::create("scenario.aoe2scenario")?;
+/// let notes = HDToDE::default().convert(&mut scenario)?;
+/// scenario.write_to(output)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HDToDE {
+    version: VersionBundle,
+    ids: IdMapConverter,
+}
+
+impl Default for HDToDE {
+    fn default() -> Self {
+        Self::new(VersionBundle::aoe2_de())
+    }
+}
+
+impl HDToDE {
+    /// Create a converter that targets the given Definitive Edition versions, without an ID table.
+    pub fn new(version: VersionBundle) -> Self {
+        Self {
+            version,
+            ids: IdMapConverter::new(),
+        }
+    }
+
+    /// Also replace IDs using the given table. By default, no IDs are replaced.
+    pub fn with_ids(mut self, ids: IdMapConverter) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Convert a scenario in-place.
+    pub fn convert(&self, scen: &mut Scenario) -> Result<Vec<ConversionNote>, ConvertError> {
+        let version = scen.version();
+        let is_hd_edition = version.is_hd_edition();
+        if !(is_hd_edition || version.is_aok() || version.is_aoc()) {
+            return Err(ConvertError::InvalidVersion);
+        }
+
+        let mut notes = vec![];
+        self.ids.note_changes(scen, &mut notes);
+        self.ids.convert(scen)?;
+
+        convert_triggers(scen, is_hd_edition, &mut notes);
+
+        scen.version = self.version.clone();
+        scen.format.tribe_scen.set_de_defaults();
+        notes.extend(
+            scen.lost_information(&self.version)
+                .into_iter()
+                .map(ConversionNote::Lost),
+        );
+
+        Ok(notes)
+    }
+}
+
+/// Add the properties that DE stores for trigger conditions and effects. For HD Edition
+/// scenarios, also replace HD-only conditions and effects by their DE equivalents, or remove them.
+fn convert_triggers(scen: &mut Scenario, is_hd_edition: bool, notes: &mut Vec<ConversionNote>) {
+    if let Some(triggers) = scen.triggers_mut() {
+        for trigger in triggers.triggers_unordered_mut() {
+            for condition in trigger.conditions_unordered_mut() {
+                condition.resize_properties(NUM_CONDITION_PROPERTIES);
+            }
+            for effect in trigger.effects_unordered_mut() {
+                effect.resize_properties(NUM_EFFECT_PROPERTIES);
+                if !is_hd_edition {
+                    continue;
+                }
+                let (new_type, enabled) = match effect.effect_type() {
+                    HD_ENABLE_TECH => (DE_ENABLE_DISABLE_TECHNOLOGY, true),
+                    HD_DISABLE_TECH => (DE_ENABLE_DISABLE_TECHNOLOGY, false),
+                    HD_ENABLE_UNIT => (DE_ENABLE_DISABLE_OBJECT, true),
+                    HD_DISABLE_UNIT => (DE_ENABLE_DISABLE_OBJECT, false),
+                    _ => continue,
+                };
+                effect.set_effect_type(new_type);
+                effect.set_enabled(enabled);
+            }
+        }
+    }
+
+    if !is_hd_edition {
+        return;
+    }
+    remove_trigger_items(
+        scen,
+        notes,
+        |condition| !HD_CONDITION_TYPES.contains(&condition.condition_type()),
+        |effect| !HD_EFFECT_TYPES.contains(&effect.effect_type()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LostInformation;
+    use std::fs::File;
+
+    #[test]
+    fn convert_hd_to_de() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Year_of_the_Pig.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let num_objects = scen.objects().count();
+        let notes = HDToDE::default().convert(&mut scen)?;
+        assert_eq!(notes, vec![]);
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert!(scen.version().is_age2_de());
+        assert_eq!(scen.objects().count(), num_objects);
+        let lost = scen.lost_information(&VersionBundle::hd_edition());
+        assert!(lost.contains(&LostInformation::WaterDefinition));
+        assert!(lost.contains(&LostInformation::ColorMood));
+        assert!(!lost.contains(&LostInformation::CollideAndCorrect));
+        Ok(())
+    }
+
+    #[test]
+    fn convert_aoc_to_de() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let num_triggers = scen
+            .triggers()
+            .map_or(0, |triggers| triggers.num_triggers());
+        HDToDE::default().convert(&mut scen)?;
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert!(scen.version().is_age2_de());
+        assert_eq!(
+            scen.triggers()
+                .map_or(0, |triggers| triggers.num_triggers()),
+            num_triggers
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn convert_rejects_de() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert!(matches!(
+            HDToDE::default().convert(&mut scen),
+            Err(ConvertError::InvalidVersion)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+use super::{ConversionNote, ConvertError};
+use crate::{Scenario, Trigger, UnitTypeID};
+use genie_support::{CivilizationID, SoundID, TechID};
+use nohash_hasher::IntMap;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// Error indicating that an ID map file could not be read.
+#[derive(Debug, thiserror::Error)]
+pub enum ParseIdMapError {
+    /// A line did not have the `kind,from,to` shape.
+    #[error("line {}: expected `kind,from,to`, got {:?}", .0, .1)]
+    InvalidLine(usize, String),
+    /// A line started with an unknown kind of ID.
+    #[error("line {}: unknown ID kind {:?}, expected unit, terrain, tech, sound, or civ", .0, .1)]
+    UnknownKind(usize, String),
+    /// A line contained an ID that is not a valid number for its kind.
+    #[error("line {}: invalid ID {:?}", .0, .1)]
+    InvalidId(usize, String, #[source] ParseIntError),
+    /// An error occurred while reading.
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// Convert a scenario by replacing unit, terrain, tech, sound, and civilization IDs according to
+/// lookup tables.
+///
+/// ID maps can be built in code, or loaded from a CSV-style text file. Every line in the file maps
+/// a single ID, and has the form `kind,from,to`, where `kind` is one of `unit`, `terrain`, `tech`,
+/// `sound`, or `civ`. Empty lines are ignored, and `#` starts a comment that runs until the end of the
+/// line. If an ID is mapped more than once, the last line wins.
+///
+/// ```text
+/// # Fire Galley <-> Fire Ship
+/// unit,1103,529
+/// unit,529,1103
+/// terrain,11,3 # Dirt 2 -> Dirt 3
+/// ```
+///
+/// The HD Edition → WololoKingdoms, AoC → WololoKingdoms, Definitive Edition → HD Edition, and
+/// AoE1 → AoC tables are available as presets.
+///
+/// ## Usage
+///
+/// ```rust,no_run
+/// # use genie_scx::Scenario;
+/// use genie_scx::convert::IdMapConverter;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut scenario = Scenario::read_from(std::fs::File::open("scenario.scx")?)?;
+/// let converter: IdMapConverter = std::fs::read_to_string("mymod.csv")?.parse()?;
+/// converter.convert(&mut scenario)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct IdMapConverter {
+    unit_ids_map: IntMap<i32, i32>,
+    terrain_ids_map: IntMap<u8, u8>,
+    tech_ids_map: IntMap<i32, i32>,
+    sound_ids_map: IntMap<i32, i32>,
+    civ_ids_map: IntMap<i32, i32>,
+}
+
+impl IdMapConverter {
+    /// Create a converter that does not change any IDs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in table for converting HD Edition scenarios to WololoKingdoms.
+    pub fn hd_to_wk() -> Self {
+        include_str!("presets/hd_to_wk.csv")
+            .parse()
+            .expect("built-in HD to WK table must be valid")
+    }
+
+    /// The built-in table for converting AoC scenarios to WololoKingdoms.
+    pub fn aoc_to_wk() -> Self {
+        include_str!("presets/aoc_to_wk.csv")
+            .parse()
+            .expect("built-in AoC to WK table must be valid")
+    }
+
+    /// The built-in table for converting Definitive Edition scenarios to HD Edition.
+    pub fn de_to_hd() -> Self {
+        include_str!("presets/de_to_hd.csv")
+            .parse()
+            .expect("built-in DE to HD table must be valid")
+    }
+
+    /// The built-in table for converting AoE1 and Rise of Rome scenarios to AoC.
+    pub fn aoe_to_aoc() -> Self {
+        include_str!("presets/aoe_to_aoc.csv")
+            .parse()
+            .expect("built-in AoE to AoC table must be valid")
+    }
+
+    /// Read an ID map file from an input stream.
+    pub fn read_from(mut input: impl Read) -> Result<Self, ParseIdMapError> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        text.parse()
+    }
+
+    /// Map a unit type ID.
+    pub fn map_unit(&mut self, from: UnitTypeID, to: UnitTypeID) {
+        self.unit_ids_map
+            .insert(u16::from(from).into(), u16::from(to).into());
+    }
+
+    /// Map a terrain type ID.
+    pub fn map_terrain(&mut self, from: u8, to: u8) {
+        self.terrain_ids_map.insert(from, to);
+    }
+
+    /// Map a tech ID.
+    pub fn map_tech(&mut self, from: TechID, to: TechID) {
+        self.tech_ids_map
+            .insert(u16::from(from).into(), u16::from(to).into());
+    }
+
+    /// Map a sound ID.
+    pub fn map_sound(&mut self, from: SoundID, to: SoundID) {
+        self.sound_ids_map
+            .insert(u16::from(from).into(), u16::from(to).into());
+    }
+
+    /// Map a civilization ID.
+    pub fn map_civ(&mut self, from: CivilizationID, to: CivilizationID) {
+        self.civ_ids_map
+            .insert(u8::from(from).into(), u8::from(to).into());
+    }
+
+    /// Get the new ID for a unit type, if it is mapped.
+    pub fn unit(&self, id: UnitTypeID) -> Option<UnitTypeID> {
+        self.unit_ids_map
+            .get(&u16::from(id).into())
+            .map(|&id| UnitTypeID::from(id as u16))
+    }
+
+    /// Get the new ID for a terrain type, if it is mapped.
+    pub fn terrain(&self, id: u8) -> Option<u8> {
+        self.terrain_ids_map.get(&id).copied()
+    }
+
+    /// Get the new ID for a tech, if it is mapped.
+    pub fn tech(&self, id: TechID) -> Option<TechID> {
+        self.tech_ids_map
+            .get(&u16::from(id).into())
+            .map(|&id| TechID::from(id as u16))
+    }
+
+    /// Get the new ID for a sound, if it is mapped.
+    pub fn sound(&self, id: SoundID) -> Option<SoundID> {
+        self.sound_ids_map
+            .get(&u16::from(id).into())
+            .map(|&id| SoundID::from(id as u16))
+    }
+
+    /// Get the new ID for a civilization, if it is mapped.
+    pub fn civ(&self, id: CivilizationID) -> Option<CivilizationID> {
+        self.civ_ids_map
+            .get(&u8::from(id).into())
+            .map(|&id| CivilizationID::from(id as u8))
+    }
+
+    /// Iterate over the mapped unit type IDs, as `(from, to)` pairs.
+    pub fn units(&self) -> impl Iterator<Item = (UnitTypeID, UnitTypeID)> + '_ {
+        self.unit_ids_map
+            .iter()
+            .map(|(&from, &to)| (UnitTypeID::from(from as u16), UnitTypeID::from(to as u16)))
+    }
+
+    /// Iterate over the mapped terrain type IDs, as `(from, to)` pairs.
+    pub fn terrains(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.terrain_ids_map.iter().map(|(&from, &to)| (from, to))
+    }
+
+    /// Does the table contain a raw civilization ID, as stored in the player data?
+    pub(crate) fn has_civ(&self, id: i32) -> bool {
+        self.civ_ids_map.contains_key(&id)
+    }
+
+    /// Report the objects and tiles that the ID table is about to replace. IDs that the table
+    /// maps to themselves are not reported.
+    pub(crate) fn note_changes(&self, scen: &Scenario, notes: &mut Vec<ConversionNote>) {
+        let mut units = BTreeMap::new();
+        for object in scen.objects() {
+            if let Some(to) = self.unit(object.object_type) {
+                if to == object.object_type {
+                    continue;
+                }
+                let key = (u16::from(object.object_type), u16::from(to));
+                *units.entry(key).or_insert(0) += 1;
+            }
+        }
+        for ((from, to), count) in units {
+            notes.push(ConversionNote::ApproximatedUnit {
+                from: from.into(),
+                to: to.into(),
+                count,
+            });
+        }
+
+        let mut terrains = BTreeMap::new();
+        for tile in scen.map().tiles() {
+            if let Some(to) = self.terrain(tile.terrain).filter(|&to| to != tile.terrain) {
+                *terrains.entry((tile.terrain, to)).or_insert(0) += 1;
+            }
+        }
+        for ((from, to), count) in terrains {
+            notes.push(ConversionNote::ApproximatedTerrain { from, to, count });
+        }
+    }
+
+    /// Replace a raw unit type ID as stored in triggers and disabled lists.
+    fn convert_unit_id(&self, id: &mut i32) {
+        if let Some(new_id) = self.unit_ids_map.get(id) {
+            *id = *new_id;
+        }
+    }
+
+    /// Replace a raw tech ID as stored in triggers and disabled lists.
+    fn convert_tech_id(&self, id: &mut i32) {
+        if let Some(new_id) = self.tech_ids_map.get(id) {
+            *id = *new_id;
+        }
+    }
+
+    /// Update the unit type, tech, and sound IDs in trigger conditions and effects.
+    ///
+    /// The object type is an object class, not a unit type, so it is left alone.
+    fn convert_trigger(&self, trigger: &mut Trigger) {
+        trigger.conditions_unordered_mut().for_each(|cond| {
+            let mut unit_type = cond.raw_unit_type();
+            self.convert_unit_id(&mut unit_type);
+            cond.set_raw_unit_type(unit_type);
+
+            let mut tech = cond.technology_id();
+            self.convert_tech_id(&mut tech);
+            cond.set_technology_id(tech);
+        });
+        trigger.effects_unordered_mut().for_each(|effect| {
+            let mut unit_type = effect.raw_unit_type();
+            self.convert_unit_id(&mut unit_type);
+            effect.set_raw_unit_type(unit_type);
+
+            let mut tech = effect.technology_id();
+            self.convert_tech_id(&mut tech);
+            effect.set_technology_id(tech);
+
+            if let Some(sound) = self.sound_ids_map.get(&effect.sound_id()) {
+                effect.set_sound_id(*sound);
+            }
+        });
+    }
+
+    /// Convert a scenario in-place.
+    pub fn convert(&self, scen: &mut Scenario) -> Result<(), ConvertError> {
+        for object in scen.objects_mut() {
+            if let Some(new_type) = self.unit(object.object_type) {
+                object.object_type = new_type;
+            }
+        }
+
+        for tile in scen.map_mut().tiles_mut() {
+            if let Some(new_type) = self.terrain(tile.terrain) {
+                tile.terrain = new_type;
+            }
+            if let Some(layered) = tile.layered_terrain {
+                let new_type = u8::try_from(layered).ok().and_then(|id| self.terrain(id));
+                if let Some(new_type) = new_type {
+                    tile.layered_terrain = Some(new_type.into());
+                }
+            }
+        }
+
+        if let Some(trigger_system) = scen.triggers_mut() {
+            for trigger in trigger_system.triggers_unordered_mut() {
+                self.convert_trigger(trigger);
+            }
+        }
+
+        let tribe_scen = &mut scen.format.tribe_scen;
+        for player in 0..16 {
+            for id in tribe_scen.player_disabled_techs_mut(player) {
+                self.convert_tech_id(id);
+            }
+            for id in tribe_scen.player_disabled_units_mut(player) {
+                self.convert_unit_id(id);
+            }
+            for id in tribe_scen.player_disabled_buildings_mut(player) {
+                self.convert_unit_id(id);
+            }
+        }
+        for civ in tribe_scen.player_civilizations_mut() {
+            if let Some(new_civ) = self.civ_ids_map.get(civ) {
+                *civ = *new_civ;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for IdMapConverter {
+    type Err = ParseIdMapError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut converter = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let (kind, from, to) = match fields.as_slice() {
+                [kind, from, to] => (*kind, *from, *to),
+                _ => return Err(ParseIdMapError::InvalidLine(line_number, line.to_string())),
+            };
+            let parse_u16 = |id: &str| {
+                id.parse::<u16>()
+                    .map_err(|err| ParseIdMapError::InvalidId(line_number, id.to_string(), err))
+            };
+            let parse_u8 = |id: &str| {
+                id.parse::<u8>()
+                    .map_err(|err| ParseIdMapError::InvalidId(line_number, id.to_string(), err))
+            };
+
+            match kind {
+                "unit" => converter.map_unit(parse_u16(from)?.into(), parse_u16(to)?.into()),
+                "terrain" => converter.map_terrain(parse_u8(from)?, parse_u8(to)?),
+                "tech" => converter.map_tech(parse_u16(from)?.into(), parse_u16(to)?.into()),
+                "sound" => converter.map_sound(parse_u16(from)?.into(), parse_u16(to)?.into()),
+                "civ" => converter.map_civ(parse_u8(from)?.into(), parse_u8(to)?.into()),
+                _ => return Err(ParseIdMapError::UnknownKind(line_number, kind.to_string())),
+            }
+        }
+        Ok(converter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn parse_id_map() {
+        let converter: IdMapConverter = "
+            # comment
+            unit,1103,529 # Fire Galley, Fire Ship
+            terrain, 11, 3
+            tech,1,2
+            sound,4,5
+            civ,2,7
+            unit,1103,467
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(converter.unit(1103.into()), Some(467.into()));
+        assert_eq!(converter.terrain(11), Some(3));
+        assert_eq!(converter.tech(1.into()), Some(2.into()));
+        assert_eq!(converter.sound(4.into()), Some(5.into()));
+        assert_eq!(converter.civ(2.into()), Some(7.into()));
+        assert_eq!(converter.unit(1.into()), None);
+
+        assert!(matches!(
+            "unit,1".parse::<IdMapConverter>(),
+            Err(ParseIdMapError::InvalidLine(1, _))
+        ));
+        assert!(matches!(
+            "\nbuilding,1,2".parse::<IdMapConverter>(),
+            Err(ParseIdMapError::UnknownKind(2, _))
+        ));
+        assert!(matches!(
+            "terrain,300,1".parse::<IdMapConverter>(),
+            Err(ParseIdMapError::InvalidId(1, _, _))
+        ));
+    }
+
+    #[test]
+    fn convert_hd_to_wk() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Year_of_the_Pig.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let before = scen.map().clone();
+        let converter = IdMapConverter::hd_to_wk();
+        converter.convert(&mut scen)?;
+        for (old, new) in before.tiles().zip(scen.map().tiles()) {
+            let expected = converter.terrain(old.terrain).unwrap_or(old.terrain);
+            assert_eq!(new.terrain, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn keep_trigger_object_classes() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+        let mut trigger = scen
+            .triggers()
+            .unwrap()
+            .triggers_unordered()
+            .find(|trigger| {
+                trigger.conditions_unordered().count() > 0
+                    && trigger.effects_unordered().count() > 0
+            })
+            .unwrap()
+            .clone();
+        for condition in trigger.conditions_unordered_mut() {
+            condition.set_raw_unit_type(4);
+            condition.set_raw_object_type(2);
+        }
+        for effect in trigger.effects_unordered_mut() {
+            effect.set_raw_unit_type(4);
+            effect.set_raw_object_type(2);
+        }
+
+        let converter: IdMapConverter = "unit,2,900\nunit,4,901".parse()?;
+        converter.convert_trigger(&mut trigger);
+        for condition in trigger.conditions_unordered() {
+            assert_eq!(condition.raw_unit_type(), 901);
+            assert_eq!(condition.raw_object_type(), 2);
+        }
+        for effect in trigger.effects_unordered() {
+            assert_eq!(effect.raw_unit_type(), 901);
+            assert_eq!(effect.raw_object_type(), 2);
+        }
+        Ok(())
+    }
+}
@@ -2,12 +2,34 @@
 //!
 //! This module implements conversions between different scenario formats and game versions.
 mod aoc_to_wk;
+mod aoe_to_aoc;
+mod de_to_hd;
+mod hd_to_de;
 mod hd_to_wk;
+mod id_map;
+mod wk_to_hd;
 
-use crate::{Scenario, ScenarioObject};
+use crate::{
+    LostInformation, Scenario, ScenarioObject, TriggerCondition, TriggerEffect, UnitTypeID,
+};
+use std::fmt;
 
 pub use aoc_to_wk::AoCToWK;
+pub use aoe_to_aoc::AoEToAoC;
+pub use de_to_hd::DEToHD;
+pub use hd_to_de::HDToDE;
 pub use hd_to_wk::HDToWK;
+pub use id_map::{IdMapConverter, ParseIdMapError};
+pub use wk_to_hd::WKToHD;
+
+/// HD Edition effect types that DE replaced by its Enable/Disable Object and Enable/Disable
+/// Technology effects.
+const HD_ENABLE_TECH: i32 = 32;
+const HD_DISABLE_TECH: i32 = 33;
+const HD_ENABLE_UNIT: i32 = 34;
+const HD_DISABLE_UNIT: i32 = 35;
+const DE_ENABLE_DISABLE_OBJECT: i32 = 38;
+const DE_ENABLE_DISABLE_TECHNOLOGY: i32 = 39;
 
 /// Error indicating scenario conversion failure.
 #[derive(Debug, thiserror::Error)]
@@ -17,6 +39,146 @@ pub enum ConvertError {
     InvalidVersion,
 }
 
+/// Something a converter had to drop or approximate, because the target game can not represent
+/// it.
+///
+/// Trigger numbers are the unordered trigger indices.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversionNote {
+    /// Objects of a unit type were replaced by a similar unit type.
+    ApproximatedUnit {
+        from: UnitTypeID,
+        to: UnitTypeID,
+        count: usize,
+    },
+    /// Tiles of a terrain type were replaced by a similar terrain type.
+    ApproximatedTerrain { from: u8, to: u8, count: usize },
+    /// Objects have a unit type that could have come from several original unit types, and were
+    /// left unchanged.
+    AmbiguousUnit { unit_type: UnitTypeID, count: usize },
+    /// Tiles have a terrain type that could have come from several original terrain types, and
+    /// were left unchanged.
+    AmbiguousTerrain { terrain: u8, count: usize },
+    /// Objects have a unit type whose slot is also used by a unit of the original game. They were
+    /// assumed to be the converted unit, and replaced by it.
+    AssumedUnit {
+        from: UnitTypeID,
+        to: UnitTypeID,
+        count: usize,
+    },
+    /// Objects have a unit type that is not in the conversion table, and were left unchanged.
+    UnmappedUnit { unit_type: UnitTypeID, count: usize },
+    /// Tiles have a terrain type that is not in the conversion table, and were left unchanged.
+    UnmappedTerrain { terrain: u8, count: usize },
+    /// A player has a civilization that is not in the conversion table, and was left unchanged.
+    /// Players are numbered from 1.
+    UnmappedCivilization { player: u8, civilization: i32 },
+    /// Layered terrain was painted onto the base terrain of this many tiles.
+    FlattenedLayeredTerrain(usize),
+    /// Objects of a unit type that does not exist in the target game were removed.
+    RemovedObjects { unit_type: UnitTypeID, count: usize },
+    /// A trigger condition that does not exist in the target game was removed.
+    RemovedCondition { trigger: usize, condition_type: i32 },
+    /// A trigger effect that does not exist in the target game was removed.
+    RemovedEffect { trigger: usize, effect_type: i32 },
+    /// A trigger lost some of its conditions, and was disabled so it does not fire too early.
+    DisabledTrigger(usize),
+    /// Disabled techs, units, or buildings that do not exist in the target game were removed.
+    RemovedDisabledIds(usize),
+    /// Data that can not be stored in the target version.
+    Lost(LostInformation),
+}
+
+impl fmt::Display for ConversionNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionNote::ApproximatedUnit { from, to, count } => write!(
+                f,
+                "replaced {} objects of unit type {} by unit type {}",
+                count,
+                u16::from(*from),
+                u16::from(*to)
+            ),
+            ConversionNote::ApproximatedTerrain { from, to, count } => write!(
+                f,
+                "replaced terrain {} by terrain {} on {} tiles",
+                from, to, count
+            ),
+            ConversionNote::AmbiguousUnit { unit_type, count } => write!(
+                f,
+                "could not recover the original unit type of {} objects of unit type {}",
+                count,
+                u16::from(*unit_type)
+            ),
+            ConversionNote::AmbiguousTerrain { terrain, count } => write!(
+                f,
+                "could not recover the original terrain of {} tiles of terrain {}",
+                count, terrain
+            ),
+            ConversionNote::AssumedUnit { from, to, count } => write!(
+                f,
+                "assumed that {} objects of unit type {} were unit type {}",
+                count,
+                u16::from(*from),
+                u16::from(*to)
+            ),
+            ConversionNote::UnmappedUnit { unit_type, count } => write!(
+                f,
+                "no replacement for unit type {}, used by {} objects",
+                u16::from(*unit_type),
+                count
+            ),
+            ConversionNote::UnmappedTerrain { terrain, count } => write!(
+                f,
+                "no replacement for terrain {}, used by {} tiles",
+                terrain, count
+            ),
+            ConversionNote::UnmappedCivilization {
+                player,
+                civilization,
+            } => write!(
+                f,
+                "no replacement for civilization {} of player {}",
+                civilization, player
+            ),
+            ConversionNote::FlattenedLayeredTerrain(count) => {
+                write!(
+                    f,
+                    "replaced base terrain by layered terrain on {} tiles",
+                    count
+                )
+            }
+            ConversionNote::RemovedObjects { unit_type, count } => write!(
+                f,
+                "removed {} objects of unit type {}",
+                count,
+                u16::from(*unit_type)
+            ),
+            ConversionNote::RemovedCondition {
+                trigger,
+                condition_type,
+            } => write!(
+                f,
+                "removed condition of type {} from trigger #{}",
+                condition_type, trigger
+            ),
+            ConversionNote::RemovedEffect {
+                trigger,
+                effect_type,
+            } => write!(
+                f,
+                "removed effect of type {} from trigger #{}",
+                effect_type, trigger
+            ),
+            ConversionNote::DisabledTrigger(trigger) => write!(f, "disabled trigger #{}", trigger),
+            ConversionNote::RemovedDisabledIds(count) => {
+                write!(f, "removed {} disabled techs, units, or buildings", count)
+            }
+            ConversionNote::Lost(lost) => write!(f, "lost {}", lost),
+        }
+    }
+}
+
 /// Convert an AoC or HD Edition scenario file to a WololoKingdoms one.
 ///
 /// It will auto-detect the version of the file, and output a WK compatible scenario.
@@ -57,3 +219,44 @@ impl AutoToWK {
         }
     }
 }
+
+/// Remove trigger conditions and effects, and disable triggers that lost a condition.
+fn remove_trigger_items(
+    scen: &mut Scenario,
+    notes: &mut Vec<ConversionNote>,
+    keep_condition: impl Fn(&TriggerCondition) -> bool,
+    keep_effect: impl Fn(&TriggerEffect) -> bool,
+) {
+    let triggers = match scen.triggers_mut() {
+        Some(triggers) => triggers,
+        None => return,
+    };
+
+    for (index, trigger) in triggers.triggers_unordered_mut().enumerate() {
+        let mut removed_condition = false;
+        for condition in trigger.conditions_unordered() {
+            if !keep_condition(condition) {
+                removed_condition = true;
+                notes.push(ConversionNote::RemovedCondition {
+                    trigger: index,
+                    condition_type: condition.condition_type(),
+                });
+            }
+        }
+        for effect in trigger.effects_unordered() {
+            if !keep_effect(effect) {
+                notes.push(ConversionNote::RemovedEffect {
+                    trigger: index,
+                    effect_type: effect.effect_type(),
+                });
+            }
+        }
+
+        trigger.retain_conditions(&keep_condition);
+        trigger.retain_effects(&keep_effect);
+        if removed_condition && trigger.enabled() {
+            trigger.set_enabled(false);
+            notes.push(ConversionNote::DisabledTrigger(index));
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use super::{ConversionNote, ConvertError, IdMapConverter};
+use crate::{Scenario, VersionBundle};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Convert a WololoKingdoms scenario to an HD Edition one.
+///
+/// This reverses the [`HDToWK`](super::HDToWK) and [`AoCToWK`](super::AoCToWK) tables. HD units
+/// that WK moved into unused AoC unit slots are moved back, and the swapped Fire Galley/Fire Ship
+/// and Demolition Raft/Demolition Ship IDs are swapped back. Objects in those slots are assumed to
+/// be the WK unit, not the original AoC unit that used the slot, and reported as
+/// [`ConversionNote::AssumedUnit`].
+///
+/// WK merges some HD terrains into similar looking ones. Those can only be converted back when the
+/// WK terrain ID is not also used by HD itself. Tiles and objects that could have come from
+/// several original IDs are left unchanged, and reported as [`ConversionNote::AmbiguousTerrain`]
+/// and [`ConversionNote::AmbiguousUnit`].
+///
+/// ## Usage
+///
+/// ```rust,no_run
+/// # use genie_scx::Scenario;
+/// use genie_scx::convert::WKToHD;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut scenario = Scenario::read_from(std::fs::File::open("wk.scx")?)?;
+/// # let output = std::fs::File::create("hd.scx")?;
+/// let notes = WKToHD::default().convert(&mut scenario)?;
+/// scenario.write_to(output)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WKToHD {
+    version: VersionBundle,
+    ids: IdMapConverter,
+    ambiguous_units: BTreeSet<u16>,
+    ambiguous_terrains: BTreeSet<u8>,
+    reused_units: BTreeSet<u16>,
+}
+
+impl Default for WKToHD {
+    fn default() -> Self {
+        Self::from_tables(&IdMapConverter::hd_to_wk(), &IdMapConverter::aoc_to_wk())
+    }
+}
+
+impl WKToHD {
+    /// Create a converter that reverses the given HD → WK and AoC → WK tables.
+    pub fn from_tables(hd_to_wk: &IdMapConverter, aoc_to_wk: &IdMapConverter) -> Self {
+        let mut ids = IdMapConverter::new();
+        let mut ambiguous_units = BTreeSet::new();
+        let mut ambiguous_terrains = BTreeSet::new();
+        let mut reused_units = BTreeSet::new();
+
+        let mut unit_sources = BTreeMap::new();
+        for (from, to) in hd_to_wk.units() {
+            unit_sources
+                .entry(u16::from(to))
+                .or_insert_with(Vec::new)
+                .push(from);
+        }
+        for (&to, sources) in &unit_sources {
+            match sources.as_slice() {
+                [from] => {
+                    ids.map_unit(to.into(), *from);
+                    // If the unit that originally used this slot was not moved away, an object in
+                    // it might also be that unit.
+                    let kept =
+                        hd_to_wk.unit(to.into()).is_none() && aoc_to_wk.unit(to.into()).is_none();
+                    if kept {
+                        reused_units.insert(to);
+                    }
+                }
+                _ => {
+                    ambiguous_units.insert(to);
+                }
+            }
+        }
+        // The AoC table swaps some units around. Where HD units were moved elsewhere, the swapped
+        // slots can be restored from it.
+        for (from, to) in aoc_to_wk.units() {
+            if !unit_sources.contains_key(&u16::from(to)) {
+                ids.map_unit(to, from);
+            }
+        }
+
+        let mut terrain_sources = BTreeMap::new();
+        for (from, to) in hd_to_wk.terrains() {
+            terrain_sources
+                .entry(to)
+                .or_insert_with(Vec::new)
+                .push(from);
+        }
+        for (&to, sources) in &terrain_sources {
+            // If HD leaves this terrain unchanged, a tile with it might have had either ID.
+            let kept_by_hd = hd_to_wk.terrain(to).is_none();
+            match sources.as_slice() {
+                [from] if !kept_by_hd => ids.map_terrain(to, *from),
+                _ => {
+                    ambiguous_terrains.insert(to);
+                }
+            }
+        }
+
+        Self {
+            version: VersionBundle::hd_edition(),
+            ids,
+            ambiguous_units,
+            ambiguous_terrains,
+            reused_units,
+        }
+    }
+
+    /// Get the table that is used to convert IDs back.
+    pub fn ids(&self) -> &IdMapConverter {
+        &self.ids
+    }
+
+    /// Convert a scenario in-place.
+    pub fn convert(&self, scen: &mut Scenario) -> Result<Vec<ConversionNote>, ConvertError> {
+        if !scen.version().is_aoc() {
+            return Err(ConvertError::InvalidVersion);
+        }
+
+        let mut notes = vec![];
+        let mut units = BTreeMap::new();
+        let mut reused = BTreeMap::new();
+        for object in scen.objects() {
+            let unit_type = u16::from(object.object_type);
+            if self.ambiguous_units.contains(&unit_type) {
+                *units.entry(unit_type).or_insert(0) += 1;
+            } else if self.reused_units.contains(&unit_type) {
+                *reused.entry(unit_type).or_insert(0) += 1;
+            }
+        }
+        for (unit_type, count) in units {
+            notes.push(ConversionNote::AmbiguousUnit {
+                unit_type: unit_type.into(),
+                count,
+            });
+        }
+        for (unit_type, count) in reused {
+            let from = unit_type.into();
+            if let Some(to) = self.ids.unit(from) {
+                notes.push(ConversionNote::AssumedUnit { from, to, count });
+            }
+        }
+        let mut terrains = BTreeMap::new();
+        for tile in scen.map().tiles() {
+            if self.ambiguous_terrains.contains(&tile.terrain) {
+                *terrains.entry(tile.terrain).or_insert(0) += 1;
+            }
+        }
+        for (terrain, count) in terrains {
+            notes.push(ConversionNote::AmbiguousTerrain { terrain, count });
+        }
+
+        self.ids.convert(scen)?;
+
+        scen.version = self.version.clone();
+        notes.extend(
+            scen.lost_information(&self.version)
+                .into_iter()
+                .map(ConversionNote::Lost),
+        );
+
+        Ok(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::HDToWK;
+    use crate::ScenarioObject;
+    use std::fs::File;
+
+    #[test]
+    fn reverse_tables() {
+        let converter = WKToHD::default();
+        let ids = converter.ids();
+        // Organ Gun
+        assert_eq!(ids.unit(106.into()), Some(1001.into()));
+        // Fire Ship, Fire Galley
+        assert_eq!(ids.unit(1103.into()), Some(529.into()));
+        assert_eq!(ids.unit(529.into()), Some(1103.into()));
+        assert_eq!(ids.unit(467.into()), Some(1103.into()));
+        // AoC still uses these slots for its own units.
+        assert!(converter.reused_units.contains(&106));
+        assert!(converter.reused_units.contains(&467));
+        assert!(!converter.reused_units.contains(&529));
+        // Demolition Ship, Demolition Raft
+        assert_eq!(ids.unit(1104.into()), Some(527.into()));
+        assert_eq!(ids.unit(527.into()), Some(1104.into()));
+        // Cracked Earth
+        assert_eq!(ids.terrain(38), Some(45));
+        // Snow Dirt and Snow Road both exist in HD
+        assert_eq!(ids.terrain(33), None);
+    }
+
+    #[test]
+    fn hd_to_wk_and_back() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Year_of_the_Pig.aoe2scenario")?;
+        let mut original = Scenario::read_from(&mut f)?;
+        // Organ Gun, which WK stores in the slot of AoC's INFIL_D.
+        original.add_object(
+            1,
+            ScenarioObject {
+                object_type: 1001.into(),
+                position: (10.5, 10.5, 0.0),
+                ..Default::default()
+            },
+        )?;
+        let mut scen = original.clone();
+        HDToWK::default().convert(&mut scen)?;
+        let mut out = vec![];
+        scen.write_to_version(&mut out, &VersionBundle::aoc())?;
+        let mut scen = Scenario::read_from(&out[..])?;
+
+        let notes = WKToHD::default().convert(&mut scen)?;
+        assert!(notes.contains(&ConversionNote::AmbiguousTerrain {
+            terrain: 41,
+            count: 1
+        }));
+        assert!(notes.contains(&ConversionNote::AssumedUnit {
+            from: 106.into(),
+            to: 1001.into(),
+            count: 1
+        }));
+        assert!(scen.version().is_hd_edition());
+        for (old, new) in original.objects().zip(scen.objects()) {
+            assert_eq!(old.object_type, new.object_type);
+        }
+        Ok(())
+    }
+}
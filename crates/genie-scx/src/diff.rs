@@ -0,0 +1,508 @@
+//! Finding what changed between two versions of a scenario.
+
+use crate::{Cinematic, Scenario, ScenarioObject, ScenarioText};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The part of a scenario that a change belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DiffSection {
+    /// Map size and terrain.
+    Map,
+    /// Placed objects.
+    Objects,
+    /// Triggers.
+    Triggers,
+    /// Player settings.
+    Players,
+    /// Victory conditions.
+    Victory,
+    /// Texts and cinematics.
+    Text,
+}
+
+impl fmt::Display for DiffSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DiffSection::Map => "Map",
+            DiffSection::Objects => "Objects",
+            DiffSection::Triggers => "Triggers",
+            DiffSection::Players => "Players",
+            DiffSection::Victory => "Victory",
+            DiffSection::Text => "Text",
+        })
+    }
+}
+
+/// A per-player setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerSetting {
+    /// The player name.
+    Name,
+    /// The civilization.
+    Civilization,
+    /// Whether the player is a human or computer player.
+    PlayerType,
+    /// The starting resources.
+    Resources,
+    /// The starting age.
+    StartingAge,
+    /// The diplomatic stances towards other players.
+    Diplomacy,
+    /// The disabled techs.
+    DisabledTechs,
+    /// The disabled units.
+    DisabledUnits,
+    /// The disabled buildings.
+    DisabledBuildings,
+}
+
+impl fmt::Display for PlayerSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlayerSetting::Name => "name",
+            PlayerSetting::Civilization => "civilization",
+            PlayerSetting::PlayerType => "player type",
+            PlayerSetting::Resources => "starting resources",
+            PlayerSetting::StartingAge => "starting age",
+            PlayerSetting::Diplomacy => "diplomacy",
+            PlayerSetting::DisabledTechs => "disabled techs",
+            PlayerSetting::DisabledUnits => "disabled units",
+            PlayerSetting::DisabledBuildings => "disabled buildings",
+        })
+    }
+}
+
+/// A difference between two versions of a scenario.
+///
+/// Player numbers start at 0 for GAIA. Objects are matched by their ID, and triggers by their
+/// unordered index, which is also their trigger ID.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScenarioChange {
+    /// The map was resized.
+    MapResized { from: (u32, u32), to: (u32, u32) },
+    /// A map tile changed. Only tiles that exist in both versions are compared.
+    TileChanged { x: u32, y: u32 },
+    /// An object was added.
+    ObjectAdded { player: u8, id: i32 },
+    /// An object was removed.
+    ObjectRemoved { player: u8, id: i32 },
+    /// An object was moved.
+    ObjectMoved {
+        id: i32,
+        from: (f32, f32, f32),
+        to: (f32, f32, f32),
+    },
+    /// An object was given to a different player.
+    ObjectOwnerChanged { id: i32, from: u8, to: u8 },
+    /// An object's type, angle, or other properties changed.
+    ObjectModified { id: i32 },
+    /// A trigger was added.
+    TriggerAdded { index: usize, name: Option<String> },
+    /// A trigger was removed.
+    TriggerRemoved { index: usize, name: Option<String> },
+    /// A trigger's settings, conditions, or effects changed.
+    TriggerModified { index: usize, name: Option<String> },
+    /// The order in which triggers are displayed in the editor changed. Only triggers that exist
+    /// in both versions are compared.
+    TriggerOrderChanged,
+    /// A per-player setting changed.
+    PlayerChanged { player: u8, setting: PlayerSetting },
+    /// The team settings changed.
+    TeamSettingsChanged,
+    /// The scenario-wide victory settings changed.
+    VictorySettingsChanged,
+    /// A player's own victory conditions, or their allied victory setting, changed.
+    PlayerVictoryChanged { player: u8 },
+    /// A text, or its string table key, changed.
+    TextChanged(ScenarioText),
+    /// A cinematic changed.
+    CinematicChanged(Cinematic),
+}
+
+impl ScenarioChange {
+    /// Get the part of the scenario that this change belongs to.
+    pub fn section(&self) -> DiffSection {
+        match self {
+            ScenarioChange::MapResized { .. } | ScenarioChange::TileChanged { .. } => {
+                DiffSection::Map
+            }
+            ScenarioChange::ObjectAdded { .. }
+            | ScenarioChange::ObjectRemoved { .. }
+            | ScenarioChange::ObjectMoved { .. }
+            | ScenarioChange::ObjectOwnerChanged { .. }
+            | ScenarioChange::ObjectModified { .. } => DiffSection::Objects,
+            ScenarioChange::TriggerAdded { .. }
+            | ScenarioChange::TriggerRemoved { .. }
+            | ScenarioChange::TriggerModified { .. }
+            | ScenarioChange::TriggerOrderChanged => DiffSection::Triggers,
+            ScenarioChange::PlayerChanged { .. } | ScenarioChange::TeamSettingsChanged => {
+                DiffSection::Players
+            }
+            ScenarioChange::VictorySettingsChanged
+            | ScenarioChange::PlayerVictoryChanged { .. } => DiffSection::Victory,
+            ScenarioChange::TextChanged(_) | ScenarioChange::CinematicChanged(_) => {
+                DiffSection::Text
+            }
+        }
+    }
+}
+
+/// Format a trigger index with its name, if it has one.
+fn trigger_label(index: usize, name: &Option<String>) -> String {
+    match name {
+        Some(name) => format!("trigger #{} \"{}\"", index, name),
+        None => format!("trigger #{}", index),
+    }
+}
+
+impl fmt::Display for ScenarioChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioChange::MapResized { from, to } => write!(
+                f,
+                "map resized from {}x{} to {}x{}",
+                from.0, from.1, to.0, to.1
+            ),
+            ScenarioChange::TileChanged { x, y } => write!(f, "tile ({}, {}) changed", x, y),
+            ScenarioChange::ObjectAdded { player, id } => {
+                write!(f, "object {} added for player {}", id, player)
+            }
+            ScenarioChange::ObjectRemoved { player, id } => {
+                write!(f, "object {} of player {} removed", id, player)
+            }
+            ScenarioChange::ObjectMoved { id, from, to } => write!(
+                f,
+                "object {} moved from ({}, {}) to ({}, {})",
+                id, from.0, from.1, to.0, to.1
+            ),
+            ScenarioChange::ObjectOwnerChanged { id, from, to } => write!(
+                f,
+                "object {} given from player {} to player {}",
+                id, from, to
+            ),
+            ScenarioChange::ObjectModified { id } => write!(f, "object {} changed", id),
+            ScenarioChange::TriggerAdded { index, name } => {
+                write!(f, "{} added", trigger_label(*index, name))
+            }
+            ScenarioChange::TriggerRemoved { index, name } => {
+                write!(f, "{} removed", trigger_label(*index, name))
+            }
+            ScenarioChange::TriggerModified { index, name } => {
+                write!(f, "{} changed", trigger_label(*index, name))
+            }
+            ScenarioChange::TriggerOrderChanged => f.write_str("trigger display order changed"),
+            ScenarioChange::PlayerChanged { player, setting } => {
+                write!(f, "{} of player {} changed", setting, player)
+            }
+            ScenarioChange::TeamSettingsChanged => f.write_str("team settings changed"),
+            ScenarioChange::VictorySettingsChanged => f.write_str("victory settings changed"),
+            ScenarioChange::PlayerVictoryChanged { player } => {
+                write!(f, "victory conditions of player {} changed", player)
+            }
+            ScenarioChange::TextChanged(text) => write!(f, "{:?} text changed", text),
+            ScenarioChange::CinematicChanged(cinematic) => {
+                write!(f, "{:?} cinematic changed", cinematic)
+            }
+        }
+    }
+}
+
+fn diff_map(before: &Scenario, after: &Scenario, changes: &mut Vec<ScenarioChange>) {
+    let (old, new) = (before.map(), after.map());
+    let from = (old.width(), old.height());
+    let to = (new.width(), new.height());
+    if from != to {
+        changes.push(ScenarioChange::MapResized { from, to });
+    }
+    for y in 0..from.1.min(to.1) {
+        for x in 0..from.0.min(to.0) {
+            if old.tile(x, y) != new.tile(x, y) {
+                changes.push(ScenarioChange::TileChanged { x, y });
+            }
+        }
+    }
+}
+
+/// Index the objects in a scenario by their ID.
+fn objects_by_id(scen: &Scenario) -> BTreeMap<i32, (u8, &ScenarioObject)> {
+    let mut objects = BTreeMap::new();
+    for (player, list) in scen.format.player_objects.iter().enumerate() {
+        for object in list {
+            objects.insert(object.id, (player as u8, object));
+        }
+    }
+    objects
+}
+
+fn diff_objects(before: &Scenario, after: &Scenario, changes: &mut Vec<ScenarioChange>) {
+    let old = objects_by_id(before);
+    let new = objects_by_id(after);
+
+    for (&id, &(player, _)) in &old {
+        if !new.contains_key(&id) {
+            changes.push(ScenarioChange::ObjectRemoved { player, id });
+        }
+    }
+
+    for (&id, &(player, object)) in &new {
+        let (old_player, old_object) = match old.get(&id) {
+            Some(&entry) => entry,
+            None => {
+                changes.push(ScenarioChange::ObjectAdded { player, id });
+                continue;
+            }
+        };
+        if old_object.position != object.position {
+            changes.push(ScenarioChange::ObjectMoved {
+                id,
+                from: old_object.position,
+                to: object.position,
+            });
+        }
+        if old_player != player {
+            changes.push(ScenarioChange::ObjectOwnerChanged {
+                id,
+                from: old_player,
+                to: player,
+            });
+        }
+        let modified = old_object.object_type != object.object_type
+            || old_object.state != object.state
+            || old_object.angle != object.angle
+            || old_object.frame != object.frame
+            || old_object.garrisoned_in != object.garrisoned_in;
+        if modified {
+            changes.push(ScenarioChange::ObjectModified { id });
+        }
+    }
+}
+
+fn diff_triggers(before: &Scenario, after: &Scenario, changes: &mut Vec<ScenarioChange>) {
+    let old: Vec<_> = before
+        .triggers()
+        .map(|triggers| triggers.triggers_unordered().collect())
+        .unwrap_or_default();
+    let new: Vec<_> = after
+        .triggers()
+        .map(|triggers| triggers.triggers_unordered().collect())
+        .unwrap_or_default();
+
+    for index in 0..old.len().max(new.len()) {
+        match (old.get(index), new.get(index)) {
+            (Some(old), Some(new)) if old != new => {
+                changes.push(ScenarioChange::TriggerModified {
+                    index,
+                    name: new.name().map(str::to_string),
+                });
+            }
+            (Some(old), None) => changes.push(ScenarioChange::TriggerRemoved {
+                index,
+                name: old.name().map(str::to_string),
+            }),
+            (None, Some(new)) => changes.push(ScenarioChange::TriggerAdded {
+                index,
+                name: new.name().map(str::to_string),
+            }),
+            _ => {}
+        }
+    }
+
+    // Triggers that were added or removed always move the others around, so leave them out.
+    let shared = old.len().min(new.len());
+    let display_order = |scen: &Scenario| -> Vec<usize> {
+        scen.triggers()
+            .map(|triggers| triggers.trigger_ids().filter(|&id| id < shared).collect())
+            .unwrap_or_default()
+    };
+    if display_order(before) != display_order(after) {
+        changes.push(ScenarioChange::TriggerOrderChanged);
+    }
+}
+
+/// Get the name of a player, starting at 1.
+fn player_name(scen: &Scenario, player: u8) -> Option<&str> {
+    let index = usize::from(player).checked_sub(1)?;
+    scen.format
+        .tribe_scen
+        .base
+        .player_names
+        .get(index)?
+        .as_deref()
+}
+
+fn diff_players(before: &Scenario, after: &Scenario, changes: &mut Vec<ScenarioChange>) {
+    let num_players = before
+        .format
+        .player_objects
+        .len()
+        .max(after.format.player_objects.len())
+        .saturating_sub(1);
+    let players = 1..=u8::try_from(num_players).unwrap_or(u8::MAX);
+
+    for player in players.clone() {
+        let mut changed = |setting, is_changed: bool| {
+            if is_changed {
+                changes.push(ScenarioChange::PlayerChanged { player, setting });
+            }
+        };
+        changed(
+            PlayerSetting::Name,
+            player_name(before, player) != player_name(after, player),
+        );
+        changed(
+            PlayerSetting::Civilization,
+            before.player_civilization(player) != after.player_civilization(player),
+        );
+        changed(
+            PlayerSetting::PlayerType,
+            before.player_type(player) != after.player_type(player),
+        );
+        changed(
+            PlayerSetting::Resources,
+            before.player_resources(player) != after.player_resources(player),
+        );
+        changed(
+            PlayerSetting::StartingAge,
+            before.player_starting_age(player) != after.player_starting_age(player),
+        );
+        changed(
+            PlayerSetting::Diplomacy,
+            players
+                .clone()
+                .any(|other| before.diplomacy(player, other) != after.diplomacy(player, other)),
+        );
+        changed(
+            PlayerSetting::DisabledTechs,
+            before.disabled_techs(player) != after.disabled_techs(player),
+        );
+        changed(
+            PlayerSetting::DisabledUnits,
+            before.disabled_units(player) != after.disabled_units(player),
+        );
+        changed(
+            PlayerSetting::DisabledBuildings,
+            before.disabled_buildings(player) != after.disabled_buildings(player),
+        );
+    }
+
+    if before.team_settings() != after.team_settings() {
+        changes.push(ScenarioChange::TeamSettingsChanged);
+    }
+
+    if before.victory_settings() != after.victory_settings() {
+        changes.push(ScenarioChange::VictorySettingsChanged);
+    }
+    for player in players {
+        if before.player_victory(player) != after.player_victory(player)
+            || before.allied_victory(player) != after.allied_victory(player)
+        {
+            changes.push(ScenarioChange::PlayerVictoryChanged { player });
+        }
+    }
+}
+
+fn diff_texts(before: &Scenario, after: &Scenario, changes: &mut Vec<ScenarioChange>) {
+    for text in ScenarioText::ALL {
+        if before.text(text) != after.text(text)
+            || before.text_string_key(text) != after.text_string_key(text)
+        {
+            changes.push(ScenarioChange::TextChanged(text));
+        }
+    }
+    for cinematic in [Cinematic::Pregame, Cinematic::Victory, Cinematic::Loss] {
+        if before.cinematic(cinematic) != after.cinematic(cinematic) {
+            changes.push(ScenarioChange::CinematicChanged(cinematic));
+        }
+    }
+}
+
+/// Find the differences between two versions of a scenario, sorted by section.
+pub(crate) fn diff(before: &Scenario, after: &Scenario) -> Vec<ScenarioChange> {
+    let mut changes = vec![];
+    diff_map(before, after, &mut changes);
+    diff_objects(before, after, &mut changes);
+    diff_triggers(before, after, &mut changes);
+    diff_players(before, after, &mut changes);
+    diff_texts(before, after, &mut changes);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffSection, PlayerSetting, ScenarioChange};
+    use crate::{Scenario, ScenarioText};
+    use std::fs::File;
+
+    #[test]
+    fn no_changes() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.diff(&scen.clone()), vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn find_changes() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let before = Scenario::read_from(&mut f)?;
+        let mut after = before.clone();
+
+        after.map_mut().tile_mut(3, 4).unwrap().terrain += 1;
+        let id = after.objects().next().unwrap().id;
+        after.object_mut(id).unwrap().position.0 += 1.0;
+        after.set_player_starting_age(2, crate::StartingAge::CastleAge)?;
+        after.set_text(ScenarioText::Hints, Some("New hint".to_string()));
+        after
+            .triggers_mut()
+            .unwrap()
+            .triggers_unordered_mut()
+            .nth(5)
+            .unwrap()
+            .set_name(Some("Renamed".to_string()));
+
+        let changes = before.diff(&after);
+        assert!(changes.contains(&ScenarioChange::TileChanged { x: 3, y: 4 }));
+        assert!(changes.iter().any(
+            |change| matches!(change, ScenarioChange::ObjectMoved { id: moved, .. } if *moved == id)
+        ));
+        assert!(changes.contains(&ScenarioChange::PlayerChanged {
+            player: 2,
+            setting: PlayerSetting::StartingAge
+        }));
+        assert!(changes.contains(&ScenarioChange::TextChanged(ScenarioText::Hints)));
+        assert!(changes
+            .iter()
+            .any(|change| matches!(change, ScenarioChange::TriggerModified { index: 5, .. })));
+        assert_eq!(changes.len(), 5);
+        assert!(changes
+            .windows(2)
+            .all(|pair| pair[0].section() <= pair[1].section()));
+        assert_eq!(changes[0].section(), DiffSection::Map);
+        Ok(())
+    }
+
+    #[test]
+    fn find_trigger_order_changes() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let before = Scenario::read_from(&mut f)?;
+        let mut after = before.clone();
+        after.triggers_mut().unwrap().trigger_order_mut().swap(0, 1);
+        assert_eq!(
+            before.diff(&after),
+            vec![ScenarioChange::TriggerOrderChanged]
+        );
+
+        // Adding a trigger at the end is not an order change.
+        let mut after = before.clone();
+        let trigger = after.triggers().unwrap().triggers().next().unwrap().clone();
+        after.triggers_mut().unwrap().push(trigger);
+        let changes = before.diff(&after);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(
+            changes[0],
+            ScenarioChange::TriggerAdded { index: 780, .. }
+        ));
+        Ok(())
+    }
+}
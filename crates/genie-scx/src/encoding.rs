@@ -0,0 +1,241 @@
+//! Text encodings for the strings in scenario files.
+//!
+//! Scenario files do not say which encoding their strings use: the game uses the code page of the
+//! system it runs on, so a scenario made on a Russian system stores WINDOWS-1251 text, and one
+//! made on a Chinese system stores GBK text. Definitive Edition scenarios use UTF-8.
+//!
+//! Strings are first read as WINDOWS-1252, which maps every byte to a character, and then
+//! converted to the actual encoding. Before writing, they are converted back.
+
+use crate::VersionBundle;
+use encoding_rs::{
+    Encoding, BIG5, EUC_KR, GBK, SHIFT_JIS, UTF_8, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252,
+    WINDOWS_1253, WINDOWS_1254, WINDOWS_1255, WINDOWS_1256, WINDOWS_1257, WINDOWS_1258,
+    WINDOWS_874,
+};
+use genie_support::{DecodeStringError, EncodeStringError};
+
+/// The encodings that scenario strings may be stored in: the Windows system code pages, and UTF-8.
+const SUPPORTED_ENCODINGS: [&Encoding; 15] = [
+    WINDOWS_1252,
+    WINDOWS_1250,
+    WINDOWS_1251,
+    WINDOWS_1253,
+    WINDOWS_1254,
+    WINDOWS_1255,
+    WINDOWS_1256,
+    WINDOWS_1257,
+    WINDOWS_1258,
+    WINDOWS_874,
+    GBK,
+    BIG5,
+    SHIFT_JIS,
+    EUC_KR,
+    UTF_8,
+];
+
+/// The minimum confidence for a detected encoding to be used. Binary junk in unused string
+/// buffers, which is common in old scenarios, is usually detected with a low confidence.
+const MIN_CONFIDENCE: f32 = 0.5;
+
+/// How to decode the strings in a scenario file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// Guess the encoding from the contents of the strings, falling back to UTF-8 for Definitive
+    /// Edition scenarios and to WINDOWS-1252 for older ones.
+    #[default]
+    Detect,
+    /// Use this encoding.
+    Fixed(&'static Encoding),
+}
+
+/// Find the Windows code page that best matches an encoding label from the detector.
+fn code_page_for_label(label: &str) -> Option<&'static Encoding> {
+    let encoding = Encoding::for_label(label.as_bytes())?;
+    let encoding = match encoding.name() {
+        "ISO-8859-2" => WINDOWS_1250,
+        "ISO-8859-5" | "KOI8-R" | "KOI8-U" | "IBM866" | "x-mac-cyrillic" => WINDOWS_1251,
+        "ISO-8859-7" => WINDOWS_1253,
+        "ISO-8859-8" | "ISO-8859-8-I" => WINDOWS_1255,
+        "EUC-JP" | "ISO-2022-JP" => SHIFT_JIS,
+        "gb18030" => GBK,
+        _ => encoding,
+    };
+    SUPPORTED_ENCODINGS.contains(&encoding).then_some(encoding)
+}
+
+/// Get the bytes that a string was stored as, if it was read as WINDOWS-1252.
+fn raw_bytes(string: &str) -> Vec<u8> {
+    WINDOWS_1252.encode(string).0.into_owned()
+}
+
+/// The encoding to use when the strings do not give any clues.
+fn default_encoding(version: &VersionBundle) -> &'static Encoding {
+    if version.is_age2_de() {
+        UTF_8
+    } else {
+        WINDOWS_1252
+    }
+}
+
+/// Guess the encoding of strings that were read as WINDOWS-1252.
+fn detect(strings: &[&mut String], version: &VersionBundle) -> &'static Encoding {
+    let mut bytes = vec![];
+    for string in strings.iter().filter(|string| !string.is_ascii()) {
+        bytes.extend(raw_bytes(string));
+        bytes.push(b'\n');
+    }
+    if bytes.is_empty() {
+        return default_encoding(version);
+    }
+    if std::str::from_utf8(&bytes).is_ok() {
+        return UTF_8;
+    }
+    let (label, confidence, _language) = chardet::detect(&bytes);
+    if confidence < MIN_CONFIDENCE {
+        return default_encoding(version);
+    }
+    code_page_for_label(&label).unwrap_or_else(|| default_encoding(version))
+}
+
+/// Decode a string that was read as WINDOWS-1252 using `encoding`. Fails if the result would not
+/// encode back to the same bytes, so writing the string again can not change it.
+fn decode(string: &str, encoding: &'static Encoding) -> Option<String> {
+    let bytes = raw_bytes(string);
+    let decoded = encoding.decode_without_bom_handling_and_without_replacement(&bytes)?;
+    let (encoded, _enc, failed) = encoding.encode(&decoded);
+    if failed || encoded != bytes {
+        return None;
+    }
+    Some(decoded.into_owned())
+}
+
+/// Convert strings that were read as WINDOWS-1252 to the given encoding, or a detected one.
+///
+/// Only the first `num_detected` strings are used to detect the encoding. Scenarios pass their
+/// header and TribeScen strings here, which are also all that `ScenarioSummary` reads, so a
+/// summary and a full read detect the same encoding.
+///
+/// When detecting, this falls back to WINDOWS-1252 if the strings can not all be decoded with the
+/// detected encoding, as every byte sequence is valid WINDOWS-1252. Returns the encoding that was
+/// used.
+pub(crate) fn decode_strings(
+    mut strings: Vec<&mut String>,
+    num_detected: usize,
+    requested: TextEncoding,
+    version: &VersionBundle,
+) -> Result<&'static Encoding, DecodeStringError> {
+    let encoding = match requested {
+        TextEncoding::Fixed(encoding) => encoding,
+        TextEncoding::Detect => detect(&strings[..num_detected.min(strings.len())], version),
+    };
+    if encoding == WINDOWS_1252 {
+        return Ok(encoding);
+    }
+
+    let decoded = strings
+        .iter()
+        .map(|string| decode(string, encoding))
+        .collect::<Option<Vec<_>>>();
+    match (decoded, requested) {
+        (Some(decoded), _) => {
+            for (string, decoded) in strings.iter_mut().zip(decoded) {
+                **string = decoded;
+            }
+            Ok(encoding)
+        }
+        (None, TextEncoding::Detect) => Ok(WINDOWS_1252),
+        (None, TextEncoding::Fixed(_)) => Err(DecodeStringError),
+    }
+}
+
+/// Convert strings to the form in which they are written as WINDOWS-1252, so they end up stored
+/// in the given encoding.
+pub(crate) fn encode_strings(
+    strings: Vec<&mut String>,
+    encoding: &'static Encoding,
+) -> Result<(), EncodeStringError> {
+    if encoding == WINDOWS_1252 {
+        return Ok(());
+    }
+    for string in strings {
+        let (bytes, _enc, failed) = encoding.encode(string);
+        if failed {
+            return Err(EncodeStringError);
+        }
+        *string = WINDOWS_1252
+            .decode_without_bom_handling(&bytes)
+            .0
+            .into_owned();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextEncoding;
+    use crate::{Error, Scenario, ScenarioSummary, ScenarioText};
+    use encoding_rs::{UTF_8, WINDOWS_1251, WINDOWS_1252};
+    use std::fs::File;
+
+    const RUSSIAN: &str = "Защищайте замок, пока не прибудет подкрепление с севера.";
+
+    #[test]
+    fn code_page_round_trip() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Jeremiah Johnson (Update).scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.set_encoding(WINDOWS_1251);
+        scen.set_text(ScenarioText::Description, Some(RUSSIAN.to_string()));
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.encoding(), WINDOWS_1251);
+        assert_eq!(scen.text(ScenarioText::Description), Some(RUSSIAN));
+
+        let scen = Scenario::read_from_with_encoding(&out[..], TextEncoding::Fixed(WINDOWS_1252))?;
+        assert_ne!(scen.text(ScenarioText::Description), Some(RUSSIAN));
+        let mut rewritten = vec![];
+        scen.write_to(&mut rewritten)?;
+        let scen = Scenario::read_from(&rewritten[..])?;
+        assert_eq!(scen.text(ScenarioText::Description), Some(RUSSIAN));
+        Ok(())
+    }
+
+    #[test]
+    fn unrepresentable_text() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Jeremiah Johnson (Update).scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.encoding(), WINDOWS_1252);
+        scen.set_text(ScenarioText::Description, Some(RUSSIAN.to_string()));
+        assert!(matches!(
+            scen.write_to(&mut vec![]),
+            Err(Error::EncodeStringError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn definitive_edition_defaults_to_utf8() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.encoding(), UTF_8);
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        assert_eq!(ScenarioSummary::read_from(&mut f)?.encoding(), UTF_8);
+
+        scen.set_text(ScenarioText::Description, Some(RUSSIAN.to_string()));
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.text(ScenarioText::Description), Some(RUSSIAN));
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_binary_junk() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Dawn of a New Age.scn")?;
+        let scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.encoding(), WINDOWS_1252);
+        Ok(())
+    }
+}
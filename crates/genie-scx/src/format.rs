@@ -2,29 +2,127 @@
 
 #![allow(clippy::cognitive_complexity)]
 
-use crate::ai::AIInfo;
+use crate::ai::{AIFile, AIInfo, AIScriptKind};
 use crate::bitmap::Bitmap;
+use crate::encoding;
 use crate::header::SCXHeader;
 use crate::map::Map;
 use crate::player::*;
+use crate::remap::permute;
+use crate::setup::TeamSettings;
+use crate::text::{Cinematic, ScenarioText};
 use crate::triggers::TriggerSystem;
 use crate::types::*;
 use crate::victory::*;
 use crate::{Error, Result, VersionBundle};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use encoding_rs::{Encoding, WINDOWS_1252};
 use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use genie_support::{
-    f32_eq, read_opt_u32, write_opt_str, write_str, ReadStringsExt, StringKey, UnitTypeID,
+    encode_str, read_opt_u32, write_opt_str, write_str, ReadStringsExt, StringKey, UnitTypeID,
 };
+use std::borrow::Cow;
 use std::convert::{TryFrom, TryInto};
 use std::io::{self, Read, Write};
 
-// pub enum LostInformation {
-//     DisabledTechs(i32, i32),
-//     DisabledUnits(i32, i32),
-//     DisabledBuildings(i32, i32),
-//     MapType,
-// }
+/// A piece of scenario data that can not be represented in the game version that the scenario is
+/// being written for.
+///
+/// Player numbers start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LostInformation {
+    /// Disabled techs for a player (player number, number of techs that do not fit).
+    DisabledTechs(i32, i32),
+    /// Disabled units for a player (player number, number of units that do not fit).
+    DisabledUnits(i32, i32),
+    /// Disabled buildings for a player (player number, number of buildings that do not fit).
+    DisabledBuildings(i32, i32),
+    /// A player's starting age (player number).
+    StartingAge(i32),
+    /// A player's starting ore or trade goods (player number).
+    StartingResources(i32),
+    /// The map type.
+    MapType,
+    /// Team locking and team count settings.
+    TeamSettings,
+    /// The multiplayer victory mode, score, or time limit.
+    VictorySettings,
+    /// Hints, victory and loss messages, history, or scouting text.
+    Messages,
+    /// The water definition.
+    WaterDefinition,
+    /// The colour mood.
+    ColorMood,
+    /// The collide-and-correct pathing setting.
+    CollideAndCorrect,
+    /// The villager force drop setting.
+    VillagerForceDrop,
+    /// Layered terrain and mask data (number of affected tiles).
+    LayeredTerrain(usize),
+    /// The setting to disable wave rendering.
+    RenderWaves,
+    /// Objects placed inside other objects (number of garrisoned objects).
+    GarrisonedObjects(usize),
+    /// All triggers (number of triggers).
+    Triggers(usize),
+    /// Short descriptions, header and mute settings on triggers (number of affected triggers).
+    TriggerDisplayFields(usize),
+    /// Trigger variables and enabled techs.
+    TriggerVariables,
+    /// Embedded AI files.
+    AIFiles,
+    /// The author name.
+    AuthorName,
+    /// HD Edition DLC information.
+    DLCOptions,
+}
+
+impl std::fmt::Display for LostInformation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LostInformation::DisabledTechs(player, num) => {
+                write!(f, "{} disabled techs for player {}", num, player)
+            }
+            LostInformation::DisabledUnits(player, num) => {
+                write!(f, "{} disabled units for player {}", num, player)
+            }
+            LostInformation::DisabledBuildings(player, num) => {
+                write!(f, "{} disabled buildings for player {}", num, player)
+            }
+            LostInformation::StartingAge(player) => {
+                write!(f, "starting age for player {}", player)
+            }
+            LostInformation::StartingResources(player) => {
+                write!(f, "starting ore and trade goods for player {}", player)
+            }
+            LostInformation::MapType => f.write_str("map type"),
+            LostInformation::TeamSettings => f.write_str("team settings"),
+            LostInformation::VictorySettings => f.write_str("multiplayer victory settings"),
+            LostInformation::Messages => f.write_str("scenario messages"),
+            LostInformation::WaterDefinition => f.write_str("water definition"),
+            LostInformation::ColorMood => f.write_str("colour mood"),
+            LostInformation::CollideAndCorrect => f.write_str("collide-and-correct setting"),
+            LostInformation::VillagerForceDrop => f.write_str("villager force drop setting"),
+            LostInformation::LayeredTerrain(num) => {
+                write!(f, "layered terrain on {} tiles", num)
+            }
+            LostInformation::RenderWaves => f.write_str("wave rendering setting"),
+            LostInformation::GarrisonedObjects(num) => write!(f, "{} garrisoned objects", num),
+            LostInformation::Triggers(num) => write!(f, "{} triggers", num),
+            LostInformation::TriggerDisplayFields(num) => {
+                write!(f, "display settings on {} triggers", num)
+            }
+            LostInformation::TriggerVariables => f.write_str("trigger variables"),
+            LostInformation::AIFiles => f.write_str("embedded AI files"),
+            LostInformation::AuthorName => f.write_str("author name"),
+            LostInformation::DLCOptions => f.write_str("DLC options"),
+        }
+    }
+}
+
+fn is_set(text: &Option<String>) -> bool {
+    text.as_deref().map_or(false, |text| !text.is_empty())
+}
 
 /// An object placed in the scenario.
 #[derive(Debug, Clone, Default)]
@@ -57,12 +155,12 @@ impl ScenarioObject {
         let object_type = input.read_u16::<LE>()?.into();
         let state = input.read_u8()?;
         let angle = input.read_f32::<LE>()?;
-        let frame = if version < SCXVersion(*b"1.15") {
+        let frame = if !version.has_object_frames() {
             -1
         } else {
             input.read_i16::<LE>()?
         };
-        let garrisoned_in = if version < SCXVersion(*b"1.13") {
+        let garrisoned_in = if !version.supports_garrisoned_objects() {
             None
         } else {
             Some(input.read_i32::<LE>()?)
@@ -73,7 +171,7 @@ impl ScenarioObject {
         })
         .and_then(|id| match id {
             // 0 means -1 in "recent" versions
-            0 if version > SCXVersion(*b"1.12") => None,
+            0 if version.supports_garrisoned_objects() => None,
             id => Some(id),
         });
 
@@ -96,10 +194,10 @@ impl ScenarioObject {
         output.write_u16::<LE>(self.object_type.into())?;
         output.write_u8(self.state)?;
         output.write_f32::<LE>(self.angle)?;
-        if version > SCXVersion(*b"1.14") {
+        if version.has_object_frames() {
             output.write_i16::<LE>(self.frame)?;
         }
-        if version > SCXVersion(*b"1.12") {
+        if version.supports_garrisoned_objects() {
             match self.garrisoned_in {
                 Some(id) => output.write_i32::<LE>(id)?,
                 None => output.write_i32::<LE>(-1)?,
@@ -114,10 +212,10 @@ pub(crate) struct RGEScen {
     /// Data version.
     pub(crate) version: f32,
     /// Names for each player.
-    player_names: Vec<Option<String>>,
+    pub(crate) player_names: Vec<Option<String>>,
     /// Name IDs for each player.
-    player_string_table: Vec<Option<StringKey>>,
-    player_base_properties: Vec<PlayerBaseProperties>,
+    pub(crate) player_string_table: Vec<Option<StringKey>>,
+    pub(crate) player_base_properties: Vec<PlayerBaseProperties>,
     victory_conquest: bool,
     /// File name of this scenario.
     pub(crate) name: String,
@@ -136,8 +234,8 @@ pub(crate) struct RGEScen {
     pregame_cinematic: Option<String>,
     victory_cinematic: Option<String>,
     loss_cinematic: Option<String>,
-    #[allow(dead_code)]
     mission_bmp: Option<String>,
+    mission_picture: Option<Bitmap>,
     player_build_lists: Vec<Option<String>>,
     player_city_plans: Vec<Option<String>>,
     player_ai_rules: Vec<Option<String>>,
@@ -157,7 +255,7 @@ impl RGEScen {
         }
 
         let mut player_string_table = vec![None; 16];
-        if version > 1.16 {
+        if supports_player_name_keys(version) {
             for string_id in player_string_table.iter_mut() {
                 *string_id = read_opt_u32(&mut input)?;
             }
@@ -190,7 +288,7 @@ impl RGEScen {
             // assert_eq!(_timeline_available, 0, "Unexpected RGE_Timeline");
         }
 
-        if version >= 1.28 {
+        if supports_civ_lock(version) {
             let _civ_lock = &mut [0; 16];
             input.read_u32_into::<LE>(_civ_lock)?;
         }
@@ -204,7 +302,7 @@ impl RGEScen {
             win_message_string_table,
             loss_message_string_table,
             history_string_table,
-        ) = if version >= 1.16 {
+        ) = if supports_text_keys(version) {
             (
                 read_opt_u32(&mut input)?,
                 read_opt_u32(&mut input)?,
@@ -216,7 +314,7 @@ impl RGEScen {
             Default::default()
         };
 
-        let scout_string_table = if version >= 1.22 {
+        let scout_string_table = if supports_scout_text(version) {
             read_opt_u32(&mut input)?
         } else {
             Default::default()
@@ -224,7 +322,8 @@ impl RGEScen {
 
         let description = input.read_u16_length_prefixed_str()?;
 
-        let (hints, win_message, loss_message, history) = if version >= 1.11 {
+        let (hints, win_message, loss_message, history) = if supports_instruction_messages(version)
+        {
             let hints = input.read_u16_length_prefixed_str()?;
             let win_message = input.read_u16_length_prefixed_str()?;
             let loss_message = input.read_u16_length_prefixed_str()?;
@@ -234,7 +333,7 @@ impl RGEScen {
             (None, None, None, None)
         };
 
-        let scout = if version >= 1.22 {
+        let scout = if supports_scout_text(version) {
             input.read_u16_length_prefixed_str()?
         } else {
             None
@@ -248,13 +347,13 @@ impl RGEScen {
         let victory_cinematic = input.read_u16_length_prefixed_str()?;
         let loss_cinematic = input.read_u16_length_prefixed_str()?;
 
-        let mission_bmp = if version >= 1.09 {
+        let mission_bmp = if supports_bitmap_names(version) {
             input.read_u16_length_prefixed_str()?
         } else {
             None
         };
 
-        let _mission_picture = if version >= 1.10 {
+        let mission_picture = if supports_bitmaps(version) {
             Bitmap::read_from(&mut input)?
         } else {
             None
@@ -271,7 +370,7 @@ impl RGEScen {
         }
 
         let mut player_ai_rules = vec![None; 16];
-        if version >= 1.08 {
+        if supports_ai_rules(version) {
             for ai_rules in player_ai_rules.iter_mut() {
                 *ai_rules = input.read_u16_length_prefixed_str()?;
             }
@@ -281,7 +380,7 @@ impl RGEScen {
         for files in player_files.iter_mut() {
             let build_list_length = input.read_i32::<LE>()? as usize;
             let city_plan_length = input.read_i32::<LE>()? as usize;
-            let ai_rules_length = if version >= 1.08 {
+            let ai_rules_length = if supports_ai_rules(version) {
                 input.read_i32::<LE>()? as usize
             } else {
                 0
@@ -327,6 +426,7 @@ impl RGEScen {
             victory_cinematic,
             loss_cinematic,
             mission_bmp,
+            mission_picture,
             player_build_lists,
             player_city_plans,
             player_ai_rules,
@@ -343,15 +443,14 @@ impl RGEScen {
             for name in &self.player_names {
                 let mut padded_bytes = Vec::with_capacity(256);
                 if let Some(ref name) = name {
-                    let name_bytes = name.as_bytes();
-                    padded_bytes.write_all(name_bytes)?;
+                    padded_bytes.write_all(&encode_str(name)?)?;
                 }
                 padded_bytes.extend(vec![0; 256 - padded_bytes.len()]);
                 output.write_all(&padded_bytes)?;
             }
         }
 
-        if version > 1.16 {
+        if supports_player_name_keys(version) {
             assert_eq!(self.player_string_table.len(), 16);
             for id in &self.player_string_table {
                 write_opt_string_key(&mut output, id)?;
@@ -377,7 +476,7 @@ impl RGEScen {
         output.write_i16::<LE>(0)?;
         output.write_f32::<LE>(-1.0)?;
 
-        if version >= 1.28 {
+        if supports_civ_lock(version) {
             // Civ Lock data
             for _ in 0..16 {
                 output.write_u32::<LE>(0)?;
@@ -386,42 +485,49 @@ impl RGEScen {
 
         write_str(&mut output, &self.name)?;
 
-        if version >= 1.16 {
+        if supports_text_keys(version) {
             write_opt_string_key(&mut output, &self.description_string_table)?;
             write_opt_string_key(&mut output, &self.hints_string_table)?;
             write_opt_string_key(&mut output, &self.win_message_string_table)?;
             write_opt_string_key(&mut output, &self.loss_message_string_table)?;
             write_opt_string_key(&mut output, &self.history_string_table)?;
         }
-        if version >= 1.22 {
+        if supports_scout_text(version) {
             write_opt_string_key(&mut output, &self.scout_string_table)?;
         }
 
         write_opt_str(&mut output, &self.description)?;
-        if version >= 1.11 {
+        if supports_instruction_messages(version) {
             write_opt_str(&mut output, &self.hints)?;
             write_opt_str(&mut output, &self.win_message)?;
             write_opt_str(&mut output, &self.loss_message)?;
             write_opt_str(&mut output, &self.history)?;
         }
-        if version >= 1.22 {
+        if supports_scout_text(version) {
             write_opt_str(&mut output, &self.scout)?;
         }
 
         write_opt_str(&mut output, &self.pregame_cinematic)?;
         write_opt_str(&mut output, &self.victory_cinematic)?;
         write_opt_str(&mut output, &self.loss_cinematic)?;
-        if version >= 1.09 {
-            // mission_bmp
-            write_opt_str(&mut output, &None)?;
+        if supports_bitmap_names(version) {
+            write_opt_str(&mut output, &self.mission_bmp)?;
         }
 
-        if version >= 1.10 {
-            // mission_picture
-            output.write_u32::<LE>(0)?;
-            output.write_u32::<LE>(0)?;
-            output.write_u32::<LE>(0)?;
-            output.write_u16::<LE>(1)?;
+        if supports_bitmaps(version) {
+            let palettized = match &self.mission_picture {
+                Some(picture) if !supports_true_color_bitmaps(version) => picture.to_palettized(),
+                _ => None,
+            };
+            match palettized.as_ref().or(self.mission_picture.as_ref()) {
+                Some(picture) => picture.write_to(&mut output)?,
+                None => {
+                    output.write_u32::<LE>(0)?;
+                    output.write_u32::<LE>(0)?;
+                    output.write_u32::<LE>(0)?;
+                    output.write_u16::<LE>(1)?;
+                }
+            }
         }
 
         assert_eq!(self.player_build_lists.len(), 16);
@@ -434,7 +540,7 @@ impl RGEScen {
             write_opt_str(&mut output, city_plan)?;
         }
 
-        if version >= 1.08 {
+        if supports_ai_rules(version) {
             assert_eq!(self.player_ai_rules.len(), 16);
             for ai_rules in &self.player_ai_rules {
                 write_opt_str(&mut output, ai_rules)?;
@@ -443,32 +549,24 @@ impl RGEScen {
 
         assert_eq!(self.player_files.len(), 16);
         for files in &self.player_files {
-            if let Some(build_list) = &files.build_list {
-                output.write_u32::<LE>(build_list.len() as u32)?;
-            } else {
-                output.write_u32::<LE>(0)?;
-            }
-            if let Some(city_plan) = &files.city_plan {
-                output.write_u32::<LE>(city_plan.len() as u32)?;
-            } else {
-                output.write_u32::<LE>(0)?;
-            }
-            if version >= 1.08 {
-                if let Some(ai_rules) = &files.ai_rules {
-                    output.write_u32::<LE>(ai_rules.len() as u32)?;
-                } else {
-                    output.write_u32::<LE>(0)?;
-                }
+            let build_list = files.build_list.as_deref().map(encode_str).transpose()?;
+            let city_plan = files.city_plan.as_deref().map(encode_str).transpose()?;
+            let ai_rules = files.ai_rules.as_deref().map(encode_str).transpose()?;
+            let len = |bytes: &Option<Cow<'_, [u8]>>| bytes.as_ref().map_or(0, |bytes| bytes.len());
+            output.write_u32::<LE>(len(&build_list) as u32)?;
+            output.write_u32::<LE>(len(&city_plan) as u32)?;
+            if supports_ai_rules(version) {
+                output.write_u32::<LE>(len(&ai_rules) as u32)?;
             }
-            if let Some(build_list) = &files.build_list {
-                output.write_all(build_list.as_bytes())?;
+            if let Some(build_list) = &build_list {
+                output.write_all(build_list)?;
             }
-            if let Some(city_plan) = &files.city_plan {
-                output.write_all(city_plan.as_bytes())?;
+            if let Some(city_plan) = &city_plan {
+                output.write_all(city_plan)?;
             }
-            if version >= 1.08 {
-                if let Some(ai_rules) = &files.ai_rules {
-                    output.write_all(ai_rules.as_bytes())?;
+            if supports_ai_rules(version) {
+                if let Some(ai_rules) = &ai_rules {
+                    output.write_all(ai_rules)?;
                 }
             }
         }
@@ -484,6 +582,78 @@ impl RGEScen {
 
         Ok(())
     }
+
+    /// Get all the strings in the scenario data, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        let texts = [
+            &mut self.description,
+            &mut self.hints,
+            &mut self.win_message,
+            &mut self.loss_message,
+            &mut self.history,
+            &mut self.scout,
+            &mut self.pregame_cinematic,
+            &mut self.victory_cinematic,
+            &mut self.loss_cinematic,
+            &mut self.mission_bmp,
+        ];
+        let files = self.player_files.iter_mut().flat_map(|files| {
+            [
+                &mut files.build_list,
+                &mut files.city_plan,
+                &mut files.ai_rules,
+            ]
+        });
+        std::iter::once(&mut self.name)
+            .chain(self.player_names.iter_mut().flatten())
+            .chain(texts.into_iter().flatten())
+            .chain(self.player_build_lists.iter_mut().flatten())
+            .chain(self.player_city_plans.iter_mut().flatten())
+            .chain(self.player_ai_rules.iter_mut().flatten())
+            .chain(files.flatten())
+    }
+
+    /// Get a kind of embedded AI file for a player.
+    pub(crate) fn ai_script(&self, index: usize, kind: AIScriptKind) -> Option<AIFile> {
+        let files = &self.player_files[index];
+        let (name, content) = match kind {
+            AIScriptKind::BuildList => (&self.player_build_lists[index], &files.build_list),
+            AIScriptKind::CityPlan => (&self.player_city_plans[index], &files.city_plan),
+            AIScriptKind::Rules => (&self.player_ai_rules[index], &files.ai_rules),
+        };
+        // Without content, the name refers to an AI that comes with the game.
+        let content = content.as_ref()?;
+        Some(AIFile::new(
+            name.clone().unwrap_or_default(),
+            content.clone(),
+        ))
+    }
+
+    /// Replace or remove a kind of AI file for a player, and return the previous one.
+    pub(crate) fn set_ai_script(
+        &mut self,
+        index: usize,
+        kind: AIScriptKind,
+        file: Option<AIFile>,
+    ) -> Option<AIFile> {
+        let previous = self.ai_script(index, kind);
+        let files = &mut self.player_files[index];
+        let (name, content) = match kind {
+            AIScriptKind::BuildList => (&mut self.player_build_lists[index], &mut files.build_list),
+            AIScriptKind::CityPlan => (&mut self.player_city_plans[index], &mut files.city_plan),
+            AIScriptKind::Rules => (&mut self.player_ai_rules[index], &mut files.ai_rules),
+        };
+        let non_empty = |string: &str| Some(string.to_string()).filter(|string| !string.is_empty());
+        *name = file.as_ref().and_then(|file| non_empty(file.filename()));
+        *content = file.as_ref().and_then(|file| non_empty(file.content()));
+        previous
+    }
+
+    /// Does any player have an AI rules script? Those can only be stored since data version 1.08.
+    pub(crate) fn has_ai_rules(&self) -> bool {
+        (0..self.player_files.len())
+            .any(|index| self.ai_script(index, AIScriptKind::Rules).is_some())
+    }
 }
 
 /// Embeddable scenario data. This includes all scenario settings, but not map data, triggers, and
@@ -499,7 +669,7 @@ pub struct TribeScen {
     /// things are duplicate).
     pub(crate) base: RGEScen,
     /// Starting resources for players.
-    player_start_resources: Vec<PlayerStartResources>,
+    pub(crate) player_start_resources: Vec<PlayerStartResources>,
     /// Victory settings.
     victory: VictoryInfo,
     /// Whether all victory conditions need to be met for victory to occur.
@@ -511,30 +681,18 @@ pub struct TribeScen {
     /// Time at which the highest-scoring player will win the multiplayer match.
     victory_time: i32,
     /// Initial diplomacy stances between players.
-    diplomacy: Vec<Vec<DiplomaticStance>>,
+    pub(crate) diplomacy: Vec<Vec<DiplomaticStance>>,
     legacy_victory_info: Vec<Vec<LegacyVictoryInfo>>,
     /// Whether Allied Victory is enabled for each player.
-    allied_victory: Vec<i32>,
-    teams_locked: bool,
-    can_change_teams: bool,
-    random_start_locations: bool,
-    max_teams: u8,
-    /// Number of disabled techs per player.
-    ///
-    /// TODO only use `disabled_techs` for this
-    num_disabled_techs: Vec<i32>,
+    pub(crate) allied_victory: Vec<i32>,
+    pub(crate) teams_locked: bool,
+    pub(crate) can_change_teams: bool,
+    pub(crate) random_start_locations: bool,
+    pub(crate) max_teams: u8,
     /// Disabled tech IDs per player.
     disabled_techs: Vec<Vec<i32>>,
-    /// Number of disabled units per player.
-    ///
-    /// TODO only use `disabled_units` for this
-    num_disabled_units: Vec<i32>,
     /// Disabled unit IDs per player.
     disabled_units: Vec<Vec<i32>>,
-    /// Number of disabled buildings per player.
-    ///
-    /// TODO only use `disabled_buildings` for this
-    num_disabled_buildings: Vec<i32>,
     /// Disabled building IDs per player.
     disabled_buildings: Vec<Vec<i32>>,
     /// (What exactly?)
@@ -552,7 +710,7 @@ pub struct TribeScen {
     /// Whether "All Techs" is enabled.
     all_techs: bool,
     /// The starting age per player.
-    player_start_ages: Vec<StartingAge>,
+    pub(crate) player_start_ages: Vec<StartingAge>,
     /// The initial camera location.
     view: (i32, i32),
     /// The map type.
@@ -617,20 +775,14 @@ impl TribeScen {
         let victory = VictoryInfo::read_from(&mut input)?;
         let victory_all_flag = input.read_i32::<LE>()? != 0;
 
-        let mp_victory_type = if version >= 1.13 {
-            input.read_i32::<LE>()?
-        } else {
-            4
-        };
-        let victory_score = if version >= 1.13 {
-            input.read_i32::<LE>()?
-        } else {
-            900
-        };
-        let victory_time = if version >= 1.13 {
-            input.read_i32::<LE>()?
+        let (mp_victory_type, victory_score, victory_time) = if supports_victory_settings(version) {
+            (
+                input.read_i32::<LE>()?,
+                input.read_i32::<LE>()?,
+                input.read_i32::<LE>()?,
+            )
         } else {
-            9000
+            (4, 900, 9000)
         };
 
         log::debug!(
@@ -664,88 +816,106 @@ impl TribeScen {
             *setting = input.read_i32::<LE>()?;
         }
 
-        let (teams_locked, can_change_teams, random_start_locations, max_teams) = if version >= 1.24
-        {
-            (
-                input.read_i8()? != 0,
-                input.read_i8()? != 0,
-                input.read_i8()? != 0,
-                input.read_u8()?,
-            )
-        } else if f32_eq!(version, 1.23) {
-            (input.read_i32::<LE>()? != 0, true, true, 4)
-        } else {
-            (false, true, true, 4)
-        };
+        let (teams_locked, can_change_teams, random_start_locations, max_teams) =
+            match stored_team_settings(version) {
+                StoredTeamSettings::All => (
+                    input.read_i8()? != 0,
+                    input.read_i8()? != 0,
+                    input.read_i8()? != 0,
+                    input.read_u8()?,
+                ),
+                StoredTeamSettings::LockedOnly => (input.read_i32::<LE>()? != 0, true, true, 4),
+                StoredTeamSettings::None => (false, true, true, 4),
+            };
 
         let mut num_disabled_techs = vec![0; 16];
-        let mut disabled_techs = vec![vec![]; 16];
+        let mut disabled_techs: Vec<Vec<i32>> = vec![vec![]; 16];
         let mut num_disabled_units = vec![0; 16];
-        let mut disabled_units = vec![vec![]; 16];
+        let mut disabled_units: Vec<Vec<i32>> = vec![vec![]; 16];
         let mut num_disabled_buildings = vec![0; 16];
-        let mut disabled_buildings = vec![vec![]; 16];
+        let mut disabled_buildings: Vec<Vec<i32>> = vec![vec![]; 16];
+
+        match disabled_lists(version) {
+            DisabledLists::Exact => {
+                // Definitive Edition only stores the exact number of disabled
+                // techs/units/buildings.
+                input.read_i32_into::<LE>(&mut num_disabled_techs)?;
+                for (player_disabled_techs, &num) in
+                    disabled_techs.iter_mut().zip(num_disabled_techs.iter())
+                {
+                    *player_disabled_techs = vec![0; num as usize];
+                    input.read_i32_into::<LE>(player_disabled_techs)?;
+                }
 
-        if version >= 1.28 {
-            // Definitive Edition only stores the exact number of disabled techs/units/buildings.
-            input.read_i32_into::<LE>(&mut num_disabled_techs)?;
-            for (player_disabled_techs, &num) in
-                disabled_techs.iter_mut().zip(num_disabled_techs.iter())
-            {
-                *player_disabled_techs = vec![0; num as usize];
-                input.read_i32_into::<LE>(player_disabled_techs)?;
-            }
+                input.read_i32_into::<LE>(&mut num_disabled_units)?;
+                for (player_disabled_units, &num) in
+                    disabled_units.iter_mut().zip(num_disabled_units.iter())
+                {
+                    *player_disabled_units = vec![0; num as usize];
+                    input.read_i32_into::<LE>(player_disabled_units)?;
+                }
 
-            input.read_i32_into::<LE>(&mut num_disabled_units)?;
-            for (player_disabled_units, &num) in
-                disabled_units.iter_mut().zip(num_disabled_units.iter())
-            {
-                *player_disabled_units = vec![0; num as usize];
-                input.read_i32_into::<LE>(player_disabled_units)?;
+                input.read_i32_into::<LE>(&mut num_disabled_buildings)?;
+                for (player_disabled_buildings, &num) in disabled_buildings
+                    .iter_mut()
+                    .zip(num_disabled_buildings.iter())
+                {
+                    *player_disabled_buildings = vec![0; num as usize];
+                    input.read_i32_into::<LE>(player_disabled_buildings)?;
+                }
             }
+            DisabledLists::Padded {
+                techs,
+                units,
+                buildings,
+            } => {
+                // AoC and friends store up to 20 or 30 of each.
+                input.read_i32_into::<LE>(&mut num_disabled_techs)?;
+                for player_disabled_techs in disabled_techs.iter_mut() {
+                    *player_disabled_techs = vec![0; techs];
+                    input.read_i32_into::<LE>(player_disabled_techs)?;
+                }
 
-            input.read_i32_into::<LE>(&mut num_disabled_buildings)?;
-            for (player_disabled_buildings, &num) in disabled_buildings
-                .iter_mut()
-                .zip(num_disabled_buildings.iter())
-            {
-                *player_disabled_buildings = vec![0; num as usize];
-                input.read_i32_into::<LE>(player_disabled_buildings)?;
-            }
-        } else if version >= 1.18 {
-            // AoC and friends store up to 20 or 30 of each.
-            input.read_i32_into::<LE>(&mut num_disabled_techs)?;
-            for player_disabled_techs in disabled_techs.iter_mut() {
-                *player_disabled_techs = vec![0; 30];
-                input.read_i32_into::<LE>(player_disabled_techs)?;
-            }
+                input.read_i32_into::<LE>(&mut num_disabled_units)?;
+                for player_disabled_units in disabled_units.iter_mut() {
+                    *player_disabled_units = vec![0; units];
+                    input.read_i32_into::<LE>(player_disabled_units)?;
+                }
 
-            input.read_i32_into::<LE>(&mut num_disabled_units)?;
-            for player_disabled_units in disabled_units.iter_mut() {
-                *player_disabled_units = vec![0; 30];
-                input.read_i32_into::<LE>(player_disabled_units)?;
-            }
+                input.read_i32_into::<LE>(&mut num_disabled_buildings)?;
+                for player_disabled_buildings in disabled_buildings.iter_mut() {
+                    *player_disabled_buildings = vec![0; buildings];
+                    input.read_i32_into::<LE>(player_disabled_buildings)?;
+                }
 
-            input.read_i32_into::<LE>(&mut num_disabled_buildings)?;
-            let max_disabled_buildings = if version >= 1.25 { 30 } else { 20 };
-            for player_disabled_buildings in disabled_buildings.iter_mut() {
-                *player_disabled_buildings = vec![0; max_disabled_buildings];
-                input.read_i32_into::<LE>(player_disabled_buildings)?;
+                // Drop the padding.
+                for (lists, nums) in [
+                    (&mut disabled_techs, &num_disabled_techs),
+                    (&mut disabled_units, &num_disabled_units),
+                    (&mut disabled_buildings, &num_disabled_buildings),
+                ] {
+                    for (list, &num) in lists.iter_mut().zip(nums.iter()) {
+                        list.truncate(usize::try_from(num).unwrap_or(0));
+                    }
+                }
             }
-        } else if version > 1.03 {
-            // Old scenarios only allowed disabling up to 20 techs per player.
-            for i in 0..16 {
-                let player_disabled_techs = &mut disabled_techs[i];
-                *player_disabled_techs = vec![0; 20];
-                input.read_i32_into::<LE>(player_disabled_techs)?;
-                // The number of disabled techs wasn't stored either, so we need to guess it!
-                num_disabled_techs[i] = player_disabled_techs
-                    .iter()
-                    .position(|val| *val <= 0)
-                    .map(|index| (index as i32) + 1)
-                    .unwrap_or(0);
+            DisabledLists::TechsOnly(techs) => {
+                // Old scenarios only allowed disabling up to 20 techs per player.
+                for player_disabled_techs in disabled_techs.iter_mut() {
+                    *player_disabled_techs = vec![0; techs];
+                    input.read_i32_into::<LE>(player_disabled_techs)?;
+                    // The number of disabled techs wasn't stored either, so we need to guess it!
+                    // The list ends before the first empty entry, or is full.
+                    let num = player_disabled_techs
+                        .iter()
+                        .position(|val| *val <= 0)
+                        .unwrap_or(player_disabled_techs.len());
+                    player_disabled_techs.truncate(num);
+                }
+            }
+            DisabledLists::Unsupported => {
+                // <= 1.03 did not support disabling anything
             }
-        } else {
-            // <= 1.03 did not support disabling anything
         }
 
         let combat_mode = if version > 1.04 {
@@ -779,7 +949,7 @@ impl TribeScen {
             (-1, -1)
         };
 
-        let map_type = if version >= 1.21 {
+        let map_type = if supports_map_type(version) {
             match input.read_i32::<LE>()? {
                 // HD Edition uses -2 instead of -1?
                 -2 | -1 => None,
@@ -799,24 +969,24 @@ impl TribeScen {
         let mut collide_and_correct = false;
         let mut villager_force_drop = false;
 
-        if version >= 1.35 {
+        if stores_trigger_count(version) {
             // Duplicated here from TriggerSystem … we can discard it because the TriggerSystem
             // will read the same number later.
             let _trigger_count = input.read_u32::<LE>()?;
         }
-        if version >= 1.30 {
+        if supports_water_definition(version) {
             let _str_signature = input.read_u16::<LE>()?;
             water_definition = input.read_u16_length_prefixed_str()?;
         }
 
-        if version >= 1.32 {
+        if supports_color_mood(version) {
             let _str_signature = input.read_u16::<LE>()?;
             color_mood = input.read_u16_length_prefixed_str()?;
         }
-        if version >= 1.36 {
+        if supports_collide_and_correct(version) {
             collide_and_correct = input.read_u8()? != 0;
         }
-        if version >= 1.37 {
+        if supports_villager_force_drop(version) {
             villager_force_drop = input.read_u8()? != 0;
         }
 
@@ -835,11 +1005,8 @@ impl TribeScen {
             can_change_teams,
             random_start_locations,
             max_teams,
-            num_disabled_techs,
             disabled_techs,
-            num_disabled_units,
             disabled_units,
-            num_disabled_buildings,
             disabled_buildings,
             combat_mode,
             naval_mode,
@@ -864,8 +1031,7 @@ impl TribeScen {
             for name in &self.base.player_names {
                 let mut padded_bytes = Vec::with_capacity(256);
                 if let Some(ref name) = name {
-                    let name_bytes = name.as_bytes();
-                    padded_bytes.write_all(name_bytes)?;
+                    padded_bytes.write_all(&encode_str(name)?)?;
                 }
                 padded_bytes.extend(vec![0; 256 - padded_bytes.len()]);
                 output.write_all(&padded_bytes)?;
@@ -896,7 +1062,7 @@ impl TribeScen {
         self.victory.write_to(&mut output)?;
         output.write_i32::<LE>(i32::from(self.victory_all_flag))?;
 
-        if version >= 1.13 {
+        if supports_victory_settings(version) {
             output.write_i32::<LE>(self.mp_victory_type)?;
             output.write_i32::<LE>(self.victory_score)?;
             output.write_i32::<LE>(self.victory_time)?;
@@ -925,118 +1091,109 @@ impl TribeScen {
             output.write_i32::<LE>(*value)?;
         }
 
-        if version >= 1.24 {
-            output.write_i8(i8::from(self.teams_locked))?;
-            output.write_i8(i8::from(self.can_change_teams))?;
-            output.write_i8(i8::from(self.random_start_locations))?;
-            output.write_u8(self.max_teams)?;
-        } else if f32_eq!(version, 1.23) {
-            output.write_i32::<LE>(i32::from(self.teams_locked))?;
-        }
-
-        if version >= 1.28 {
-            for num in &self.num_disabled_techs {
-                output.write_i32::<LE>(*num)?;
-            }
-            for (player_disabled_techs, &num) in self
-                .disabled_techs
-                .iter()
-                .zip(self.num_disabled_techs.iter())
-            {
-                for i in 0..num as usize {
-                    output.write_i32::<LE>(*player_disabled_techs.get(i).unwrap_or(&-1))?;
-                }
+        match stored_team_settings(version) {
+            StoredTeamSettings::All => {
+                output.write_i8(i8::from(self.teams_locked))?;
+                output.write_i8(i8::from(self.can_change_teams))?;
+                output.write_i8(i8::from(self.random_start_locations))?;
+                output.write_u8(self.max_teams)?;
             }
-
-            for num in &self.num_disabled_units {
-                output.write_i32::<LE>(*num)?;
+            StoredTeamSettings::LockedOnly => {
+                output.write_i32::<LE>(i32::from(self.teams_locked))?;
             }
-            for (player_disabled_units, &num) in self
-                .disabled_units
-                .iter()
-                .zip(self.num_disabled_units.iter())
-            {
-                for i in 0..num as usize {
-                    output.write_i32::<LE>(*player_disabled_units.get(i).unwrap_or(&-1))?;
+            StoredTeamSettings::None => {}
+        }
+
+        match disabled_lists(version) {
+            DisabledLists::Exact => {
+                for lists in [
+                    &self.disabled_techs,
+                    &self.disabled_units,
+                    &self.disabled_buildings,
+                ] {
+                    for list in lists {
+                        output.write_i32::<LE>(list.len() as i32)?;
+                    }
+                    for list in lists {
+                        for &id in list {
+                            output.write_i32::<LE>(id)?;
+                        }
+                    }
                 }
             }
-
-            for num in &self.num_disabled_buildings {
-                output.write_i32::<LE>(*num)?;
-            }
-            for (player_disabled_buildings, &num) in self
-                .disabled_buildings
-                .iter()
-                .zip(self.num_disabled_buildings.iter())
-            {
-                for i in 0..num as usize {
-                    output.write_i32::<LE>(*player_disabled_buildings.get(i).unwrap_or(&-1))?;
+            DisabledLists::Padded {
+                techs,
+                units,
+                buildings,
+            } => {
+                let most = |lists: &[Vec<i32>]| lists.iter().map(Vec::len).max().unwrap_or(0);
+                let most_techs = most(&self.disabled_techs);
+                if most_techs > techs {
+                    return Err(Error::TooManyDisabledTechsForVersionError(
+                        most_techs as i32,
+                        techs as i32,
+                    ));
                 }
-            }
-        } else if version >= 1.18 {
-            let max_disabled_buildings = if version >= 1.25 { 30 } else { 20 };
-            let most = *self.num_disabled_buildings.iter().max().unwrap_or(&0);
-            if most > max_disabled_buildings {
-                return Err(Error::TooManyDisabledBuildingsError(
-                    most,
-                    max_disabled_buildings,
-                ));
-            }
-
-            for num in &self.num_disabled_techs {
-                output.write_i32::<LE>(*num)?;
-            }
-            for player_disabled_techs in &self.disabled_techs {
-                for i in 0..30 {
-                    output.write_i32::<LE>(*player_disabled_techs.get(i).unwrap_or(&-1))?;
+                let most_units = most(&self.disabled_units);
+                if most_units > units {
+                    return Err(Error::TooManyDisabledUnitsError(
+                        most_units as i32,
+                        units as i32,
+                    ));
                 }
-            }
-
-            for num in &self.num_disabled_units {
-                output.write_i32::<LE>(*num)?;
-            }
-            for player_disabled_units in &self.disabled_units {
-                for i in 0..30 {
-                    output.write_i32::<LE>(*player_disabled_units.get(i).unwrap_or(&-1))?;
+                let most_buildings = most(&self.disabled_buildings);
+                if most_buildings > buildings {
+                    return Err(Error::TooManyDisabledBuildingsError(
+                        most_buildings as i32,
+                        buildings as i32,
+                    ));
                 }
-            }
 
-            for num in &self.num_disabled_buildings {
-                output.write_i32::<LE>(*num)?;
-            }
-            for player_disabled_buildings in &self.disabled_buildings {
-                for i in 0..max_disabled_buildings as usize {
-                    output.write_i32::<LE>(*player_disabled_buildings.get(i).unwrap_or(&-1))?;
+                for (lists, padded_len) in [
+                    (&self.disabled_techs, techs),
+                    (&self.disabled_units, units),
+                    (&self.disabled_buildings, buildings),
+                ] {
+                    for list in lists {
+                        output.write_i32::<LE>(list.len() as i32)?;
+                    }
+                    for list in lists {
+                        for i in 0..padded_len {
+                            output.write_i32::<LE>(*list.get(i).unwrap_or(&-1))?;
+                        }
+                    }
                 }
             }
-        } else if version > 1.03 {
-            let most = *self.num_disabled_techs.iter().max().unwrap_or(&0);
-            if most > 20 {
-                return Err(Error::TooManyDisabledTechsError(most));
-            }
-            if self.num_disabled_units.iter().any(|&n| n > 0) {
-                return Err(Error::CannotDisableUnitsError);
-            }
-            if self.num_disabled_buildings.iter().any(|&n| n > 0) {
-                return Err(Error::CannotDisableBuildingsError);
-            }
+            DisabledLists::TechsOnly(techs) => {
+                let most = self.disabled_techs.iter().map(Vec::len).max().unwrap_or(0);
+                if most > techs {
+                    return Err(Error::TooManyDisabledTechsError(most as i32));
+                }
+                if self.disabled_units.iter().any(|list| !list.is_empty()) {
+                    return Err(Error::CannotDisableUnitsError);
+                }
+                if self.disabled_buildings.iter().any(|list| !list.is_empty()) {
+                    return Err(Error::CannotDisableBuildingsError);
+                }
 
-            // Old scenarios only allowed disabling up to 20 techs per player.
-            for player_disabled_techs in &self.disabled_techs {
-                for i in 0..20 {
-                    output.write_i32::<LE>(*player_disabled_techs.get(i).unwrap_or(&-1))?;
+                // Old scenarios only allowed disabling up to 20 techs per player.
+                for player_disabled_techs in &self.disabled_techs {
+                    for i in 0..techs {
+                        output.write_i32::<LE>(*player_disabled_techs.get(i).unwrap_or(&-1))?;
+                    }
                 }
             }
-        } else {
-            // <= 1.03 did not support disabling anything
-            if self.num_disabled_techs.iter().any(|&n| n > 0) {
-                return Err(Error::CannotDisableTechsError);
-            }
-            if self.num_disabled_units.iter().any(|&n| n > 0) {
-                return Err(Error::CannotDisableUnitsError);
-            }
-            if self.num_disabled_buildings.iter().any(|&n| n > 0) {
-                return Err(Error::CannotDisableBuildingsError);
+            DisabledLists::Unsupported => {
+                // <= 1.03 did not support disabling anything
+                if self.disabled_techs.iter().any(|list| !list.is_empty()) {
+                    return Err(Error::CannotDisableTechsError);
+                }
+                if self.disabled_units.iter().any(|list| !list.is_empty()) {
+                    return Err(Error::CannotDisableUnitsError);
+                }
+                if self.disabled_buildings.iter().any(|list| !list.is_empty()) {
+                    return Err(Error::CannotDisableBuildingsError);
+                }
             }
         }
 
@@ -1063,7 +1220,7 @@ impl TribeScen {
             output.write_i32::<LE>(self.view.1)?;
         }
 
-        if version >= 1.21 {
+        if supports_map_type(version) {
             output.write_i32::<LE>(self.map_type.unwrap_or(-1))?;
         }
 
@@ -1074,19 +1231,22 @@ impl TribeScen {
             }
         }
 
-        if version >= 1.28 {
+        if stores_trigger_count(version) {
             output.write_u32::<LE>(num_triggers)?;
+        }
+        if supports_water_definition(version) {
             output.write_u16::<LE>(0)?;
             write_opt_str(&mut output, &self.water_definition)?;
         }
 
-        if version >= 1.36 {
-            output.write_u8(0)?;
-            output.write_u8(0)?;
+        if supports_color_mood(version) {
+            output.write_u16::<LE>(0)?;
             write_opt_str(&mut output, &self.color_mood)?;
+        }
+        if supports_collide_and_correct(version) {
             output.write_u8(u8::from(self.collide_and_correct))?;
         }
-        if version >= 1.37 {
+        if supports_villager_force_drop(version) {
             output.write_u8(u8::from(self.villager_force_drop))?;
         }
 
@@ -1100,6 +1260,409 @@ impl TribeScen {
     pub fn description(&self) -> Option<&str> {
         self.base.description.as_deref()
     }
+
+    /// Get one of the scenario texts and its string table key.
+    pub(crate) fn text(&self, text: ScenarioText) -> (Option<&str>, Option<&StringKey>) {
+        let base = &self.base;
+        let (value, key) = match text {
+            ScenarioText::Description => (&base.description, &base.description_string_table),
+            ScenarioText::Hints => (&base.hints, &base.hints_string_table),
+            ScenarioText::WinMessage => (&base.win_message, &base.win_message_string_table),
+            ScenarioText::LossMessage => (&base.loss_message, &base.loss_message_string_table),
+            ScenarioText::History => (&base.history, &base.history_string_table),
+            ScenarioText::Scout => (&base.scout, &base.scout_string_table),
+        };
+        (value.as_deref(), key.as_ref())
+    }
+
+    /// Get mutable references to one of the scenario texts and its string table key.
+    pub(crate) fn text_mut(
+        &mut self,
+        text: ScenarioText,
+    ) -> (&mut Option<String>, &mut Option<StringKey>) {
+        let base = &mut self.base;
+        match text {
+            ScenarioText::Description => {
+                (&mut base.description, &mut base.description_string_table)
+            }
+            ScenarioText::Hints => (&mut base.hints, &mut base.hints_string_table),
+            ScenarioText::WinMessage => (&mut base.win_message, &mut base.win_message_string_table),
+            ScenarioText::LossMessage => {
+                (&mut base.loss_message, &mut base.loss_message_string_table)
+            }
+            ScenarioText::History => (&mut base.history, &mut base.history_string_table),
+            ScenarioText::Scout => (&mut base.scout, &mut base.scout_string_table),
+        }
+    }
+
+    /// Get a mutable reference to the file name of a cinematic.
+    pub(crate) fn cinematic_mut(&mut self, cinematic: Cinematic) -> &mut Option<String> {
+        match cinematic {
+            Cinematic::Pregame => &mut self.base.pregame_cinematic,
+            Cinematic::Victory => &mut self.base.victory_cinematic,
+            Cinematic::Loss => &mut self.base.loss_cinematic,
+        }
+    }
+
+    /// Get the file name of a cinematic.
+    pub(crate) fn cinematic(&self, cinematic: Cinematic) -> Option<&str> {
+        match cinematic {
+            Cinematic::Pregame => self.base.pregame_cinematic.as_deref(),
+            Cinematic::Victory => self.base.victory_cinematic.as_deref(),
+            Cinematic::Loss => self.base.loss_cinematic.as_deref(),
+        }
+    }
+
+    /// Get the picture shown on the instructions screen.
+    pub(crate) fn bitmap(&self) -> Option<&Bitmap> {
+        self.base.mission_picture.as_ref()
+    }
+
+    /// Set the picture shown on the instructions screen.
+    pub(crate) fn set_bitmap(&mut self, bitmap: Option<Bitmap>) {
+        self.base.mission_picture = bitmap;
+    }
+
+    /// Get a mutable reference to the initial camera location. -1 values indicate no location is
+    /// set.
+    pub(crate) fn view_mut(&mut self) -> &mut (i32, i32) {
+        &mut self.view
+    }
+
+    /// Find the data that will be lost when writing this as the given data version.
+    pub(crate) fn lost_information(&self, version: f32, lost: &mut Vec<LostInformation>) {
+        let (max_techs, max_units, max_buildings) = max_disabled(version);
+        for index in 0..16 {
+            let player = index as i32 + 1;
+            let techs = self.player_disabled_techs(index).len();
+            if techs > max_techs {
+                lost.push(LostInformation::DisabledTechs(
+                    player,
+                    (techs - max_techs) as i32,
+                ));
+            }
+            let units = self.player_disabled_units(index).len();
+            if units > max_units {
+                lost.push(LostInformation::DisabledUnits(
+                    player,
+                    (units - max_units) as i32,
+                ));
+            }
+            let buildings = self.player_disabled_buildings(index).len();
+            if buildings > max_buildings {
+                lost.push(LostInformation::DisabledBuildings(
+                    player,
+                    (buildings - max_buildings) as i32,
+                ));
+            }
+        }
+
+        for (index, start_age) in self.player_start_ages.iter().enumerate() {
+            if !start_age.is_supported(version) {
+                lost.push(LostInformation::StartingAge(index as i32 + 1));
+            }
+        }
+
+        for (index, resources) in self.player_start_resources.iter().enumerate() {
+            if !resources.is_supported(version) {
+                lost.push(LostInformation::StartingResources(index as i32 + 1));
+            }
+        }
+
+        if self.map_type.is_some() && !supports_map_type(version) {
+            lost.push(LostInformation::MapType);
+        }
+
+        if !self.team_settings().is_supported(version) {
+            lost.push(LostInformation::TeamSettings);
+        }
+        if !self.victory_settings().is_supported(version) {
+            lost.push(LostInformation::VictorySettings);
+        }
+
+        let base = &self.base;
+        let messages_lost = (!supports_instruction_messages(version)
+            && (is_set(&base.hints)
+                || is_set(&base.win_message)
+                || is_set(&base.loss_message)
+                || is_set(&base.history)))
+            || (!supports_scout_text(version) && is_set(&base.scout));
+        if messages_lost {
+            lost.push(LostInformation::Messages);
+        }
+
+        if is_set(&self.water_definition) && !supports_water_definition(version) {
+            lost.push(LostInformation::WaterDefinition);
+        }
+        if is_set(&self.color_mood) && !supports_color_mood(version) {
+            lost.push(LostInformation::ColorMood);
+        }
+        if self.collide_and_correct && !supports_collide_and_correct(version) {
+            lost.push(LostInformation::CollideAndCorrect);
+        }
+        if self.villager_force_drop && !supports_villager_force_drop(version) {
+            lost.push(LostInformation::VillagerForceDrop);
+        }
+    }
+
+    /// Reset the settings that `lost_information()` reports for the given data version, so they
+    /// can be stored in it.
+    pub(crate) fn drop_lost_information(&mut self, version: f32) {
+        self.truncate_disabled(version);
+
+        for start_age in &mut self.player_start_ages {
+            if !start_age.is_supported(version) {
+                *start_age = StartingAge::Default;
+            }
+        }
+
+        for resources in &mut self.player_start_resources {
+            if !resources.is_supported(version) {
+                let default = PlayerStartResources::default();
+                resources.ore = default.ore;
+                resources.goods = default.goods;
+            }
+        }
+
+        if !supports_map_type(version) {
+            self.map_type = None;
+        }
+
+        if !self.team_settings().is_supported(version) {
+            let locked = stored_team_settings(version) == StoredTeamSettings::LockedOnly
+                && self.teams_locked;
+            self.set_team_settings(TeamSettings {
+                locked,
+                ..TeamSettings::default()
+            });
+        }
+        if !self.victory_settings().is_supported(version) {
+            let default = VictorySettings::default();
+            self.set_victory_settings(VictorySettings {
+                mode: default.mode,
+                score: default.score,
+                time: default.time,
+                ..self.victory_settings()
+            });
+        }
+
+        let base = &mut self.base;
+        if !supports_instruction_messages(version) {
+            base.hints = None;
+            base.win_message = None;
+            base.loss_message = None;
+            base.history = None;
+        }
+        if !supports_scout_text(version) {
+            base.scout = None;
+        }
+
+        if !supports_water_definition(version) {
+            self.water_definition = None;
+        }
+        if !supports_color_mood(version) {
+            self.color_mood = None;
+        }
+        if !supports_collide_and_correct(version) {
+            self.collide_and_correct = false;
+        }
+        if !supports_villager_force_drop(version) {
+            self.villager_force_drop = false;
+        }
+    }
+
+    /// Fill in the Definitive Edition water definition and colour mood, if they are not set, using
+    /// the values that the DE editor uses for new scenarios.
+    ///
+    /// Collide-and-correct pathing is turned off. Older games do not have it, so units keep
+    /// moving the way they did in the game the scenario was made for. It is also what the DE
+    /// editor uses for new scenarios.
+    pub(crate) fn set_de_defaults(&mut self) {
+        if !is_set(&self.water_definition) {
+            self.water_definition = Some("Preset_Main".to_string());
+        }
+        if !is_set(&self.color_mood) {
+            self.color_mood = Some("Empty".to_string());
+        }
+        self.collide_and_correct = false;
+    }
+
+    /// Drop disabled techs, units, and buildings that can not be stored in the given data version.
+    pub(crate) fn truncate_disabled(&mut self, version: f32) {
+        let (max_techs, max_units, max_buildings) = max_disabled(version);
+        for (lists, max) in [
+            (&mut self.disabled_techs, max_techs),
+            (&mut self.disabled_units, max_units),
+            (&mut self.disabled_buildings, max_buildings),
+        ] {
+            for list in lists.iter_mut() {
+                list.truncate(max);
+            }
+        }
+    }
+
+    /// Iterate over the civilization IDs of all players, as `(active, civilization)` pairs.
+    pub(crate) fn player_civilizations(&self) -> impl Iterator<Item = (bool, i32)> + '_ {
+        self.base
+            .player_base_properties
+            .iter()
+            .map(|properties| (properties.active != 0, properties.civilization))
+    }
+
+    /// Iterate over the civilization IDs of all players, with mutable references.
+    pub(crate) fn player_civilizations_mut(&mut self) -> impl Iterator<Item = &mut i32> {
+        self.base
+            .player_base_properties
+            .iter_mut()
+            .map(|properties| &mut properties.civilization)
+    }
+
+    /// Reorder the per-player settings, so that player `i + 1` gets the settings of player
+    /// `sources[i] + 1`.
+    ///
+    /// `map_player` translates player numbers stored in the settings.
+    pub(crate) fn remap_players(&mut self, sources: &[usize], map_player: impl Fn(i32) -> i32) {
+        let base = &mut self.base;
+        permute(&mut base.player_names, sources);
+        permute(&mut base.player_string_table, sources);
+        permute(&mut base.player_base_properties, sources);
+        permute(&mut base.player_build_lists, sources);
+        permute(&mut base.player_city_plans, sources);
+        permute(&mut base.player_ai_rules, sources);
+        permute(&mut base.player_files, sources);
+        permute(&mut base.ai_rules_types, sources);
+
+        permute(&mut self.player_start_resources, sources);
+        permute(&mut self.diplomacy, sources);
+        for stances in self.diplomacy.iter_mut() {
+            permute(stances, sources);
+        }
+        permute(&mut self.legacy_victory_info, sources);
+        for entry in self.legacy_victory_info.iter_mut().flatten() {
+            entry.player_id = map_player(entry.player_id);
+        }
+        permute(&mut self.allied_victory, sources);
+        permute(&mut self.disabled_techs, sources);
+        permute(&mut self.disabled_units, sources);
+        permute(&mut self.disabled_buildings, sources);
+        permute(&mut self.player_start_ages, sources);
+        permute(&mut self.base_priorities, sources);
+    }
+
+    /// Get the team settings.
+    pub(crate) fn team_settings(&self) -> TeamSettings {
+        TeamSettings {
+            locked: self.teams_locked,
+            can_change_teams: self.can_change_teams,
+            random_start_locations: self.random_start_locations,
+            max_teams: self.max_teams,
+        }
+    }
+
+    /// Replace the team settings.
+    pub(crate) fn set_team_settings(&mut self, settings: TeamSettings) {
+        self.teams_locked = settings.locked;
+        self.can_change_teams = settings.can_change_teams;
+        self.random_start_locations = settings.random_start_locations;
+        self.max_teams = settings.max_teams;
+    }
+
+    /// Get the scenario-wide victory settings.
+    pub(crate) fn victory_settings(&self) -> VictorySettings {
+        VictorySettings {
+            mode: VictoryMode::from(self.mp_victory_type),
+            score: self.victory_score,
+            time: self.victory_time,
+            conquest: self.victory.conquest,
+            relics: self.victory.relics,
+            exploration: self.victory.exploration,
+            all_required: self.victory_all_flag,
+            ruins: self.victory.ruins,
+            discoveries: self.victory.discoveries,
+            gold: self.victory.gold,
+        }
+    }
+
+    /// Replace the scenario-wide victory settings.
+    pub(crate) fn set_victory_settings(&mut self, settings: VictorySettings) {
+        self.mp_victory_type = i32::from(settings.mode);
+        self.victory_score = settings.score;
+        self.victory_time = settings.time;
+        self.victory = VictoryInfo {
+            conquest: settings.conquest,
+            ruins: settings.ruins,
+            relics: settings.relics,
+            discoveries: settings.discoveries,
+            exploration: settings.exploration,
+            gold: settings.gold,
+        };
+        self.victory_all_flag = settings.all_required;
+    }
+
+    /// Set whether a player slot is used. Index 0 is player 1.
+    pub(crate) fn set_player_active(&mut self, player: usize, active: bool) {
+        self.base.player_base_properties[player].active = i32::from(active);
+    }
+
+    /// Get the IDs of the techs that are disabled for a player.
+    pub(crate) fn player_disabled_techs(&self, player: usize) -> &[i32] {
+        &self.disabled_techs[player]
+    }
+
+    /// Get the IDs of the units that are disabled for a player.
+    pub(crate) fn player_disabled_units(&self, player: usize) -> &[i32] {
+        &self.disabled_units[player]
+    }
+
+    /// Get the IDs of the buildings that are disabled for a player.
+    pub(crate) fn player_disabled_buildings(&self, player: usize) -> &[i32] {
+        &self.disabled_buildings[player]
+    }
+
+    /// Get the list of techs that are disabled for a player, mutably.
+    pub(crate) fn player_disabled_techs_mut(&mut self, player: usize) -> &mut Vec<i32> {
+        &mut self.disabled_techs[player]
+    }
+
+    /// Get the list of units that are disabled for a player, mutably.
+    pub(crate) fn player_disabled_units_mut(&mut self, player: usize) -> &mut Vec<i32> {
+        &mut self.disabled_units[player]
+    }
+
+    /// Get the list of buildings that are disabled for a player, mutably.
+    pub(crate) fn player_disabled_buildings_mut(&mut self, player: usize) -> &mut Vec<i32> {
+        &mut self.disabled_buildings[player]
+    }
+
+    /// Remove disabled techs, units, and buildings for which `keep_tech` or `keep_unit` return
+    /// false, and return how many were removed.
+    #[cfg(feature = "validate")]
+    pub(crate) fn retain_disabled(
+        &mut self,
+        mut keep_tech: impl FnMut(i32) -> bool,
+        mut keep_unit: impl FnMut(i32) -> bool,
+    ) -> usize {
+        let mut removed = 0;
+        let mut retain = |list: &mut Vec<i32>, keep: &mut dyn FnMut(i32) -> bool| {
+            let len = list.len();
+            list.retain(|&id| keep(id));
+            removed += len - list.len();
+        };
+        for player in 0..16 {
+            retain(&mut self.disabled_techs[player], &mut keep_tech);
+            retain(&mut self.disabled_units[player], &mut keep_unit);
+            retain(&mut self.disabled_buildings[player], &mut keep_unit);
+        }
+        removed
+    }
+
+    /// Get all the strings in the scenario data, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.base
+            .strings_mut()
+            .chain(self.water_definition.iter_mut())
+            .chain(self.color_mood.iter_mut())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1123,7 +1686,9 @@ pub struct SCXFormat {
     /// Triggers (only in AoK and up).
     pub(crate) triggers: Option<TriggerSystem>,
     /// AI information (AoK and up).
-    ai_info: Option<AIInfo>,
+    pub(crate) ai_info: Option<AIInfo>,
+    /// The text encoding that strings are stored in.
+    pub(crate) encoding: &'static Encoding,
 }
 
 impl SCXFormat {
@@ -1188,7 +1753,7 @@ impl SCXFormat {
         }
 
         // The order is flipped … thanks DE
-        let (scenario_players, player_objects) = if version >= SCXVersion(*b"1.36") {
+        let (scenario_players, player_objects) = if version.has_players_before_objects() {
             let players = read_scenario_players(&mut input, player_version)?;
             let objects = read_player_objects(&mut input, num_players, version)?;
             (players, objects)
@@ -1198,13 +1763,13 @@ impl SCXFormat {
             (players, objects)
         };
 
-        let triggers = if version < SCXVersion(*b"1.14") {
+        let triggers = if !version.supports_triggers() {
             None
         } else {
             Some(TriggerSystem::read_from(&mut input)?)
         };
 
-        let ai_info = if version > SCXVersion(*b"1.17") && version < SCXVersion(*b"2.00") {
+        let ai_info = if version.supports_ai_files() {
             AIInfo::read_from(&mut input)?
         } else {
             None
@@ -1221,6 +1786,7 @@ impl SCXFormat {
             scenario_players,
             triggers,
             ai_info,
+            encoding: WINDOWS_1252,
         })
     }
 
@@ -1250,37 +1816,40 @@ impl SCXFormat {
     }
 
     fn write_scenario_players(
-        &self,
         mut output: impl Write,
+        scenario_players: &[ScenarioPlayerData],
         player_version: f32,
         victory_version: f32,
     ) -> Result<()> {
-        output.write_i32::<LE>(self.scenario_players.len() as i32 + 1)?;
-        for player in &self.scenario_players {
+        output.write_i32::<LE>(scenario_players.len() as i32 + 1)?;
+        for player in scenario_players {
             player.write_to(&mut output, player_version, victory_version)?;
         }
         Ok(())
     }
 
     pub fn write_to(&self, mut output: impl Write, version: &VersionBundle) -> Result<()> {
+        let mut text = TextParts::new(self);
+        encoding::encode_strings(text.strings_mut(self.encoding), self.encoding)?;
+
         let player_version = match version.format.to_player_version() {
             Some(v) => v,
             None => return Err(Error::UnsupportedFormatVersionError(version.format)),
         };
 
         output.write_all(version.format.as_bytes())?;
-        self.header
+        text.header
             .write_to(&mut output, version.format, version.header)?;
 
         let mut output = DeflateEncoder::new(output, Compression::default());
         output.write_i32::<LE>(self.next_object_id)?;
 
-        let num_triggers = self
+        let num_triggers = text
             .triggers
             .as_ref()
             .map(|trigger_system| trigger_system.num_triggers())
             .unwrap_or(0);
-        self.tribe_scen
+        text.tribe_scen
             .write_to(&mut output, version.data, num_triggers)?;
         self.map.write_to(&mut output, version.map)?;
 
@@ -1289,26 +1858,27 @@ impl SCXFormat {
             player.write_to(&mut output, player_version)?;
         }
 
-        if version.format >= SCXVersion(*b"1.36") {
-            self.write_scenario_players(&mut output, player_version, version.victory)?;
+        let players = &text.scenario_players;
+        if version.format.has_players_before_objects() {
+            Self::write_scenario_players(&mut output, players, player_version, version.victory)?;
             self.write_player_objects(&mut output, version.format)?;
         } else {
             self.write_player_objects(&mut output, version.format)?;
-            self.write_scenario_players(&mut output, player_version, version.victory)?;
+            Self::write_scenario_players(&mut output, players, player_version, version.victory)?;
         }
 
-        if version.format > SCXVersion(*b"1.13") {
+        if version.supports_triggers() {
             let def = TriggerSystem::default();
-            let triggers = match self.triggers {
+            let triggers = match text.triggers {
                 Some(ref tr) => tr,
                 None => &def,
             };
             triggers.write_to(&mut output, version.triggers.unwrap_or(1.6))?;
         }
 
-        if version.format > SCXVersion(*b"1.17") && version.format < SCXVersion(*b"2.00") {
+        if version.supports_ai_files() {
             let def = AIInfo::default();
-            let ai_info = match self.ai_info {
+            let ai_info = match text.ai_info {
                 Some(ref ai) => ai,
                 None => &def,
             };
@@ -1320,6 +1890,120 @@ impl SCXFormat {
         Ok(())
     }
 
+    /// Find the data that will be lost when writing this scenario as the given version.
+    pub(crate) fn lost_information(&self, version: &VersionBundle) -> Vec<LostInformation> {
+        let mut lost = vec![];
+
+        if is_set(&self.header.author_name) && !version.supports_author_name() {
+            lost.push(LostInformation::AuthorName);
+        }
+        if self.header.dlc_options.is_some() && !version.has_dlc_options() {
+            lost.push(LostInformation::DLCOptions);
+        }
+
+        self.tribe_scen.lost_information(version.data, &mut lost);
+
+        let layered_tiles = self.map.num_layered_tiles();
+        if layered_tiles > 0 && !version.supports_layered_terrain() {
+            lost.push(LostInformation::LayeredTerrain(layered_tiles));
+        }
+        if !self.map.render_waves() && !version.supports_render_waves() {
+            lost.push(LostInformation::RenderWaves);
+        }
+
+        if !version.supports_garrisoned_objects() {
+            let garrisoned = self
+                .player_objects
+                .iter()
+                .flatten()
+                .filter(|object| object.garrisoned_in.is_some())
+                .count();
+            if garrisoned > 0 {
+                lost.push(LostInformation::GarrisonedObjects(garrisoned));
+            }
+        }
+
+        if let Some(triggers) = &self.triggers {
+            let num_triggers = triggers.num_triggers() as usize;
+            if !version.supports_triggers() {
+                if num_triggers > 0 {
+                    lost.push(LostInformation::Triggers(num_triggers));
+                }
+            } else {
+                let display_triggers = triggers
+                    .triggers_unordered()
+                    .filter(|trigger| trigger.uses_display_fields())
+                    .count();
+                if display_triggers > 0 && !version.supports_trigger_display_fields() {
+                    lost.push(LostInformation::TriggerDisplayFields(display_triggers));
+                }
+                if triggers.uses_variables() && !version.supports_trigger_variables() {
+                    lost.push(LostInformation::TriggerVariables);
+                }
+            }
+        }
+
+        let has_ai_files = self.ai_info.as_ref().map_or(false, AIInfo::has_files);
+        let has_ai_rules = self.tribe_scen.base.has_ai_rules();
+        if (has_ai_files && !version.supports_ai_files())
+            || (has_ai_rules && !version.supports_ai_rules())
+        {
+            lost.push(LostInformation::AIFiles);
+        }
+
+        lost
+    }
+
+    /// Remove the data that `lost_information()` reports for the given versions, so that writing
+    /// the scenario for them drops exactly that data.
+    pub(crate) fn drop_lost_information(&mut self, version: &VersionBundle) {
+        if !version.supports_author_name() {
+            self.header.author_name = None;
+        }
+        if !version.has_dlc_options() {
+            self.header.dlc_options = None;
+        }
+
+        self.tribe_scen.drop_lost_information(version.data);
+
+        if !version.supports_layered_terrain() {
+            self.map.clear_layered_terrain();
+        }
+        if !version.supports_render_waves() {
+            self.map.set_render_waves(true);
+        }
+
+        if !version.supports_garrisoned_objects() {
+            for object in self.player_objects.iter_mut().flatten() {
+                object.garrisoned_in = None;
+            }
+        }
+
+        if !version.supports_triggers() {
+            self.triggers = None;
+        } else if let Some(triggers) = &mut self.triggers {
+            if !version.supports_trigger_display_fields() {
+                triggers
+                    .triggers_unordered_mut()
+                    .for_each(|trigger| trigger.clear_display_fields());
+            }
+            if !version.supports_trigger_variables() {
+                triggers.clear_variables();
+            }
+        }
+
+        if !version.supports_ai_files() {
+            self.ai_info = None;
+        }
+        if !version.supports_ai_rules() {
+            for index in 0..self.tribe_scen.base.player_files.len() {
+                self.tribe_scen
+                    .base
+                    .set_ai_script(index, AIScriptKind::Rules, None);
+            }
+        }
+    }
+
     /// Get the name of the UserPatch mod that was used to create this scenario, if applicable.
     ///
     /// Returns None if no mod was used.
@@ -1340,6 +2024,74 @@ impl SCXFormat {
         format!("{:#?}", self).hash(&mut hasher);
         hasher.finish()
     }
+
+    /// Get all the strings in the scenario, for converting them between text encodings.
+    ///
+    /// The header and TribeScen strings come first, as encoding detection only looks at those.
+    pub(crate) fn strings_mut(&mut self) -> Vec<&mut String> {
+        let mut strings: Vec<&mut String> = self.header.strings_mut().collect();
+        strings.extend(self.tribe_scen.strings_mut());
+        strings.extend(
+            self.scenario_players
+                .iter_mut()
+                .flat_map(ScenarioPlayerData::strings_mut),
+        );
+        if let Some(triggers) = &mut self.triggers {
+            strings.extend(triggers.strings_mut());
+        }
+        if let Some(ai_info) = &mut self.ai_info {
+            strings.extend(ai_info.strings_mut());
+        }
+        strings
+    }
+}
+
+/// The parts of a scenario that contain strings, as they are written.
+///
+/// Strings in other encodings than WINDOWS-1252 are converted before writing. Only the parts that
+/// contain strings are copied to do that; the map and objects, which are most of a scenario, are
+/// not.
+struct TextParts<'a> {
+    header: Cow<'a, SCXHeader>,
+    tribe_scen: Cow<'a, TribeScen>,
+    scenario_players: Cow<'a, [ScenarioPlayerData]>,
+    triggers: Option<Cow<'a, TriggerSystem>>,
+    ai_info: Option<Cow<'a, AIInfo>>,
+}
+
+impl<'a> TextParts<'a> {
+    fn new(format: &'a SCXFormat) -> Self {
+        Self {
+            header: Cow::Borrowed(&format.header),
+            tribe_scen: Cow::Borrowed(&format.tribe_scen),
+            scenario_players: Cow::Borrowed(&format.scenario_players),
+            triggers: format.triggers.as_ref().map(Cow::Borrowed),
+            ai_info: format.ai_info.as_ref().map(Cow::Borrowed),
+        }
+    }
+
+    /// Get all the strings, copying the parts that contain them unless they are already stored
+    /// as WINDOWS-1252.
+    fn strings_mut(&mut self, encoding: &'static Encoding) -> Vec<&mut String> {
+        if encoding == WINDOWS_1252 {
+            return vec![];
+        }
+        let mut strings: Vec<&mut String> = self.header.to_mut().strings_mut().collect();
+        strings.extend(self.tribe_scen.to_mut().strings_mut());
+        strings.extend(
+            self.scenario_players
+                .to_mut()
+                .iter_mut()
+                .flat_map(ScenarioPlayerData::strings_mut),
+        );
+        if let Some(triggers) = &mut self.triggers {
+            strings.extend(triggers.to_mut().strings_mut());
+        }
+        if let Some(ai_info) = &mut self.ai_info {
+            strings.extend(ai_info.to_mut().strings_mut());
+        }
+        strings
+    }
 }
 
 fn write_opt_string_key(mut output: impl Write, opt_key: &Option<StringKey>) -> Result<()> {
@@ -1354,8 +2106,8 @@ fn write_opt_string_key(mut output: impl Write, opt_key: &Option<StringKey>) ->
 
 #[cfg(test)]
 mod tests {
-    use super::SCXFormat;
-    use crate::{Result, VersionBundle};
+    use super::{LostInformation, SCXFormat};
+    use crate::{Error, Result, Scenario, VersionBundle};
     use std::fs::File;
     use std::io::{Cursor, ErrorKind, Read};
 
@@ -1583,4 +2335,78 @@ mod tests {
             "should produce exactly the same scenario"
         );
     }
+
+    /// Layered terrain can not be stored in AoC scenarios.
+    #[test]
+    fn aoe_de2_to_aoc_lost_information() {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario").unwrap();
+        let scen = Scenario::read_from(&mut f).expect("failed to read");
+
+        let lost = scen.lost_information(&VersionBundle::aoc());
+        assert!(lost
+            .iter()
+            .any(|info| matches!(info, LostInformation::LayeredTerrain(_))));
+        assert!(scen.lost_information(scen.version()).is_empty());
+
+        let result = scen.write_to_version_strict(&mut vec![], &VersionBundle::aoc());
+        assert!(matches!(result, Err(Error::LostInformationError(ref info)) if *info == lost));
+
+        let mut out = vec![];
+        let dropped = scen
+            .write_to_version_lenient(&mut out, &VersionBundle::aoc())
+            .expect("lenient write failed");
+        assert_eq!(dropped, lost);
+        let aoc = Scenario::read_from(Cursor::new(out)).expect("failed to read converted");
+        assert_eq!(aoc.map().num_layered_tiles(), 0);
+    }
+
+    /// Disabled techs that do not fit in the target version are an error, unless written
+    /// leniently.
+    #[test]
+    fn too_many_disabled_techs_for_aoc() {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario").unwrap();
+        let mut scen = Scenario::read_from(&mut f).expect("failed to read");
+        scen.format.tribe_scen.disabled_techs[0] = (0..31).collect();
+
+        let result = scen.write_to_version(&mut vec![], &VersionBundle::aoc());
+        assert!(matches!(
+            result,
+            Err(Error::TooManyDisabledTechsForVersionError(31, 30))
+        ));
+
+        let mut out = vec![];
+        scen.write_to_version_lenient(&mut out, &VersionBundle::aoc())
+            .expect("lenient write failed");
+        let aoc = Scenario::read_from(Cursor::new(out)).expect("failed to read converted");
+        assert_eq!(aoc.disabled_techs(1).map(|techs| techs.len()), Some(30));
+    }
+
+    /// Lenient writes drop exactly the data that `lost_information()` reports.
+    #[test]
+    fn drop_lost_information() -> Result<()> {
+        for name in [
+            "layertest.aoe2scenario",
+            "Hotkey Trainer Buildings.aoe2scenario",
+            "Year_of_the_Pig.aoe2scenario",
+            "Age of Heroes b1-3-5.scx",
+        ] {
+            let mut f = File::open(format!("test/scenarios/{}", name))?;
+            let scen = Scenario::read_from(&mut f)?;
+            for version in [
+                VersionBundle::ror(),
+                VersionBundle::aok(),
+                VersionBundle::aoc(),
+                VersionBundle::hd_edition(),
+            ] {
+                let mut format = scen.format.clone();
+                format.drop_lost_information(&version);
+                assert_eq!(format.lost_information(&version), vec![], "{}", name);
+
+                let mut out = vec![];
+                scen.write_to_version_lenient(&mut out, &version)?;
+                Scenario::read_from(Cursor::new(out))?;
+            }
+        }
+        Ok(())
+    }
 }
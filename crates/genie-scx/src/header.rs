@@ -1,7 +1,7 @@
-use crate::types::{DLCPackage, DataSet, SCXVersion};
+use crate::types::{has_dlc_options, DLCPackage, DataSet, SCXVersion};
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{write_opt_i32_str, ReadStringsExt};
+use genie_support::{encode_str, write_opt_i32_str, ReadStringsExt};
 use std::convert::TryFrom;
 use std::io::{Read, Write};
 
@@ -100,7 +100,7 @@ impl SCXHeader {
         } else {
             0
         };
-        let description = if format_version == *b"3.13" {
+        let description = if format_version.has_hd_style_description() {
             input.read_hd_style_str()?
         } else {
             input.read_u32_length_prefixed_str()?
@@ -109,7 +109,7 @@ impl SCXHeader {
         let any_sp_victory = input.read_u32::<LE>()? != 0;
         let active_player_count = input.read_u32::<LE>()?;
 
-        let dlc_options = if version > 2 && format_version != *b"3.13" {
+        let dlc_options = if has_dlc_options(format_version, version) {
             Some(DLCOptions::read_from(&mut input)?)
         } else {
             None
@@ -151,10 +151,10 @@ impl SCXHeader {
 
         let mut description_bytes = vec![];
         if let Some(ref description) = self.description {
-            description_bytes.write_all(description.as_bytes())?;
+            description_bytes.write_all(&encode_str(description)?)?;
         }
         description_bytes.push(0);
-        if format_version == *b"3.13" {
+        if format_version.has_hd_style_description() {
             assert!(
                 description_bytes.len() <= std::u16::MAX as usize,
                 "description length must fit in u16"
@@ -172,7 +172,7 @@ impl SCXHeader {
         intermediate.write_u32::<LE>(u32::from(self.any_sp_victory))?;
         intermediate.write_u32::<LE>(self.active_player_count)?;
 
-        if version > 2 && format_version != *b"3.13" {
+        if has_dlc_options(format_version, version) {
             let def = DLCOptions::default();
             let dlc_options = match self.dlc_options {
                 Some(ref options) => options,
@@ -202,4 +202,11 @@ impl SCXHeader {
         self.timestamp = duration.as_secs() as u32;
         Ok(())
     }
+
+    /// Get all the strings in the header, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.description
+            .iter_mut()
+            .chain(self.author_name.iter_mut())
+    }
 }
@@ -2,6 +2,14 @@
 //!
 //! This crate aims to support every single scenario that exists. If a scenario file from any Age
 //! of Empires 1 or Age of Empires 2 version does not work, please upload it and file an issue!
+//!
+//! Features that need other file formats are optional:
+//!
+//! - `validate`: check scenarios against a data file, and remove what a data file does not define
+//!   when converting from the Definitive Edition.
+//! - `terrain-rules`: derive terrain painting rules from a data file.
+//! - `minimap`: render minimaps.
+//! - `string-table`: export and import scenario texts as string tables.
 
 #![deny(future_incompatible)]
 #![deny(nonstandard_style)]
@@ -13,27 +21,72 @@
 mod ai;
 mod bitmap;
 pub mod convert;
+mod diff;
+mod encoding;
 mod format;
 mod header;
-mod map;
+pub mod map;
+mod merge;
+#[cfg(feature = "minimap")]
+mod minimap;
+mod objects;
 mod player;
+mod remap;
+mod setup;
+mod spatial;
+#[cfg(feature = "string-table")]
+mod string_table;
+mod summary;
+mod text;
+mod transform;
 mod triggers;
 mod types;
+#[cfg(feature = "validate")]
+mod validate;
 mod victory;
 
 use format::SCXFormat;
-use genie_support::{ReadStringError, WriteStringError};
+#[cfg(feature = "validate")]
+use genie_dat::DatFile;
+#[cfg(feature = "string-table")]
+use genie_lang::LangFile;
+use genie_support::{CivilizationID, ReadStringError, WriteStringError};
+use rgb::RGB8;
+use setup::DisabledList;
+use spatial::SpatialCache;
 use std::io::{self, Read, Write};
+use transform::Transform;
 
-pub use format::{ScenarioObject, TribeScen};
+pub use ai::{AIError, AIFile, AIScriptKind};
+pub use bitmap::Bitmap;
+pub use diff::{DiffSection, PlayerSetting, ScenarioChange};
+pub use encoding::TextEncoding;
+pub use encoding_rs::{self, Encoding};
+pub use format::{LostInformation, ScenarioObject, TribeScen};
 pub use genie_support::{DecodeStringError, EncodeStringError};
-pub use genie_support::{StringKey, UnitTypeID};
+pub use genie_support::{StringKey, TechID, UnitTypeID};
 pub use header::{DLCOptions, SCXHeader};
 pub use map::{Map, Tile};
-pub use player::{ScenarioPlayerData, WorldPlayerData};
+pub use merge::{MergeError, MergeOptions};
+#[cfg(feature = "minimap")]
+pub use minimap::{MinimapImage, MinimapProjection, MinimapRenderer};
+pub use objects::ObjectError;
+pub use player::{PlayerStartResources, ScenarioPlayerData, WorldPlayerData};
+pub use remap::RemapError;
+pub use setup::{PlayerType, SetupError, TeamSettings};
+pub use spatial::ObjectFilter;
+#[cfg(feature = "string-table")]
+pub use string_table::StringImport;
+pub use summary::ScenarioSummary;
+pub use text::{Cinematic, ScenarioText};
+pub use transform::{Mirror, Rotation};
 pub use triggers::{Trigger, TriggerCondition, TriggerEffect, TriggerSystem};
 pub use types::*;
-pub use victory::{VictoryConditions, VictoryEntry, VictoryPointEntry, VictoryState};
+#[cfg(feature = "validate")]
+pub use validate::{TriggerItem, ValidationIssue};
+pub use victory::{
+    VictoryConditions, VictoryEntry, VictoryMode, VictoryPointEntry, VictorySettings, VictoryState,
+};
 
 /// Error type for SCX methods, containing all types of errors that may occur while reading or
 /// writing scenario files.
@@ -45,10 +98,15 @@ pub enum Error {
     /// Attempted to read a scenario with an unsupported format version identifier.
     #[error("unsupported format version {:?}", .0)]
     UnsupportedFormatVersionError(SCXVersion),
-    /// Attempted to write a scenario with disabled technologies, to a version that doesn't support
-    /// this many disabled technologies.
+    /// Attempted to write a scenario with more than 20 disabled technologies, to an old version
+    /// that doesn't support this many disabled technologies.
     #[error("too many disabled techs: got {}, but requested version supports up to 20", .0)]
     TooManyDisabledTechsError(i32),
+    /// Attempted to write a scenario with disabled technologies, to a version that stores them in
+    /// fixed size lists that are too short. Contains the number of disabled techs, and the most
+    /// that the requested version supports.
+    #[error("too many disabled techs: got {}, but requested version supports up to {}", .0, .1)]
+    TooManyDisabledTechsForVersionError(i32, i32),
     /// Attempted to write a scenario with disabled technologies, to a version that doesn't support
     /// disabling technologies.
     #[error("requested version does not support disabling techs")]
@@ -57,6 +115,10 @@ pub enum Error {
     /// disabling units.
     #[error("requested version does not support disabling units")]
     CannotDisableUnitsError,
+    /// Attempted to write a scenario with disabled units, to a version that doesn't support
+    /// this many disabled units.
+    #[error("too many disabled units: got {}, but requested version supports up to {}", .0, .1)]
+    TooManyDisabledUnitsError(i32, i32),
     /// Attempted to write a scenario with disabled buildings, to a version that doesn't support
     /// this many disabled buildings.
     #[error("too many disabled buildings: got {}, but requested version supports up to {}", .0, .1)]
@@ -65,6 +127,12 @@ pub enum Error {
     /// disabling buildings.
     #[error("requested version does not support disabling buildings")]
     CannotDisableBuildingsError,
+    /// Attempted to write a string that is longer than the fixed size buffer it is stored in.
+    #[error("string is {} bytes long, but can be at most {}", .0, .1)]
+    StringTooLongError(usize, usize),
+    /// Attempted to write a scenario to a version that can not represent all of its data.
+    #[error("requested version can not represent {} pieces of scenario data", .0.len())]
+    LostInformationError(Vec<LostInformation>),
     /// Failed to decode a string from the scenario file, probably because of a wrong encoding.
     #[error(transparent)]
     DecodeStringError(#[from] DecodeStringError),
@@ -120,15 +188,46 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub struct Scenario {
     format: SCXFormat,
     version: VersionBundle,
+    spatial: SpatialCache,
 }
 
 impl Scenario {
     /// Read a scenario file.
+    ///
+    /// The text encoding of the strings in the scenario is detected automatically. Use
+    /// `read_from_with_encoding` to choose it.
     pub fn read_from(input: impl Read) -> Result<Self> {
-        let format = SCXFormat::load_scenario(input)?;
+        Self::read_from_with_encoding(input, TextEncoding::Detect)
+    }
+
+    /// Read a scenario file, decoding its strings with the given text encoding.
+    ///
+    /// Scenarios made for AoE1, AoK, AoC, and HD Edition store strings in the code page of the
+    /// system they were made on. The encoding is kept when writing the scenario, so reading and
+    /// writing a scenario does not change its strings.
+    ///
+    /// ```rust,no_run
+    /// # use genie_scx::{encoding_rs::WINDOWS_1251, Scenario, TextEncoding};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let f = std::fs::File::open("russian.scx")?;
+    /// let scen = Scenario::read_from_with_encoding(f, TextEncoding::Fixed(WINDOWS_1251))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_from_with_encoding(input: impl Read, encoding: TextEncoding) -> Result<Self> {
+        let mut format = SCXFormat::load_scenario(input)?;
         let version = format.version();
+        // Detect the encoding from the strings that `ScenarioSummary` reads too, so both agree.
+        let num_detected =
+            format.header.strings_mut().count() + format.tribe_scen.strings_mut().count();
+        format.encoding =
+            encoding::decode_strings(format.strings_mut(), num_detected, encoding, &version)?;
 
-        Ok(Self { format, version })
+        Ok(Self {
+            format,
+            version,
+            spatial: Default::default(),
+        })
     }
 
     /// Read a scenario file.
@@ -149,6 +248,50 @@ impl Scenario {
         self.format.write_to(output, version)
     }
 
+    /// Write the scenario file to an output stream, targeting specific game versions, and fail if
+    /// any data can not be represented in those versions.
+    ///
+    /// Returns a `LostInformationError` listing everything that would be lost, without writing
+    /// anything.
+    pub fn write_to_version_strict(
+        &self,
+        output: impl Write,
+        version: &VersionBundle,
+    ) -> Result<()> {
+        let lost = self.format.lost_information(version);
+        if !lost.is_empty() {
+            return Err(Error::LostInformationError(lost));
+        }
+        self.format.write_to(output, version)
+    }
+
+    /// Write the scenario file to an output stream, targeting specific game versions, dropping any
+    /// data that can not be represented in those versions.
+    ///
+    /// The dropped data is exactly what [`Scenario::lost_information`] reports: disabled lists are
+    /// truncated, and the other settings are reset to values those versions can store. Returns a
+    /// list of everything that was dropped.
+    pub fn write_to_version_lenient(
+        &self,
+        output: impl Write,
+        version: &VersionBundle,
+    ) -> Result<Vec<LostInformation>> {
+        let lost = self.format.lost_information(version);
+        if lost.is_empty() {
+            self.format.write_to(output, version)?;
+        } else {
+            let mut format = self.format.clone();
+            format.drop_lost_information(version);
+            format.write_to(output, version)?;
+        }
+        Ok(lost)
+    }
+
+    /// Find the data in this scenario that can not be represented in the given game versions.
+    pub fn lost_information(&self, version: &VersionBundle) -> Vec<LostInformation> {
+        self.format.lost_information(version)
+    }
+
     /// Get the format version of this SCX file.
     #[inline]
     pub fn format_version(&self) -> SCXVersion {
@@ -179,6 +322,103 @@ impl Scenario {
         self.format.tribe_scen.description()
     }
 
+    /// Get one of the texts shown to players, like the description or the hints.
+    ///
+    /// This is the text stored in the scenario. If the text has a string table key, the game
+    /// shows the string table entry instead; use [`Scenario::resolve_text`] to look it up.
+    pub fn text(&self, text: ScenarioText) -> Option<&str> {
+        self.format.tribe_scen.text(text).0
+    }
+
+    /// Set or remove one of the texts shown to players.
+    pub fn set_text(&mut self, text: ScenarioText, value: Option<String>) {
+        *self.format.tribe_scen.text_mut(text).0 = value;
+    }
+
+    /// Get the string table key for one of the texts shown to players.
+    pub fn text_string_key(&self, text: ScenarioText) -> Option<&StringKey> {
+        self.format.tribe_scen.text(text).1
+    }
+
+    /// Set or remove the string table key for one of the texts shown to players.
+    pub fn set_text_string_key(&mut self, text: ScenarioText, key: Option<StringKey>) {
+        *self.format.tribe_scen.text_mut(text).1 = key;
+    }
+
+    /// Get one of the texts shown to players, the way the game shows it: from the string table
+    /// if the text has a string table key that exists in `lang`, or else the text stored in the
+    /// scenario.
+    #[cfg(feature = "string-table")]
+    pub fn resolve_text<'a>(&'a self, text: ScenarioText, lang: &'a LangFile) -> Option<&'a str> {
+        let (value, key) = self.format.tribe_scen.text(text);
+        key.and_then(|key| lang.get(key))
+            .map(|string| string.as_str())
+            .or(value)
+    }
+
+    /// Collect all the texts shown to players into a string table, for translation.
+    ///
+    /// This includes the scenario texts, player names, trigger descriptions, and trigger effect
+    /// messages such as those of Display Instructions and Send Chat. Texts that are only stored
+    /// as string table keys are not included. The keys look like:
+    ///
+    /// - `scenario.description`, `scenario.hints`, `scenario.win_message`,
+    ///   `scenario.loss_message`, `scenario.history`, `scenario.scout`
+    /// - `player.1.name`
+    /// - `trigger.0.description`, `trigger.0.short_description`
+    /// - `trigger.0.effect.2.message`
+    ///
+    /// Triggers and effects are numbered by their ID, so the keys stay the same as long as no
+    /// triggers or effects are removed.
+    #[cfg(feature = "string-table")]
+    pub fn export_strings(&self) -> LangFile {
+        string_table::export_strings(&self.format)
+    }
+
+    /// Write translated strings back into the scenario. `lang` uses the keys from
+    /// `export_strings()`; texts that are missing from `lang` are left as they are.
+    ///
+    /// Returns the new string table entries that the scenario refers to. This is empty when
+    /// importing with `StringImport::Inline`.
+    ///
+    /// ```rust,no_run
+    /// # use genie_scx::{Scenario, StringImport};
+    /// # use genie_lang::LangFileType;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut scen = Scenario::read_from(std::fs::File::open("scenario.scx")?)?;
+    /// let translated = LangFileType::KeyValue.read_from(std::fs::File::open("strings.txt")?)?;
+    /// scen.import_strings(&translated, StringImport::Inline);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "string-table")]
+    pub fn import_strings(&mut self, lang: &LangFile, import: StringImport) -> LangFile {
+        string_table::import_strings(&mut self.format, lang, import)
+    }
+
+    /// Get the file name of a cinematic.
+    pub fn cinematic(&self, cinematic: Cinematic) -> Option<&str> {
+        self.format.tribe_scen.cinematic(cinematic)
+    }
+
+    /// Set or remove the file name of a cinematic.
+    pub fn set_cinematic(&mut self, cinematic: Cinematic, file_name: Option<String>) {
+        *self.format.tribe_scen.cinematic_mut(cinematic) = file_name;
+    }
+
+    /// Get the text encoding that strings are stored in.
+    pub fn encoding(&self) -> &'static Encoding {
+        self.format.encoding
+    }
+
+    /// Set the text encoding to store strings in when writing the scenario.
+    ///
+    /// Writing fails with an `EncodeStringError` if a string can not be represented in this
+    /// encoding.
+    pub fn set_encoding(&mut self, encoding: &'static Encoding) {
+        self.format.encoding = encoding;
+    }
+
     /// Get the scenario filename.
     #[inline]
     pub fn filename(&self) -> &str {
@@ -200,6 +440,39 @@ impl Scenario {
         }
     }
 
+    /// Get the picture shown on the instructions screen, if any.
+    #[inline]
+    pub fn bitmap(&self) -> Option<&Bitmap> {
+        self.format.tribe_scen.bitmap()
+    }
+
+    /// Set or remove the picture shown on the instructions screen.
+    ///
+    /// Games before AoE2: Definitive Edition only support bitmaps with a palette. Other bitmaps
+    /// are quantized to 256 colours when writing to those versions.
+    pub fn set_bitmap(&mut self, bitmap: Option<Bitmap>) {
+        self.format.tribe_scen.set_bitmap(bitmap);
+    }
+
+    /// Set the picture shown on the instructions screen from RGB pixels, stored row by row from
+    /// the top left.
+    ///
+    /// The picture is quantized to 256 colours if the scenario's game version requires it. A
+    /// picture with a width or height of 0 removes the picture, like [`Scenario::set_bitmap`] with
+    /// `None`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the number of pixels does not match the width and height.
+    pub fn set_bitmap_rgb(&mut self, width: u32, height: u32, pixels: &[RGB8]) {
+        let bitmap = if !self.version.supports_true_color_bitmaps() {
+            Bitmap::from_rgb_palettized(width, height, pixels)
+        } else {
+            Bitmap::from_rgb(width, height, pixels)
+        };
+        self.set_bitmap(bitmap);
+    }
+
     /// Get the UserPatch mod name of the mod that was used to create this scenario.
     ///
     /// This returns the short name, like "WK" for WololoKingdoms or "aoc" for Age of Chivalry.
@@ -220,12 +493,122 @@ impl Scenario {
     /// Iterate mutably over all the objects placed in the scenario.
     #[inline]
     pub fn objects_mut(&mut self) -> impl Iterator<Item = &mut ScenarioObject> {
+        self.spatial.clear();
         self.format
             .player_objects
             .iter_mut()
             .flat_map(|list| list.iter_mut())
     }
 
+    /// Find the objects in an area, with `from.0 <= x < to.0` and `from.1 <= y < to.1`.
+    ///
+    /// The object at tile (x, y) has a position between (x, y) and (x + 1, y + 1), so to find
+    /// the objects on the tiles from (x1, y1) to (x2, y2) inclusive, pass `(x2 + 1, y2 + 1)` as
+    /// the end of the area.
+    pub fn objects_in_area(
+        &self,
+        from: (f32, f32),
+        to: (f32, f32),
+        filter: &ObjectFilter,
+    ) -> Vec<&ScenarioObject> {
+        self.spatial
+            .get(&self.format)
+            .in_area(&self.format, from, to, filter)
+    }
+
+    /// Find the objects within a radius of a point, sorted from closest to furthest.
+    pub fn objects_in_radius(
+        &self,
+        center: (f32, f32),
+        radius: f32,
+        filter: &ObjectFilter,
+    ) -> Vec<&ScenarioObject> {
+        self.spatial
+            .get(&self.format)
+            .in_radius(&self.format, center, radius, filter)
+    }
+
+    /// Find the object closest to a point.
+    pub fn nearest_object(
+        &self,
+        point: (f32, f32),
+        filter: &ObjectFilter,
+    ) -> Option<&ScenarioObject> {
+        self.spatial
+            .get(&self.format)
+            .nearest(&self.format, point, filter)
+    }
+
+    /// Get the object with the given ID.
+    pub fn object(&self, id: i32) -> Option<&ScenarioObject> {
+        let (player, index) = objects::find(&self.format, id)?;
+        Some(&self.format.player_objects[usize::from(player)][index])
+    }
+
+    /// Get a mutable reference to the object with the given ID.
+    ///
+    /// Changing the object's `id` or `garrisoned_in` fields directly does not update references
+    /// to it; use [`Scenario::garrison`] to garrison objects.
+    pub fn object_mut(&mut self, id: i32) -> Option<&mut ScenarioObject> {
+        self.spatial.clear();
+        let (player, index) = objects::find(&self.format, id)?;
+        Some(&mut self.format.player_objects[usize::from(player)][index])
+    }
+
+    /// Get the player that owns the object with the given ID. Player 0 is GAIA.
+    pub fn object_owner(&self, id: i32) -> Option<u8> {
+        objects::find(&self.format, id).map(|(player, _)| player)
+    }
+
+    /// Add an object for a player, and return its ID. Player 0 is GAIA.
+    ///
+    /// The object gets a new, unique ID; its `id` field is ignored. If `garrisoned_in` is set,
+    /// the object is garrisoned as with [`Scenario::garrison`].
+    pub fn add_object(
+        &mut self,
+        player: u8,
+        object: ScenarioObject,
+    ) -> std::result::Result<i32, ObjectError> {
+        self.spatial.clear();
+        objects::add(&mut self.format, player, object)
+    }
+
+    /// Remove the object with the given ID, and return it.
+    ///
+    /// Objects that were garrisoned inside it are ungarrisoned, and trigger conditions and
+    /// effects no longer refer to it.
+    pub fn remove_object(&mut self, id: i32) -> std::result::Result<ScenarioObject, ObjectError> {
+        self.spatial.clear();
+        objects::remove(&mut self.format, id)
+    }
+
+    /// Give the object with the given ID to another player. Player 0 is GAIA.
+    ///
+    /// Objects garrisoned inside it are given to the same player. If the object itself was
+    /// garrisoned, it is ungarrisoned.
+    pub fn move_object(&mut self, id: i32, player: u8) -> std::result::Result<(), ObjectError> {
+        self.spatial.clear();
+        objects::move_to_player(&mut self.format, id, player)
+    }
+
+    /// Garrison the object with the given ID inside another object of the same player.
+    ///
+    /// The object is moved to the position of the object it is garrisoned in. This does not check
+    /// whether the object types allow garrisoning.
+    /// Garrisons can not be nested, so the container must not be garrisoned itself, and the
+    /// object must not hold other objects.
+    pub fn garrison(&mut self, id: i32, container: i32) -> std::result::Result<(), ObjectError> {
+        self.spatial.clear();
+        objects::garrison(&mut self.format, id, container)
+    }
+
+    /// Ungarrison the object with the given ID. It stays at the position of the object it was
+    /// garrisoned in.
+    pub fn ungarrison(&mut self, id: i32) -> std::result::Result<(), ObjectError> {
+        self.spatial.clear();
+        objects::ungarrison(&mut self.format, id)
+    }
+
     pub fn world_players(&self) -> &[WorldPlayerData] {
         &self.format.world_players
     }
@@ -234,6 +617,296 @@ impl Scenario {
         &self.format.scenario_players
     }
 
+    /// Get a player's civilization.
+    pub fn player_civilization(&self, player: u8) -> Option<CivilizationID> {
+        setup::civilization(&self.format, player)
+    }
+
+    /// Set a player's civilization.
+    pub fn set_player_civilization(
+        &mut self,
+        player: u8,
+        civilization: CivilizationID,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_civilization(&mut self.format, player, civilization)
+    }
+
+    /// Get whether a player is controlled by a human or by the computer.
+    pub fn player_type(&self, player: u8) -> Option<PlayerType> {
+        setup::player_type(&self.format, player)
+    }
+
+    /// Set whether a player is controlled by a human or by the computer.
+    pub fn set_player_type(
+        &mut self,
+        player: u8,
+        player_type: PlayerType,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_player_type(&mut self.format, player, player_type)
+    }
+
+    /// Get a player's starting resources.
+    pub fn player_resources(&self, player: u8) -> Option<&PlayerStartResources> {
+        setup::resources(&self.format, player)
+    }
+
+    /// Set a player's starting resources.
+    ///
+    /// Fails if ore or trade goods are set to something other than their defaults, and the
+    /// scenario's game version can not store them; see [`VersionBundle::supports_ore_and_goods`].
+    pub fn set_player_resources(
+        &mut self,
+        player: u8,
+        resources: PlayerStartResources,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_resources(&mut self.format, self.version.data, player, resources)
+    }
+
+    /// Get a player's starting age.
+    pub fn player_starting_age(&self, player: u8) -> Option<StartingAge> {
+        setup::starting_age(&self.format, player)
+    }
+
+    /// Set a player's starting age. Fails if the scenario's game version does not have this
+    /// starting age.
+    pub fn set_player_starting_age(
+        &mut self,
+        player: u8,
+        age: StartingAge,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_starting_age(&mut self.format, self.version.data, player, age)
+    }
+
+    /// Get the initial diplomatic stance of `player` toward `other`.
+    pub fn diplomacy(&self, player: u8, other: u8) -> Option<DiplomaticStance> {
+        setup::diplomacy(&self.format, player, other)
+    }
+
+    /// Set the initial diplomatic stance of `player` toward `other`. This does not change the
+    /// stance of `other` toward `player`.
+    pub fn set_diplomacy(
+        &mut self,
+        player: u8,
+        other: u8,
+        stance: DiplomaticStance,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_diplomacy(&mut self.format, player, other, stance)
+    }
+
+    /// Get the lobby settings for teams.
+    pub fn team_settings(&self) -> TeamSettings {
+        self.format.tribe_scen.team_settings()
+    }
+
+    /// Set the lobby settings for teams. Fails if the scenario's game version can not store
+    /// these settings.
+    pub fn set_team_settings(
+        &mut self,
+        settings: TeamSettings,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_team_settings(&mut self.format, self.version.data, settings)
+    }
+
+    /// Get the scenario-wide victory settings.
+    pub fn victory_settings(&self) -> VictorySettings {
+        self.format.tribe_scen.victory_settings()
+    }
+
+    /// Set the scenario-wide victory settings. Fails if the scenario's game version can not store
+    /// these settings.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use genie_scx::{Scenario, VictoryMode, VictorySettings};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut scen = Scenario::read_from(std::fs::File::open("scenario.scx")?)?;
+    /// // Switch to a relic victory.
+    /// scen.set_victory_settings(VictorySettings {
+    ///     mode: VictoryMode::Custom,
+    ///     conquest: false,
+    ///     relics: 5,
+    ///     ..scen.victory_settings()
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_victory_settings(
+        &mut self,
+        settings: VictorySettings,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_victory_settings(&mut self.format, self.version.data, settings)
+    }
+
+    /// Get a player's victory conditions.
+    pub fn player_victory(&self, player: u8) -> Option<&[VictoryEntry]> {
+        setup::player_victory(&self.format, player)
+    }
+
+    /// Replace a player's victory conditions.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use genie_scx::{Scenario, VictoryEntry};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut scen = Scenario::read_from(std::fs::File::open("scenario.scx")?)?;
+    /// // Regicide: player 1 must kill the Kings of players 2 and 3.
+    /// scen.set_player_victory(1, vec![
+    ///     VictoryEntry::destroy_all(434, 2),
+    ///     VictoryEntry::destroy_all(434, 3),
+    /// ])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_player_victory(
+        &mut self,
+        player: u8,
+        entries: Vec<VictoryEntry>,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_player_victory(&mut self.format, player, entries)
+    }
+
+    /// Get the techs that are disabled for a player.
+    pub fn disabled_techs(&self, player: u8) -> Option<Vec<TechID>> {
+        let ids = setup::disabled(&self.format, player, DisabledList::Techs)?;
+        Some(
+            ids.iter()
+                .filter_map(|&id| TechID::try_from(id).ok())
+                .collect(),
+        )
+    }
+
+    /// Get the units that are disabled for a player.
+    pub fn disabled_units(&self, player: u8) -> Option<Vec<UnitTypeID>> {
+        let ids = setup::disabled(&self.format, player, DisabledList::Units)?;
+        Some(
+            ids.iter()
+                .filter_map(|&id| UnitTypeID::try_from(id).ok())
+                .collect(),
+        )
+    }
+
+    /// Get the buildings that are disabled for a player.
+    pub fn disabled_buildings(&self, player: u8) -> Option<Vec<UnitTypeID>> {
+        let ids = setup::disabled(&self.format, player, DisabledList::Buildings)?;
+        Some(
+            ids.iter()
+                .filter_map(|&id| UnitTypeID::try_from(id).ok())
+                .collect(),
+        )
+    }
+
+    /// Disable a tech for a player.
+    ///
+    /// Fails if the player already has as many disabled techs as the scenario's game version
+    /// supports; see [`VersionBundle::max_disabled_techs`].
+    pub fn disable_tech(
+        &mut self,
+        player: u8,
+        tech: TechID,
+    ) -> std::result::Result<(), SetupError> {
+        let id = i32::from(u16::from(tech));
+        setup::disable(
+            &mut self.format,
+            &self.version,
+            player,
+            DisabledList::Techs,
+            id,
+        )
+    }
+
+    /// Disable a unit for a player.
+    ///
+    /// Fails if the player already has as many disabled units as the scenario's game version
+    /// supports; see [`VersionBundle::max_disabled_units`].
+    pub fn disable_unit(
+        &mut self,
+        player: u8,
+        unit_type: UnitTypeID,
+    ) -> std::result::Result<(), SetupError> {
+        let id = i32::from(unit_type);
+        setup::disable(
+            &mut self.format,
+            &self.version,
+            player,
+            DisabledList::Units,
+            id,
+        )
+    }
+
+    /// Disable a building for a player.
+    ///
+    /// Fails if the player already has as many disabled buildings as the scenario's game version
+    /// supports; see [`VersionBundle::max_disabled_buildings`].
+    pub fn disable_building(
+        &mut self,
+        player: u8,
+        unit_type: UnitTypeID,
+    ) -> std::result::Result<(), SetupError> {
+        let id = i32::from(unit_type);
+        setup::disable(
+            &mut self.format,
+            &self.version,
+            player,
+            DisabledList::Buildings,
+            id,
+        )
+    }
+
+    /// Enable a tech that was disabled for a player. Returns whether the tech was disabled.
+    pub fn enable_tech(
+        &mut self,
+        player: u8,
+        tech: TechID,
+    ) -> std::result::Result<bool, SetupError> {
+        let id = i32::from(u16::from(tech));
+        setup::enable(&mut self.format, player, DisabledList::Techs, id)
+    }
+
+    /// Enable a unit that was disabled for a player. Returns whether the unit was disabled.
+    pub fn enable_unit(
+        &mut self,
+        player: u8,
+        unit_type: UnitTypeID,
+    ) -> std::result::Result<bool, SetupError> {
+        setup::enable(
+            &mut self.format,
+            player,
+            DisabledList::Units,
+            unit_type.into(),
+        )
+    }
+
+    /// Enable a building that was disabled for a player. Returns whether the building was
+    /// disabled.
+    pub fn enable_building(
+        &mut self,
+        player: u8,
+        unit_type: UnitTypeID,
+    ) -> std::result::Result<bool, SetupError> {
+        setup::enable(
+            &mut self.format,
+            player,
+            DisabledList::Buildings,
+            unit_type.into(),
+        )
+    }
+
+    /// Get whether a player can win together with their allies.
+    pub fn allied_victory(&self, player: u8) -> Option<bool> {
+        setup::allied_victory(&self.format, player)
+    }
+
+    /// Set whether a player can win together with their allies.
+    pub fn set_allied_victory(
+        &mut self,
+        player: u8,
+        enabled: bool,
+    ) -> std::result::Result<(), SetupError> {
+        setup::set_allied_victory(&mut self.format, player, enabled)
+    }
+
     /// Get the map/terrain data for this scenario.
     #[inline]
     pub fn map(&self) -> &Map {
@@ -257,4 +930,208 @@ impl Scenario {
     pub fn triggers_mut(&mut self) -> Option<&mut TriggerSystem> {
         self.format.triggers.as_mut()
     }
+
+    /// Change the size of the map, keeping the tile at (0, 0) in place.
+    ///
+    /// New tiles are filled with the default tile. Objects that are no longer on the map are
+    /// removed and returned, and trigger locations and areas are moved to the edge of the map.
+    /// Triggers no longer select the removed objects, and objects that were garrisoned in them
+    /// are ungarrisoned.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the width or height is 0.
+    pub fn resize(&mut self, width: u32, height: u32) -> Vec<ScenarioObject> {
+        self.crop(0, 0, width, height)
+    }
+
+    /// Keep only the area of the map of the given size, starting at the tile at (x, y).
+    ///
+    /// If the area extends past the edge of the map, the rest is filled with the default tile.
+    /// Objects, trigger locations and areas, the initial camera location, and the player start
+    /// locations are moved along with the terrain. Objects that are no longer on the map are
+    /// removed and returned, and trigger locations and areas are moved to the edge of the map.
+    /// Triggers no longer select the removed objects, and objects that were garrisoned in them
+    /// are ungarrisoned.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the width or height is 0.
+    pub fn crop(&mut self, x: u32, y: u32, width: u32, height: u32) -> Vec<ScenarioObject> {
+        assert!(width > 0 && height > 0, "map size must not be zero");
+        self.spatial.clear();
+        transform::transform(
+            &mut self.format,
+            Transform::Crop {
+                x,
+                y,
+                width,
+                height,
+            },
+        )
+    }
+
+    /// Rotate the map by a multiple of 90°.
+    ///
+    /// Objects, trigger locations and areas, the initial camera location, and the player start
+    /// locations are moved along with the terrain, and objects are turned to face the same way
+    /// relative to the terrain.
+    pub fn rotate(&mut self, rotation: Rotation) {
+        self.spatial.clear();
+        transform::transform(&mut self.format, Transform::Rotate(rotation));
+    }
+
+    /// Mirror the map across an axis.
+    ///
+    /// Objects, trigger locations and areas, the initial camera location, and the player start
+    /// locations are moved along with the terrain, and objects are turned to face the same way
+    /// relative to the terrain.
+    pub fn mirror(&mut self, mirror: Mirror) {
+        self.spatial.clear();
+        transform::transform(&mut self.format, Transform::Mirror(mirror));
+    }
+
+    /// Get the AI file of a kind that is embedded for a player.
+    pub fn player_ai_script(&self, player: u8, kind: AIScriptKind) -> Option<AIFile> {
+        ai::player_script(&self.format, player, kind)
+    }
+
+    /// List all AI files that are embedded for players, with the player number they belong to.
+    pub fn player_ai_scripts(&self) -> Vec<(u8, AIScriptKind, AIFile)> {
+        let num_players = self.format.player_objects.len().saturating_sub(1) as u8;
+        (1..=num_players)
+            .flat_map(|player| {
+                AIScriptKind::ALL.into_iter().filter_map(move |kind| {
+                    let file = self.player_ai_script(player, kind)?;
+                    Some((player, kind, file))
+                })
+            })
+            .collect()
+    }
+
+    /// Embed an AI file for a player, and return the one it replaces.
+    ///
+    /// The file is converted to the scenario's text encoding when writing, so writing fails if
+    /// the script contains characters that can not be represented in it. AI rules scripts can
+    /// only be stored in scenario data version 1.08 and up.
+    pub fn set_player_ai_script(
+        &mut self,
+        player: u8,
+        kind: AIScriptKind,
+        file: AIFile,
+    ) -> std::result::Result<Option<AIFile>, AIError> {
+        ai::set_player_script(&mut self.format, &self.version, player, kind, Some(file))
+    }
+
+    /// Remove an embedded AI file from a player, and return it.
+    pub fn remove_player_ai_script(
+        &mut self,
+        player: u8,
+        kind: AIScriptKind,
+    ) -> std::result::Result<Option<AIFile>, AIError> {
+        ai::set_player_script(&mut self.format, &self.version, player, kind, None)
+    }
+
+    /// Get the AI files that are bundled with the scenario, separately from the players.
+    ///
+    /// Only scenario format versions 1.18 to 1.99 can store these.
+    pub fn ai_files(&self) -> &[AIFile] {
+        ai::files(&self.format)
+    }
+
+    /// Bundle an AI file with the scenario, and return the bundled file with the same name that
+    /// it replaces. File names are compared case-insensitively.
+    pub fn set_ai_file(&mut self, file: AIFile) -> std::result::Result<Option<AIFile>, AIError> {
+        ai::set_file(&mut self.format, self.version.format, file)
+    }
+
+    /// Remove a bundled AI file by name, and return it.
+    pub fn remove_ai_file(&mut self, filename: &str) -> Option<AIFile> {
+        ai::remove_file(&mut self.format, filename)
+    }
+
+    /// Reorder, remove, or add players.
+    ///
+    /// Player `i + 1` becomes the player that was previously `players[i]`, and gets their
+    /// settings, objects, and AI files. A `None` entry adds an empty player, which keeps the
+    /// settings that were already in that slot. Players that are not listed are removed, and
+    /// their objects are returned.
+    ///
+    /// Diplomacy, victory conditions, and trigger conditions and effects are updated to use
+    /// the new player numbers. References to removed players are set to -1, and triggers no
+    /// longer select the removed objects.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use genie_scx::Scenario;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut scen = Scenario::read_from(std::fs::File::open("scenario.scx")?)?;
+    /// // Turn a 4 player scenario into a 1v1 between the old players 1 and 3.
+    /// scen.remap_players(&[Some(1), Some(3)])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remap_players(
+        &mut self,
+        players: &[Option<u8>],
+    ) -> std::result::Result<Vec<ScenarioObject>, RemapError> {
+        self.spatial.clear();
+        remap::remap_players(&mut self.format, players)
+    }
+
+    /// Swap two players, including their settings, objects, and references in triggers.
+    pub fn swap_players(&mut self, a: u8, b: u8) -> std::result::Result<(), RemapError> {
+        let num_players = self.format.player_objects.len().saturating_sub(1) as u8;
+        for player in [a, b] {
+            if player == 0 || player > num_players {
+                return Err(RemapError::InvalidPlayer(player));
+            }
+        }
+        let players: Vec<_> = (1..=num_players)
+            .map(|player| match player {
+                _ if player == a => Some(b),
+                _ if player == b => Some(a),
+                _ => Some(player),
+            })
+            .collect();
+        self.remap_players(&players)?;
+        Ok(())
+    }
+
+    /// Copy triggers, and optionally objects, from another scenario into this one.
+    ///
+    /// The copied triggers are added after the existing triggers, keeping their order. Trigger
+    /// conditions and effects that refer to other copied triggers or objects are updated to use
+    /// their new IDs; references to triggers or objects that were not copied are set to -1.
+    /// Conditions and effects get as many properties as this scenario's trigger version stores.
+    /// Fails if triggers are selected but this scenario's version can not store them.
+    ///
+    /// Trigger locations and areas that would not be on this map after moving them by the offset
+    /// are moved to its edge. Returns the selected objects that would not be on this map after
+    /// moving them; they are not copied.
+    pub fn merge(
+        &mut self,
+        source: &Scenario,
+        options: &MergeOptions,
+    ) -> std::result::Result<Vec<ScenarioObject>, MergeError> {
+        self.spatial.clear();
+        merge::merge(&mut self.format, &source.format, options)
+    }
+
+    /// Check that every object, terrain, tech, and unit referenced by this scenario exists in the
+    /// given data file.
+    ///
+    /// Returns an empty list if the scenario can be played with this data file.
+    #[cfg(feature = "validate")]
+    pub fn validate(&self, dat: &DatFile) -> Vec<ValidationIssue> {
+        validate::validate(&self.format, dat)
+    }
+
+    /// Find what changed between this scenario and a newer version of it.
+    ///
+    /// The changes are sorted by the section of the scenario they belong to.
+    pub fn diff(&self, other: &Scenario) -> Vec<ScenarioChange> {
+        diff::diff(self, other)
+    }
 }
@@ -1,10 +1,19 @@
+//! Map terrain data, and tools for editing it.
+//!
+//! The [`MapPainter`] type paints terrain and elevation with brushes, rectangles, and flood
+//! fills, while keeping the elevation valid for the game.
+mod paint;
+
+pub use paint::{Brush, BrushShape, MapPainter, TerrainRules};
+
+use crate::transform::{Mirror, Rotation, Transform};
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use genie_support::read_opt_u16;
 use std::io::{self, Read, Write};
 
 /// A map tile.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Tile {
     /// The terrain.
     pub terrain: u8,
@@ -86,7 +95,21 @@ impl Map {
 
     /// Read map/terrain data from an input stream.
     pub fn read_from(mut input: impl Read) -> Result<Self> {
-        let mut map = match input.read_u32::<LE>()? {
+        let mut map = Self::read_header(&mut input)?;
+
+        map.tiles.reserve((map.height * map.height) as usize);
+        for _ in 0..map.height {
+            for _ in 0..map.width {
+                map.tiles.push(Tile::read_from(&mut input, map.version)?);
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Read the map version and size from an input stream, without the tiles.
+    pub(crate) fn read_header(mut input: impl Read) -> Result<Self> {
+        let map = match input.read_u32::<LE>()? {
             0xDEADF00D => {
                 let version = input.read_u32::<LE>()?;
                 let render_waves = if version < 2 {
@@ -134,16 +157,20 @@ impl Map {
             .into());
         }
 
-        map.tiles.reserve((map.height * map.height) as usize);
-        for _ in 0..map.height {
-            for _ in 0..map.width {
-                map.tiles.push(Tile::read_from(&mut input, map.version)?);
-            }
-        }
-
         Ok(map)
     }
 
+    /// Skip over the tiles of a map whose header was read using `read_header`.
+    pub(crate) fn skip_tiles(&self, input: impl Read) -> Result<()> {
+        let tile_size = if self.version >= 1 { 7 } else { 3 };
+        let len = u64::from(self.width) * u64::from(self.height) * tile_size;
+        let skipped = io::copy(&mut input.take(len), &mut io::sink())?;
+        if skipped != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(())
+    }
+
     /// Write map/terrain data to an output stream.
     pub fn write_to(&self, mut output: impl Write, version: u32) -> Result<()> {
         if version != 0 {
@@ -166,6 +193,32 @@ impl Map {
         Ok(())
     }
 
+    /// Count the tiles that use data that was added in map version 1, like layered terrain.
+    pub(crate) fn num_layered_tiles(&self) -> usize {
+        self.tiles
+            .iter()
+            .filter(|tile| tile.layered_terrain.is_some() || tile.mask_type.is_some())
+            .count()
+    }
+
+    /// Should waves be rendered?
+    pub(crate) fn render_waves(&self) -> bool {
+        self.render_waves
+    }
+
+    /// Remove layered terrain and mask data from all tiles, keeping the base terrain.
+    pub(crate) fn clear_layered_terrain(&mut self) {
+        for tile in &mut self.tiles {
+            tile.layered_terrain = None;
+            tile.mask_type = None;
+        }
+    }
+
+    /// Set whether waves should be rendered.
+    pub(crate) fn set_render_waves(&mut self, render_waves: bool) {
+        self.render_waves = render_waves;
+    }
+
     /// Get the version of the map data.
     pub fn version(&self) -> u32 {
         self.version
@@ -234,4 +287,68 @@ impl Map {
     pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [Tile]> {
         self.tiles.chunks_exact_mut(self.width as usize)
     }
+
+    /// Change the size of the map, keeping the tile at (0, 0) in place.
+    ///
+    /// New tiles are filled with the default tile. This only changes the terrain; use
+    /// [`Scenario::resize`](crate::Scenario::resize) to also update objects and triggers.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the width or height is 0.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.crop(0, 0, width, height);
+    }
+
+    /// Keep only the area of the given size, starting at the tile at (x, y).
+    ///
+    /// If the area extends past the edge of the map, the rest is filled with the default tile.
+    /// This only changes the terrain; use [`Scenario::crop`](crate::Scenario::crop) to also
+    /// update objects and triggers.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the width or height is 0.
+    pub fn crop(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        assert!(width > 0 && height > 0, "map size must not be zero");
+        self.transform(Transform::Crop {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    /// Rotate the map by a multiple of 90°.
+    ///
+    /// This only changes the terrain; use [`Scenario::rotate`](crate::Scenario::rotate) to also
+    /// update objects and triggers.
+    pub fn rotate(&mut self, rotation: Rotation) {
+        self.transform(Transform::Rotate(rotation));
+    }
+
+    /// Mirror the map across an axis.
+    ///
+    /// This only changes the terrain; use [`Scenario::mirror`](crate::Scenario::mirror) to also
+    /// update objects and triggers.
+    pub fn mirror(&mut self, mirror: Mirror) {
+        self.transform(Transform::Mirror(mirror));
+    }
+
+    /// Move every tile to its transformed position.
+    pub(crate) fn transform(&mut self, transform: Transform) {
+        let (width, height) = transform.size(self.width, self.height);
+        let mut tiles = vec![Tile::default(); (width * height) as usize];
+        for (y, row) in self.rows().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let position = transform.tile((x as u32, y as u32), self.width, self.height);
+                if let Some((new_x, new_y)) = position {
+                    tiles[(new_y * width + new_x) as usize] = *tile;
+                }
+            }
+        }
+        self.width = width;
+        self.height = height;
+        self.tiles = tiles;
+    }
 }
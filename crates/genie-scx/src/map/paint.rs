@@ -0,0 +1,588 @@
+//! Terrain and elevation painting.
+
+use super::{Map, Tile};
+#[cfg(feature = "terrain-rules")]
+use genie_dat::DatFile;
+use std::collections::VecDeque;
+
+/// Index of the terrain restriction used by beach-only units in AoE2 data files.
+#[cfg(feature = "terrain-rules")]
+const BEACH_RESTRICTION: usize = 2;
+/// Index of the terrain restriction used by ships in AoE2 data files.
+#[cfg(feature = "terrain-rules")]
+const WATER_RESTRICTION: usize = 3;
+/// Index of the terrain restriction used by land units in AoE2 data files.
+#[cfg(feature = "terrain-rules")]
+const LAND_RESTRICTION: usize = 4;
+
+/// The shape of a brush.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushShape {
+    /// Paint every tile within `radius` tiles horizontally and vertically.
+    Square,
+    /// Paint tiles that are within `radius` steps of the center, not counting diagonal steps.
+    Diamond,
+    /// Paint tiles that are roughly `radius` tiles away from the center.
+    Circle,
+}
+
+/// A brush for painting terrain or elevation around a tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Brush {
+    shape: BrushShape,
+    radius: u32,
+}
+
+impl Default for Brush {
+    /// A brush that paints a single tile.
+    fn default() -> Self {
+        Self::square(0)
+    }
+}
+
+impl Brush {
+    /// Create a brush with the given shape. A brush with radius 0 paints a single tile.
+    pub fn new(shape: BrushShape, radius: u32) -> Self {
+        Self { shape, radius }
+    }
+
+    /// Create a square brush, covering `2 * radius + 1` tiles on each side.
+    pub fn square(radius: u32) -> Self {
+        Self::new(BrushShape::Square, radius)
+    }
+
+    /// Create a diamond-shaped brush.
+    pub fn diamond(radius: u32) -> Self {
+        Self::new(BrushShape::Diamond, radius)
+    }
+
+    /// Create a round brush.
+    pub fn circle(radius: u32) -> Self {
+        Self::new(BrushShape::Circle, radius)
+    }
+
+    /// Get the shape of this brush.
+    pub fn shape(&self) -> BrushShape {
+        self.shape
+    }
+
+    /// Get the radius of this brush.
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+
+    /// Does the brush cover the tile at the given offset from its center?
+    fn covers(&self, dx: i64, dy: i64) -> bool {
+        let radius = i64::from(self.radius);
+        match self.shape {
+            BrushShape::Square => dx.abs() <= radius && dy.abs() <= radius,
+            BrushShape::Diamond => dx.abs() + dy.abs() <= radius,
+            // Allowing up to r² + r instead of r² gives rounder shapes for small radii.
+            // Widened so that r² does not overflow for the largest radii.
+            BrushShape::Circle => {
+                let (dx, dy, radius) = (i128::from(dx), i128::from(dy), i128::from(radius));
+                dx * dx + dy * dy <= radius * radius + radius
+            }
+        }
+    }
+
+    /// Get the coordinates of the tiles covered by the brush when it is centered on the tile at
+    /// (x, y), leaving out tiles that are not on a map of the given size.
+    pub fn tiles(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32)> {
+        let radius = i64::from(self.radius);
+        let (x, y) = (i64::from(x), i64::from(y));
+        // Only visit offsets that are on the map, so huge brushes stay cheap.
+        let dx_range = (-radius).max(-x)..=radius.min(i64::from(width) - 1 - x);
+        let dy_range = (-radius).max(-y)..=radius.min(i64::from(height) - 1 - y);
+        let mut tiles = vec![];
+        for dy in dy_range {
+            for dx in dx_range.clone() {
+                if self.covers(dx, dy) {
+                    tiles.push(((x + dx) as u32, (y + dy) as u32));
+                }
+            }
+        }
+        tiles
+    }
+}
+
+/// Rules for where terrains can be placed.
+///
+/// - Disallowed terrains are never painted.
+/// - Water must be painted on flat ground, and its elevation is never changed.
+/// - Beaches can only be painted next to water.
+///
+/// Cliffs are not covered by these rules: they are objects rather than terrain, so painting never
+/// adds, removes, or checks them.
+#[derive(Debug, Clone)]
+pub struct TerrainRules {
+    allowed: Vec<bool>,
+    water: Vec<bool>,
+    beach: Vec<bool>,
+}
+
+impl Default for TerrainRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TerrainRules {
+    /// Create rules that allow every terrain anywhere.
+    pub fn new() -> Self {
+        Self {
+            allowed: vec![true; 256],
+            water: vec![false; 256],
+            beach: vec![false; 256],
+        }
+    }
+
+    /// Derive rules from a data file.
+    ///
+    /// Terrains that do not exist or are disabled in the data file are not allowed. Water and
+    /// beach terrains are found using the terrain restrictions for ships, beach units, and land
+    /// units, in the order used by AoE2 data files.
+    #[cfg(feature = "terrain-rules")]
+    pub fn from_dat(dat: &DatFile) -> Self {
+        let mut rules = Self::new();
+        for (id, allowed) in rules.allowed.iter_mut().enumerate() {
+            *allowed = dat
+                .terrains
+                .get(id)
+                .map_or(false, |terrain| terrain.enabled);
+        }
+
+        if dat.terrain_tables.len() > LAND_RESTRICTION {
+            let beach = &dat.terrain_tables[BEACH_RESTRICTION];
+            let water = &dat.terrain_tables[WATER_RESTRICTION];
+            let land = &dat.terrain_tables[LAND_RESTRICTION];
+            for id in 0..=u8::MAX {
+                if beach.is_passable(id) {
+                    rules.beach[usize::from(id)] = true;
+                } else if water.is_passable(id) && !land.is_passable(id) {
+                    rules.water[usize::from(id)] = true;
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Disallow painting a terrain.
+    pub fn with_disallowed(mut self, terrain: u8) -> Self {
+        self.allowed[usize::from(terrain)] = false;
+        self
+    }
+
+    /// Treat a terrain as water.
+    pub fn with_water(mut self, terrain: u8) -> Self {
+        self.water[usize::from(terrain)] = true;
+        self
+    }
+
+    /// Treat a terrain as a beach.
+    pub fn with_beach(mut self, terrain: u8) -> Self {
+        self.beach[usize::from(terrain)] = true;
+        self
+    }
+
+    /// Can this terrain be painted?
+    pub fn is_allowed(&self, terrain: u8) -> bool {
+        self.allowed[usize::from(terrain)]
+    }
+
+    /// Is this terrain water?
+    pub fn is_water(&self, terrain: u8) -> bool {
+        self.water[usize::from(terrain)]
+    }
+
+    /// Is this terrain a beach?
+    pub fn is_beach(&self, terrain: u8) -> bool {
+        self.beach[usize::from(terrain)]
+    }
+
+    /// Can the terrain be placed on the tile at the given index?
+    fn allows(&self, map: &Map, index: usize, terrain: u8) -> bool {
+        if !self.is_allowed(terrain) {
+            return false;
+        }
+        if self.is_water(terrain) {
+            let elevation = map.tiles[index].elevation;
+            return neighbors(map, index).all(|other| map.tiles[other].elevation == elevation);
+        }
+        if self.is_beach(terrain) {
+            return neighbors(map, index).any(|other| self.is_water(map.tiles[other].terrain));
+        }
+        true
+    }
+}
+
+/// Iterate over the indices of the (up to 8) tiles around the tile at the given index.
+fn neighbors(map: &Map, index: usize) -> impl Iterator<Item = usize> {
+    let (width, height) = (i64::from(map.width), i64::from(map.height));
+    let (x, y) = (index as i64 % width, index as i64 / width);
+    (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+        .filter(|&offset| offset != (0, 0))
+        .map(move |(dx, dy)| (x + dx, y + dy))
+        .filter(move |&(x, y)| x >= 0 && y >= 0 && x < width && y < height)
+        .map(move |(x, y)| (y * width + x) as usize)
+}
+
+/// Paints terrain and elevation on a map.
+///
+/// After painting elevation, the surrounding tiles are raised or lowered so that no two
+/// neighbouring tiles differ by more than one elevation level, which the game requires. The
+/// painted tiles themselves keep the new elevation, unless terrain rules fix the elevation of
+/// nearby tiles: painted tiles can only be one level higher or lower than those for every tile
+/// in between.
+///
+/// All painting methods return the number of tiles that were changed.
+///
+/// ## Usage
+///
+/// ```rust
+/// use genie_scx::map::{Brush, MapPainter};
+/// use genie_scx::Map;
+///
+/// let mut map = Map::new(40, 40);
+/// let mut painter = MapPainter::new(&mut map);
+/// painter.fill_terrain(0, 0, 40, 10, 1);
+/// painter.paint_elevation(20, 25, Brush::circle(3), 4);
+/// assert_eq!(map.tile(0, 0).unwrap().terrain, 1);
+/// assert_eq!(map.tile(20, 25).unwrap().elevation, 4);
+/// assert_eq!(map.tile(20, 32).unwrap().elevation, 0);
+/// ```
+#[derive(Debug)]
+pub struct MapPainter<'a> {
+    map: &'a mut Map,
+    rules: Option<TerrainRules>,
+    smooth_elevation: bool,
+}
+
+impl<'a> MapPainter<'a> {
+    /// Create a painter for a map, without terrain rules.
+    pub fn new(map: &'a mut Map) -> Self {
+        Self {
+            map,
+            rules: None,
+            smooth_elevation: true,
+        }
+    }
+
+    /// Only paint terrain where the given rules allow it.
+    pub fn with_rules(mut self, rules: TerrainRules) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Set whether to smooth the surrounding elevation after painting elevation. This is
+    /// enabled by default.
+    pub fn with_elevation_smoothing(mut self, smooth_elevation: bool) -> Self {
+        self.smooth_elevation = smooth_elevation;
+        self
+    }
+
+    /// Paint terrain with a brush centered on the tile at (x, y).
+    pub fn paint_terrain(&mut self, x: u32, y: u32, brush: Brush, terrain: u8) -> usize {
+        let tiles = self.brush_tiles(x, y, brush);
+        self.set_terrain(tiles, terrain)
+    }
+
+    /// Paint terrain on a rectangle, starting at the tile at (x, y).
+    pub fn fill_terrain(&mut self, x: u32, y: u32, width: u32, height: u32, terrain: u8) -> usize {
+        let tiles = self.rect_tiles(x, y, width, height);
+        self.set_terrain(tiles, terrain)
+    }
+
+    /// Paint terrain on the area of same-terrain tiles connected to the tile at (x, y).
+    pub fn flood_fill_terrain(&mut self, x: u32, y: u32, terrain: u8) -> usize {
+        let tiles = self.connected_tiles(x, y, |a, b| a.terrain == b.terrain);
+        self.set_terrain(tiles, terrain)
+    }
+
+    /// Paint elevation with a brush centered on the tile at (x, y).
+    pub fn paint_elevation(&mut self, x: u32, y: u32, brush: Brush, elevation: i8) -> usize {
+        let tiles = self.brush_tiles(x, y, brush);
+        self.set_elevation(tiles, elevation)
+    }
+
+    /// Paint elevation on a rectangle, starting at the tile at (x, y).
+    pub fn fill_elevation(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        elevation: i8,
+    ) -> usize {
+        let tiles = self.rect_tiles(x, y, width, height);
+        self.set_elevation(tiles, elevation)
+    }
+
+    /// Paint elevation on the area of same-elevation tiles connected to the tile at (x, y).
+    pub fn flood_fill_elevation(&mut self, x: u32, y: u32, elevation: i8) -> usize {
+        let tiles = self.connected_tiles(x, y, |a, b| a.elevation == b.elevation);
+        self.set_elevation(tiles, elevation)
+    }
+
+    /// Raise tiles throughout the map until no two neighbouring tiles differ by more than one
+    /// elevation level.
+    ///
+    /// This is useful after editing elevation using [`Map::tile_mut`]. Hills are kept as they
+    /// are, and the ground around them is raised.
+    pub fn smooth_elevation(&mut self) -> usize {
+        let mut changed = vec![false; self.map.tiles.len()];
+        let mut queue: VecDeque<usize> = (0..self.map.tiles.len()).collect();
+        while let Some(index) = queue.pop_front() {
+            let min_elevation = self.map.tiles[index].elevation.saturating_sub(1);
+            for other in neighbors(self.map, index).collect::<Vec<_>>() {
+                if self.map.tiles[other].elevation < min_elevation && !self.is_locked(other) {
+                    self.map.tiles[other].elevation = min_elevation;
+                    changed[other] = true;
+                    queue.push_back(other);
+                }
+            }
+        }
+        changed.into_iter().filter(|&changed| changed).count()
+    }
+
+    fn brush_tiles(&self, x: u32, y: u32, brush: Brush) -> Vec<usize> {
+        brush
+            .tiles(x, y, self.map.width, self.map.height)
+            .into_iter()
+            .map(|(x, y)| (y * self.map.width + x) as usize)
+            .collect()
+    }
+
+    fn rect_tiles(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<usize> {
+        let right = x.saturating_add(width).min(self.map.width);
+        let bottom = y.saturating_add(height).min(self.map.height);
+        (y..bottom)
+            .flat_map(|y| (x..right).map(move |x| (x, y)))
+            .map(|(x, y)| (y * self.map.width + x) as usize)
+            .collect()
+    }
+
+    /// Find the tiles that can be reached from the tile at (x, y) through horizontal and
+    /// vertical steps, without passing through tiles that do not match it.
+    fn connected_tiles(
+        &self,
+        x: u32,
+        y: u32,
+        matches: impl Fn(&Tile, &Tile) -> bool,
+    ) -> Vec<usize> {
+        let start = match self.map.tile(x, y) {
+            Some(tile) if x < self.map.width => *tile,
+            _ => return vec![],
+        };
+        let width = self.map.width as usize;
+        let mut seen = vec![false; self.map.tiles.len()];
+        let mut queue = VecDeque::new();
+        let mut tiles = vec![];
+        let first = (y * self.map.width + x) as usize;
+        seen[first] = true;
+        queue.push_back(first);
+        while let Some(index) = queue.pop_front() {
+            tiles.push(index);
+            let left = if index % width > 0 {
+                Some(index - 1)
+            } else {
+                None
+            };
+            let right = if index % width < width - 1 {
+                Some(index + 1)
+            } else {
+                None
+            };
+            let up = index.checked_sub(width);
+            let down = Some(index + width).filter(|&down| down < self.map.tiles.len());
+            for other in [left, right, up, down].into_iter().flatten() {
+                if !seen[other] && matches(&start, &self.map.tiles[other]) {
+                    seen[other] = true;
+                    queue.push_back(other);
+                }
+            }
+        }
+        tiles
+    }
+
+    fn set_terrain(&mut self, tiles: Vec<usize>, terrain: u8) -> usize {
+        let mut count = 0;
+        for index in tiles {
+            if self.map.tiles[index].terrain == terrain {
+                continue;
+            }
+            if let Some(rules) = &self.rules {
+                if !rules.allows(self.map, index, terrain) {
+                    continue;
+                }
+            }
+            self.map.tiles[index].terrain = terrain;
+            count += 1;
+        }
+        count
+    }
+
+    /// Is the elevation of this tile fixed by the terrain rules?
+    fn is_locked(&self, index: usize) -> bool {
+        self.rules
+            .as_ref()
+            .map_or(false, |rules| rules.is_water(self.map.tiles[index].terrain))
+    }
+
+    /// Get the lowest and highest elevation that each tile can have without differing by more
+    /// than one level from its neighbours, given the tiles whose elevation is fixed by the
+    /// terrain rules.
+    fn elevation_limits(&self) -> Vec<(i8, i8)> {
+        let mut limits = vec![(i8::MIN, i8::MAX); self.map.tiles.len()];
+        let mut queue: VecDeque<usize> = (0..self.map.tiles.len())
+            .filter(|&index| self.is_locked(index))
+            .collect();
+        for &index in &queue {
+            let elevation = self.map.tiles[index].elevation;
+            limits[index] = (elevation, elevation);
+        }
+        while let Some(index) = queue.pop_front() {
+            let (min, max) = limits[index];
+            let (min, max) = (min.saturating_sub(1), max.saturating_add(1));
+            for other in neighbors(self.map, index) {
+                let (other_min, other_max) = limits[other];
+                if min > other_min || max < other_max {
+                    limits[other] = (other_min.max(min), other_max.min(max));
+                    queue.push_back(other);
+                }
+            }
+        }
+        limits
+    }
+
+    fn set_elevation(&mut self, tiles: Vec<usize>, elevation: i8) -> usize {
+        let mut changed = vec![false; self.map.tiles.len()];
+        let mut fixed = vec![false; self.map.tiles.len()];
+        let mut queue = VecDeque::new();
+        let limits = self.elevation_limits();
+        for index in tiles {
+            if self.is_locked(index) {
+                continue;
+            }
+            let (min, max) = limits[index];
+            let elevation = elevation.min(max).max(min);
+            if self.map.tiles[index].elevation != elevation {
+                self.map.tiles[index].elevation = elevation;
+                changed[index] = true;
+            }
+            fixed[index] = true;
+            queue.push_back(index);
+        }
+
+        if self.smooth_elevation {
+            // Every tile moves towards the painted elevation, so this always finishes.
+            while let Some(index) = queue.pop_front() {
+                let current = self.map.tiles[index].elevation;
+                for other in neighbors(self.map, index).collect::<Vec<_>>() {
+                    if fixed[other] || self.is_locked(other) {
+                        continue;
+                    }
+                    let old = self.map.tiles[other].elevation;
+                    let new = old.clamp(current.saturating_sub(1), current.saturating_add(1));
+                    if new != old {
+                        self.map.tiles[other].elevation = new;
+                        changed[other] = true;
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+
+        changed.into_iter().filter(|&changed| changed).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_smooth(map: &Map) -> bool {
+        (0..map.tiles.len()).all(|index| {
+            neighbors(map, index)
+                .all(|other| (map.tiles[index].elevation - map.tiles[other].elevation).abs() <= 1)
+        })
+    }
+
+    #[test]
+    fn brush_shapes() {
+        assert_eq!(Brush::default().tiles(5, 5, 10, 10), vec![(5, 5)]);
+        assert_eq!(Brush::square(1).tiles(5, 5, 10, 10).len(), 9);
+        assert_eq!(Brush::diamond(1).tiles(5, 5, 10, 10).len(), 5);
+        assert_eq!(Brush::circle(2).tiles(5, 5, 10, 10).len(), 21);
+        assert_eq!(Brush::square(1).tiles(0, 0, 10, 10).len(), 4);
+        assert_eq!(Brush::circle(u32::MAX).tiles(5, 5, 10, 10).len(), 100);
+        assert_eq!(Brush::diamond(u32::MAX).tiles(0, 0, 10, 10).len(), 100);
+        assert!(Brush::square(3).tiles(0, 0, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn paint_terrain() {
+        let mut map = Map::new(20, 20);
+        let mut painter = MapPainter::new(&mut map);
+        assert_eq!(painter.fill_terrain(0, 0, 10, 20, 1), 200);
+        assert_eq!(painter.paint_terrain(15, 15, Brush::square(1), 1), 9);
+        assert_eq!(painter.flood_fill_terrain(0, 0, 2), 200);
+        assert_eq!(painter.flood_fill_terrain(19, 0, 3), 191);
+        assert_eq!(map.tile(0, 19).unwrap().terrain, 2);
+        assert_eq!(map.tile(15, 15).unwrap().terrain, 1);
+        assert_eq!(map.tile(19, 19).unwrap().terrain, 3);
+    }
+
+    #[test]
+    fn smooth_painted_elevation() {
+        let mut map = Map::new(30, 30);
+        let mut painter = MapPainter::new(&mut map);
+        painter.paint_elevation(10, 10, Brush::circle(2), 5);
+        painter.fill_elevation(20, 0, 10, 30, -1);
+        painter.paint_elevation(15, 13, Brush::default(), 0);
+        assert!(is_smooth(&map));
+        assert_eq!(map.tile(10, 10).unwrap().elevation, 5);
+        assert_eq!(map.tile(15, 13).unwrap().elevation, 0);
+        assert_eq!(map.tile(25, 25).unwrap().elevation, -1);
+
+        map.tile_mut(3, 3).unwrap().elevation = 4;
+        let mut painter = MapPainter::new(&mut map);
+        assert!(painter.smooth_elevation() > 0);
+        assert!(is_smooth(&map));
+        assert_eq!(map.tile(3, 3).unwrap().elevation, 4);
+    }
+
+    #[test]
+    #[cfg(feature = "terrain-rules")]
+    fn terrain_rules() -> anyhow::Result<()> {
+        let dat = DatFile::read_from(std::fs::File::open("../genie-dat/fixtures/aoc1.0c.dat")?)?;
+        let rules = TerrainRules::from_dat(&dat);
+        assert!(rules.is_water(1));
+        assert!(rules.is_beach(2));
+        assert!(!rules.is_water(0));
+        assert!(!rules.is_allowed(41));
+
+        let mut map = Map::new(20, 20);
+        let mut painter = MapPainter::new(&mut map).with_rules(rules);
+        assert_eq!(painter.paint_terrain(15, 15, Brush::default(), 2), 0);
+        assert_eq!(painter.paint_terrain(15, 15, Brush::default(), 41), 0);
+        painter.fill_terrain(0, 0, 5, 20, 1);
+        assert_eq!(painter.fill_terrain(5, 0, 1, 20, 2), 20);
+
+        // Water keeps its elevation, and the painted tiles next to it slope down to it.
+        painter.paint_elevation(6, 10, Brush::square(3), 3);
+        assert!(is_smooth(&map));
+        assert_eq!(map.tile(4, 10).unwrap().elevation, 0);
+        assert_eq!(map.tile(5, 10).unwrap().elevation, 1);
+        assert_eq!(map.tile(6, 10).unwrap().elevation, 2);
+        assert_eq!(map.tile(7, 10).unwrap().elevation, 3);
+
+        // Water cannot be painted on slopes.
+        let mut painter = MapPainter::new(&mut map).with_rules(TerrainRules::from_dat(&dat));
+        assert_eq!(painter.paint_terrain(10, 10, Brush::default(), 1), 0);
+        assert_eq!(painter.paint_terrain(15, 15, Brush::default(), 1), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,491 @@
+//! Copying triggers and objects from one scenario into another.
+
+use crate::format::SCXFormat;
+use crate::objects;
+use crate::types::trigger_properties;
+use crate::{ObjectFilter, ScenarioObject, Trigger};
+use std::collections::HashMap;
+
+/// Errors that may occur while merging scenarios.
+#[derive(Debug, thiserror::Error)]
+pub enum MergeError {
+    /// The source scenario has no trigger with this ID.
+    #[error("no trigger with ID {} in the source scenario", .0)]
+    InvalidTrigger(usize),
+    /// A trigger or object would belong to a player that the target scenario does not have.
+    #[error("player {} does not exist in the target scenario", .0)]
+    InvalidPlayer(i32),
+    /// Triggers were selected, but the target scenario's version can not store triggers.
+    #[error("the target scenario can not store triggers")]
+    TriggersUnsupported,
+}
+
+/// Selects what to copy from another scenario, and how to fit it into the target scenario.
+///
+/// The default options copy every trigger, no objects, and keep player numbers and locations
+/// as they are.
+///
+/// ## Usage
+///
+/// ```rust,no_run
+/// # use genie_scx::{MergeOptions, ObjectFilter, Scenario};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut scen = Scenario::read_from(std::fs::File::open("scenario.scx")?)?;
+/// # let ruleset = Scenario::read_from(std::fs::File::open("ruleset.scx")?)?;
+/// // Copy a king-of-the-hill ruleset, placing its monument and areas 40 tiles further east,
+/// // and giving player 1's triggers to player 3.
+/// let options = MergeOptions::new()
+///     .with_objects(ObjectFilter::new().with_object_type(276.into()))
+///     .with_player(1, 3)
+///     .with_offset(40, 0);
+/// scen.merge(&ruleset, &options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    triggers: Vec<usize>,
+    objects: Option<ObjectFilter>,
+    players: Vec<(u8, u8)>,
+    offset: (i32, i32),
+}
+
+impl MergeOptions {
+    /// Create options that copy every trigger and nothing else.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only copy the trigger with this ID. This can be called multiple times to copy several
+    /// triggers.
+    pub fn with_trigger(mut self, id: usize) -> Self {
+        self.triggers.push(id);
+        self
+    }
+
+    /// Also copy the objects selected by this filter.
+    pub fn with_objects(mut self, filter: ObjectFilter) -> Self {
+        self.objects = Some(filter);
+        self
+    }
+
+    /// Give everything that belongs to player `from` in the source scenario to player `to` in
+    /// the target scenario. Player 0 is GAIA.
+    pub fn with_player(mut self, from: u8, to: u8) -> Self {
+        self.players.retain(|&(old, _)| old != from);
+        self.players.push((from, to));
+        self
+    }
+
+    /// Move trigger locations, trigger areas, and objects by this many tiles.
+    ///
+    /// Trigger locations and areas that end up outside the target map are moved to its edge, as
+    /// when cropping a map. Objects that end up outside it are not copied.
+    pub fn with_offset(mut self, x: i32, y: i32) -> Self {
+        self.offset = (x, y);
+        self
+    }
+
+    fn map_player(&self, player: i32) -> i32 {
+        self.players
+            .iter()
+            .find(|&&(from, _)| i32::from(from) == player)
+            .map_or(player, |&(_, to)| i32::from(to))
+    }
+
+    /// Move a tile by the offset, clamping it to a map of the given size. Unset tiles, with
+    /// negative coordinates, are left as they are.
+    fn offset_tile(&self, (x, y): (i32, i32), (width, height): (u32, u32)) -> (i32, i32) {
+        if x < 0 || y < 0 {
+            return (x, y);
+        }
+        let clamp = |value: i32, max: u32| value.clamp(0, (max as i32 - 1).max(0));
+        (
+            clamp(x.saturating_add(self.offset.0), width),
+            clamp(y.saturating_add(self.offset.1), height),
+        )
+    }
+
+    /// Move an area by the offset, cropping it to a map of the given size. The corners are
+    /// sorted first, so the result always has its smallest coordinates in the first corner. Areas
+    /// that end up entirely outside the map shrink to the nearest row or column of edge tiles.
+    fn offset_area(&self, area: (i32, i32, i32, i32), size: (u32, u32)) -> (i32, i32, i32, i32) {
+        if area.0 < 0 || area.1 < 0 || area.2 < 0 || area.3 < 0 {
+            return area;
+        }
+        let (x1, y1) = self.offset_tile((area.0.min(area.2), area.1.min(area.3)), size);
+        let (x2, y2) = self.offset_tile((area.0.max(area.2), area.1.max(area.3)), size);
+        (x1, y1, x2, y2)
+    }
+}
+
+/// Find the IDs of the triggers to copy, in display order.
+fn selected_triggers(source: &SCXFormat, options: &MergeOptions) -> Result<Vec<usize>, MergeError> {
+    let source_triggers = match &source.triggers {
+        Some(triggers) => triggers,
+        None => {
+            return match options.triggers.first() {
+                Some(&id) => Err(MergeError::InvalidTrigger(id)),
+                None => Ok(vec![]),
+            }
+        }
+    };
+    let num_triggers = source_triggers.num_triggers() as usize;
+    if let Some(&id) = options.triggers.iter().find(|&&id| id >= num_triggers) {
+        return Err(MergeError::InvalidTrigger(id));
+    }
+    Ok(source_triggers
+        .trigger_ids()
+        .filter(|id| options.triggers.is_empty() || options.triggers.contains(id))
+        .collect())
+}
+
+/// Copy triggers, and optionally objects, from `source` into `target`.
+///
+/// Returns the selected objects that would not be on the target map after moving them; they are
+/// not copied. Nothing is changed if an error is returned.
+pub(crate) fn merge(
+    target: &mut SCXFormat,
+    source: &SCXFormat,
+    options: &MergeOptions,
+) -> Result<Vec<ScenarioObject>, MergeError> {
+    let trigger_ids = selected_triggers(source, options)?;
+    if !trigger_ids.is_empty() && !target.version().supports_triggers() {
+        return Err(MergeError::TriggersUnsupported);
+    }
+    let source_triggers: Vec<&Trigger> = source
+        .triggers
+        .iter()
+        .flat_map(|triggers| triggers.triggers_unordered())
+        .collect();
+
+    let num_players = target.player_objects.len() as i32;
+    let check_player = |player: i32| {
+        let player = options.map_player(player);
+        if player >= num_players {
+            Err(MergeError::InvalidPlayer(player))
+        } else {
+            Ok(player)
+        }
+    };
+
+    let mut objects = vec![];
+    if let Some(filter) = &options.objects {
+        for (player, player_objects) in source.player_objects.iter().enumerate() {
+            for object in player_objects {
+                if filter.matches(player as u8, object) {
+                    objects.push((check_player(player as i32)?, object));
+                }
+            }
+        }
+    }
+    for &id in &trigger_ids {
+        let trigger = source_triggers[id];
+        for condition in trigger.conditions_unordered() {
+            check_player(condition.player_id())?;
+        }
+        for effect in trigger.effects_unordered() {
+            check_player(effect.source_player_id())?;
+            check_player(effect.target_player_id())?;
+        }
+    }
+
+    let map_size = (target.map.width(), target.map.height());
+    let (width, height) = (map_size.0 as f32, map_size.1 as f32);
+    let (dx, dy) = (options.offset.0 as f32, options.offset.1 as f32);
+    let mut skipped = vec![];
+    let mut new_object_ids = HashMap::new();
+    let mut added = vec![];
+    for (player, object) in objects {
+        let mut object = object.clone();
+        let (x, y) = (object.position.0 + dx, object.position.1 + dy);
+        if x < 0.0 || y < 0.0 || x >= width || y >= height {
+            skipped.push(object);
+            continue;
+        }
+        object.position = (x, y, object.position.2);
+        let id = objects::allocate_id(target);
+        new_object_ids.insert(object.id, id);
+        object.id = id;
+        target.player_objects[player as usize].push(object);
+        added.push((
+            player as usize,
+            target.player_objects[player as usize].len() - 1,
+        ));
+    }
+    // Containers that were not copied can not hold the new objects.
+    for (player, index) in added {
+        let object = &mut target.player_objects[player][index];
+        object.garrisoned_in = object
+            .garrisoned_in
+            .and_then(|container| new_object_ids.get(&container).copied());
+    }
+
+    if trigger_ids.is_empty() {
+        return Ok(skipped);
+    }
+    let target_triggers = target.triggers.get_or_insert_with(Default::default);
+    let (num_condition_properties, num_effect_properties) =
+        trigger_properties(target_triggers.version());
+    let first_id = target_triggers.num_triggers() as i32;
+    let new_trigger_ids: HashMap<i32, i32> = trigger_ids
+        .iter()
+        .enumerate()
+        .map(|(index, &id)| (id as i32, first_id + index as i32))
+        .collect();
+    let map_trigger = |id: i32| -> i32 {
+        if id < 0 {
+            return id;
+        }
+        new_trigger_ids.get(&id).copied().unwrap_or(-1)
+    };
+    let map_object = |id: i32| -> i32 {
+        if id < 0 {
+            return id;
+        }
+        new_object_ids.get(&id).copied().unwrap_or(-1)
+    };
+
+    for id in trigger_ids {
+        let mut trigger = source_triggers[id].clone();
+        for condition in trigger.conditions_unordered_mut() {
+            condition.resize_properties(num_condition_properties);
+            condition.set_player_id(options.map_player(condition.player_id()));
+            condition.set_trigger_id(map_trigger(condition.trigger_id()));
+            condition.set_primary_object(map_object(condition.primary_object()));
+            condition.set_secondary_object(map_object(condition.secondary_object()));
+            condition.set_area(options.offset_area(condition.area(), map_size));
+        }
+        for effect in trigger.effects_unordered_mut() {
+            effect.resize_properties(num_effect_properties);
+            effect.set_source_player_id(options.map_player(effect.source_player_id()));
+            effect.set_target_player_id(options.map_player(effect.target_player_id()));
+            effect.set_trigger_id(map_trigger(effect.trigger_id()));
+            effect.set_object_id(map_object(effect.object_id()));
+            if !effect.objects().is_empty() {
+                let objects = effect
+                    .objects()
+                    .iter()
+                    .filter_map(|id| new_object_ids.get(id).copied())
+                    .collect();
+                effect.set_objects(objects);
+            }
+            effect.set_location(options.offset_tile(effect.location(), map_size));
+            effect.set_area(options.offset_area(effect.area(), map_size));
+        }
+        target_triggers.push(trigger);
+    }
+
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MergeError, MergeOptions};
+    use crate::{ObjectFilter, Scenario};
+    use std::fs::File;
+
+    /// Find a trigger with an effect that activates or deactivates another trigger.
+    fn find_trigger_reference(scen: &Scenario) -> Option<(usize, usize)> {
+        scen.triggers()?
+            .triggers_unordered()
+            .enumerate()
+            .find_map(|(id, trigger)| {
+                let effect = trigger
+                    .effects_unordered()
+                    .find(|effect| effect.trigger_id() >= 0)?;
+                Some((id, effect.trigger_id() as usize))
+            })
+    }
+
+    #[test]
+    fn merge_triggers() -> anyhow::Result<()> {
+        let source = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let mut target =
+            Scenario::read_from(File::open("test/scenarios/Year_of_the_Pig.aoe2scenario")?)?;
+        let (id, referenced) = find_trigger_reference(&source).unwrap();
+        let num_triggers = target
+            .triggers()
+            .map_or(0, |triggers| triggers.num_triggers());
+
+        let options = MergeOptions::new()
+            .with_trigger(id)
+            .with_trigger(referenced)
+            .with_player(1, 2)
+            .with_offset(3, 4);
+        target.merge(&source, &options)?;
+
+        let triggers = target.triggers().unwrap();
+        assert_eq!(triggers.num_triggers(), num_triggers + 2);
+        let copied: Vec<_> = triggers
+            .triggers_unordered()
+            .skip(num_triggers as usize)
+            .collect();
+        let source_trigger = source
+            .triggers()
+            .unwrap()
+            .triggers_unordered()
+            .nth(id)
+            .unwrap();
+        let trigger = copied
+            .iter()
+            .find(|trigger| trigger.name() == source_trigger.name())
+            .unwrap();
+        for (before, after) in source_trigger
+            .effects_unordered()
+            .zip(trigger.effects_unordered())
+        {
+            if before.trigger_id() >= 0 {
+                assert!(after.trigger_id() >= num_triggers as i32);
+            }
+            if before.source_player_id() == 1 {
+                assert_eq!(after.source_player_id(), 2);
+            }
+            let (x, y) = before.location();
+            if x >= 0 && y >= 0 {
+                assert_eq!(after.location(), (x + 3, y + 4));
+            }
+        }
+
+        let mut out = vec![];
+        target.write_to(&mut out)?;
+        Scenario::read_from(&out[..])?;
+        Ok(())
+    }
+
+    #[test]
+    fn merge_objects() -> anyhow::Result<()> {
+        let source = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let mut target =
+            Scenario::read_from(File::open("test/scenarios/Year_of_the_Pig.aoe2scenario")?)?;
+        let num_source = source.format.player_objects[1].len();
+        let num_target = target.format.player_objects[3].len();
+
+        let options = MergeOptions::new()
+            .with_trigger(0)
+            .with_objects(ObjectFilter::new().with_player(1))
+            .with_player(1, 3);
+        let skipped = target.merge(&source, &options)?;
+        let num_merged = target.format.player_objects[3].len();
+        assert_eq!(num_merged + skipped.len(), num_target + num_source);
+
+        let options = MergeOptions::new()
+            .with_objects(ObjectFilter::new().with_player(1))
+            .with_player(1, 200);
+        assert!(matches!(
+            target.merge(&source, &options),
+            Err(MergeError::InvalidPlayer(200))
+        ));
+        let options = MergeOptions::new().with_trigger(100_000);
+        assert!(matches!(
+            target.merge(&source, &options),
+            Err(MergeError::InvalidTrigger(100_000))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn clamp_trigger_locations() -> anyhow::Result<()> {
+        let source = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let mut target =
+            Scenario::read_from(File::open("test/scenarios/Year_of_the_Pig.aoe2scenario")?)?;
+        let (width, height) = (target.map().width() as i32, target.map().height() as i32);
+        let num_triggers = target
+            .triggers()
+            .map_or(0, |triggers| triggers.num_triggers());
+
+        let options = MergeOptions::new().with_offset(10_000, -10_000);
+        target.merge(&source, &options)?;
+        let triggers = target.triggers().unwrap();
+        for trigger in triggers.triggers_unordered().skip(num_triggers as usize) {
+            for effect in trigger.effects_unordered() {
+                let (x, y) = effect.location();
+                if x >= 0 && y >= 0 {
+                    assert_eq!((x, y), (width - 1, 0));
+                }
+            }
+            for condition in trigger.conditions_unordered() {
+                let (x1, y1, x2, y2) = condition.area();
+                if x1 >= 0 && y1 >= 0 && x2 >= 0 && y2 >= 0 {
+                    assert!(x1 < width && x2 < width && y1 < height && y2 < height);
+                    assert!(x1 <= x2 && y1 <= y2);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn crop_areas_at_map_edge() {
+        let size = (10, 10);
+        let options = MergeOptions::new().with_offset(-4, 6);
+        // Partly pushed off the map: cropped to the part that is still on it.
+        assert_eq!(options.offset_area((2, 1, 7, 5), size), (0, 7, 3, 9));
+        // Corners stored the other way around are sorted.
+        assert_eq!(options.offset_area((7, 5, 2, 1), size), (0, 7, 3, 9));
+        // Entirely pushed off the map: shrinks to the edge.
+        assert_eq!(options.offset_area((0, 4, 3, 8), size), (0, 9, 0, 9));
+        // Unset areas are left alone.
+        assert_eq!(
+            options.offset_area((-1, -1, -1, -1), size),
+            (-1, -1, -1, -1)
+        );
+
+        for x in -20..20 {
+            for y in -20..20 {
+                let options = MergeOptions::new().with_offset(x, y);
+                let (x1, y1, x2, y2) = options.offset_area((3, 8, 6, 2), size);
+                assert!(x1 <= x2 && y1 <= y2);
+                assert!((0..10).contains(&x1) && (0..10).contains(&x2));
+                assert!((0..10).contains(&y1) && (0..10).contains(&y2));
+            }
+        }
+    }
+
+    #[test]
+    fn merge_triggers_into_older_version() -> anyhow::Result<()> {
+        let source = Scenario::read_from(File::open(
+            "test/scenarios/Hotkey Trainer Buildings.aoe2scenario",
+        )?)?;
+        let mut target =
+            Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let num_triggers = target.triggers().unwrap().num_triggers();
+        target.merge(&source, &MergeOptions::new())?;
+
+        let mut out = vec![];
+        target.write_to(&mut out)?;
+        let target = Scenario::read_from(&out[..])?;
+        let triggers = target.triggers().unwrap();
+        assert_eq!(
+            triggers.num_triggers(),
+            num_triggers + source.triggers().unwrap().num_triggers()
+        );
+        // The number of properties is stored after the condition or effect type.
+        let num_properties = |bytes: &[u8]| i32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        for trigger in triggers.triggers_unordered().skip(num_triggers as usize) {
+            for condition in trigger.conditions_unordered() {
+                let mut bytes = vec![];
+                condition.write_to(&mut bytes, triggers.version())?;
+                assert_eq!(num_properties(&bytes), 18);
+            }
+            for effect in trigger.effects_unordered() {
+                let mut bytes = vec![];
+                effect.write_to(&mut bytes, triggers.version())?;
+                assert_eq!(num_properties(&bytes), 24);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn merge_triggers_into_version_without_triggers() -> anyhow::Result<()> {
+        let source = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let mut target =
+            Scenario::read_from(File::open("test/scenarios/Jeremiah Johnson (Update).scx")?)?;
+        assert!(matches!(
+            target.merge(&source, &MergeOptions::new()),
+            Err(MergeError::TriggersUnsupported)
+        ));
+        Ok(())
+    }
+}
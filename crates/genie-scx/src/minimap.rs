@@ -0,0 +1,373 @@
+//! Renders scenario maps to minimap images.
+
+use crate::format::SCXFormat;
+use crate::{Scenario, ScenarioObject};
+use genie_dat::unit_type::UnitType;
+use genie_dat::{DatFile, PaletteIndex};
+use jascpal::Palette;
+use rgb::RGB8;
+use std::convert::TryFrom;
+
+/// How the map is laid out in a minimap image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinimapProjection {
+    /// Every tile is a square, with the tile at (0, 0) in the top left corner and x increasing
+    /// to the right.
+    TopDown,
+    /// The map is a diamond like the in-game minimap, with the tile at (0, 0) in the left corner,
+    /// x increasing towards the bottom right, and y increasing towards the top right. The image
+    /// is twice as wide as it is high.
+    Isometric,
+}
+
+/// An RGB image, stored row by row from the top left.
+#[derive(Debug, Clone)]
+pub struct MinimapImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<RGB8>,
+}
+
+impl MinimapImage {
+    /// Get the width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the colour of the pixel at the given coordinates.
+    ///
+    /// If the coordinates are out of bounds, returns None.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<RGB8> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.pixels.get((y * self.width + x) as usize).copied()
+    }
+
+    /// Get all the pixels in the image.
+    pub fn pixels(&self) -> &[RGB8] {
+        &self.pixels
+    }
+
+    /// Get the pixels as packed 8-bit RGB bytes, as expected by most image encoders.
+    pub fn to_rgb_bytes(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| [pixel.r, pixel.g, pixel.b])
+            .collect()
+    }
+}
+
+/// Render a scenario map to an image, using the colours the game uses on the minimap.
+///
+/// Tiles are coloured with the minimap colours of their terrain. The game picks a lighter or
+/// darker colour for tiles on a slope: here, a tile that is higher than the tile at
+/// `(x - 1, y - 1)` uses the "high" colour, and a tile that is lower uses the "low" colour.
+/// Higher tiles are also drawn slightly lighter, so plateaus stand out.
+///
+/// Objects are drawn over the terrain. Player objects use the minimap colour of the player's
+/// colour table, and GAIA objects use the map colour of their unit type. Objects cover the tiles
+/// within their radius.
+///
+/// Palette indices are resolved with the given palette, usually the game's main palette
+/// (`50500.pal` in AoE2).
+///
+/// ## Usage
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use genie_scx::{MinimapProjection, MinimapRenderer, Scenario};
+/// use std::fs::File;
+/// let dat = genie_dat::DatFile::read_from(File::open("empires2_x1_p1.dat")?)?;
+/// let palette = jascpal::Palette::read_from(File::open("50500.pal")?)?;
+/// let scenario = Scenario::read_from(File::open("scenario.scx")?)?;
+/// let image = MinimapRenderer::new(&dat, &palette)
+///     .with_projection(MinimapProjection::Isometric)
+///     .with_scale(2)
+///     .render(&scenario);
+/// let rgb = image.to_rgb_bytes();
+/// assert_eq!(rgb.len(), (image.width() * image.height() * 3) as usize);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MinimapRenderer<'a> {
+    dat: &'a DatFile,
+    palette: &'a Palette,
+    projection: MinimapProjection,
+    scale: u32,
+    elevation_shading: bool,
+    draw_objects: bool,
+}
+
+impl<'a> MinimapRenderer<'a> {
+    /// Create a renderer that draws top-down images with one pixel per tile.
+    pub fn new(dat: &'a DatFile, palette: &'a Palette) -> Self {
+        Self {
+            dat,
+            palette,
+            projection: MinimapProjection::TopDown,
+            scale: 1,
+            elevation_shading: true,
+            draw_objects: true,
+        }
+    }
+
+    /// Set how the map is laid out in the image.
+    pub fn with_projection(mut self, projection: MinimapProjection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Set the size of a tile in pixels. For the isometric projection, this is the height of a
+    /// tile; tiles are twice as wide. Defaults to 1.
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    /// Set whether higher tiles are drawn lighter. Defaults to true. Slopes always use the slope
+    /// colours of the terrain.
+    pub fn with_elevation_shading(mut self, elevation_shading: bool) -> Self {
+        self.elevation_shading = elevation_shading;
+        self
+    }
+
+    /// Set whether objects are drawn. Defaults to true.
+    pub fn with_objects(mut self, draw_objects: bool) -> Self {
+        self.draw_objects = draw_objects;
+        self
+    }
+
+    /// Render the map of a scenario.
+    pub fn render(&self, scen: &Scenario) -> MinimapImage {
+        let tiles = self.tile_colors(&scen.format);
+        let (width, height) = (scen.map().width(), scen.map().height());
+        match self.projection {
+            MinimapProjection::TopDown => self.project_top_down(&tiles, width, height),
+            MinimapProjection::Isometric => self.project_isometric(&tiles, width, height),
+        }
+    }
+
+    /// Look up a palette colour. Indices outside the palette are black.
+    fn color(&self, index: PaletteIndex) -> RGB8 {
+        self.palette
+            .colors()
+            .get(usize::from(index))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Find the unit type of an object in any civilization.
+    fn unit_type(&self, object: &ScenarioObject) -> Option<&UnitType> {
+        self.dat
+            .civilizations
+            .iter()
+            .find_map(|civ| civ.get_unit_type(object.object_type))
+    }
+
+    /// Get the colour of every tile, row by row.
+    fn tile_colors(&self, format: &SCXFormat) -> Vec<RGB8> {
+        let map = &format.map;
+        let width = map.width() as usize;
+        let mut colors = Vec::with_capacity(width * map.height() as usize);
+        for (y, row) in map.rows().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let terrain = match self.dat.get_terrain(tile.terrain) {
+                    Some(terrain) => terrain,
+                    None => {
+                        colors.push(RGB8::default());
+                        continue;
+                    }
+                };
+                let neighbour = if x > 0 && y > 0 {
+                    map.tile(x as u32 - 1, y as u32 - 1)
+                } else {
+                    None
+                };
+                let neighbour_elevation = neighbour.map_or(tile.elevation, |tile| tile.elevation);
+                let index = match tile.elevation.cmp(&neighbour_elevation) {
+                    std::cmp::Ordering::Greater => terrain.minimap_color_high,
+                    std::cmp::Ordering::Less => terrain.minimap_color_low,
+                    std::cmp::Ordering::Equal => terrain.minimap_color_medium,
+                };
+                let mut color = self.color(index.into());
+                if self.elevation_shading {
+                    color = lighten(color, tile.elevation);
+                }
+                colors.push(color);
+            }
+        }
+
+        if self.draw_objects {
+            for (player, objects) in format.player_objects.iter().enumerate() {
+                let player_color = self.player_color(format, player);
+                for object in objects {
+                    let unit_type = self.unit_type(object);
+                    let color = match (player_color, unit_type) {
+                        (Some(color), _) => color,
+                        (None, Some(unit_type)) => self.color(unit_type.static_.map_color.into()),
+                        (None, None) => continue,
+                    };
+                    let radius = unit_type.map_or((0.0, 0.0), |unit_type| {
+                        (unit_type.static_.radius.0, unit_type.static_.radius.1)
+                    });
+                    let (x, y) = (object.position.0, object.position.1);
+                    let tile_range = |center: f32, radius: f32, max: u32| {
+                        let start = (center - radius).floor().max(0.0) as u32;
+                        let end = ((center + radius).ceil() as u32).max(start + 1).min(max);
+                        start..end
+                    };
+                    for tile_y in tile_range(y, radius.1, map.height()) {
+                        for tile_x in tile_range(x, radius.0, map.width()) {
+                            colors[tile_y as usize * width + tile_x as usize] = color;
+                        }
+                    }
+                }
+            }
+        }
+
+        colors
+    }
+
+    /// Get the minimap colour for a player's objects. Returns None for GAIA.
+    fn player_color(&self, format: &SCXFormat, player: usize) -> Option<RGB8> {
+        if player == 0 {
+            return None;
+        }
+        let color_index = format
+            .scenario_players
+            .get(player - 1)
+            .and_then(|data| data.color)
+            .and_then(|color| usize::try_from(color).ok())
+            .filter(|&color| color < self.dat.color_tables.len())
+            .unwrap_or(player - 1);
+        self.dat
+            .color_tables
+            .get(color_index)
+            .map(|table| self.color(table.minimap_colors.0))
+    }
+
+    fn project_top_down(&self, tiles: &[RGB8], width: u32, height: u32) -> MinimapImage {
+        let scale = self.scale;
+        let (image_width, image_height) = (width * scale, height * scale);
+        let mut pixels = Vec::with_capacity((image_width * image_height) as usize);
+        for y in 0..image_height {
+            for x in 0..image_width {
+                pixels.push(tiles[((y / scale) * width + x / scale) as usize]);
+            }
+        }
+        MinimapImage {
+            width: image_width,
+            height: image_height,
+            pixels,
+        }
+    }
+
+    fn project_isometric(&self, tiles: &[RGB8], width: u32, height: u32) -> MinimapImage {
+        let scale = self.scale as f32;
+        let image_width = (width + height) * self.scale;
+        let image_height = image_width / 2;
+        let mut pixels = Vec::with_capacity((image_width * image_height) as usize);
+        for py in 0..image_height {
+            for px in 0..image_width {
+                // `u` runs along x + y, and `v` along x - y, both in tiles.
+                let u = (px as f32 + 0.5) / scale;
+                let v = (py as f32 + 0.5) / scale * 2.0 - height as f32;
+                let (x, y) = ((u + v) / 2.0, (u - v) / 2.0);
+                let color = if x >= 0.0 && y >= 0.0 && x < width as f32 && y < height as f32 {
+                    tiles[(y as u32 * width + x as u32) as usize]
+                } else {
+                    RGB8::default()
+                };
+                pixels.push(color);
+            }
+        }
+        MinimapImage {
+            width: image_width,
+            height: image_height,
+            pixels,
+        }
+    }
+}
+
+/// Make a colour lighter for every elevation level.
+fn lighten(color: RGB8, elevation: i8) -> RGB8 {
+    let factor = 1.0 + 0.06 * f32::from(elevation.max(0));
+    let channel = |value: u8| (f32::from(value) * factor).min(255.0) as u8;
+    RGB8 {
+        r: channel(color.r),
+        g: channel(color.g),
+        b: channel(color.b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jascpal::Color;
+    use std::fs::File;
+
+    /// A palette where every index is a different grey.
+    fn grey_palette() -> Palette {
+        let mut palette = Palette::new();
+        for index in 0..=255 {
+            palette.add(Color {
+                r: index,
+                g: index,
+                b: index,
+            });
+        }
+        palette
+    }
+
+    #[test]
+    fn render_top_down() -> anyhow::Result<()> {
+        let dat = DatFile::read_from(File::open("../genie-dat/fixtures/aoc1.0c.dat")?)?;
+        let palette = grey_palette();
+        let scen = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let image = MinimapRenderer::new(&dat, &palette)
+            .with_scale(2)
+            .with_objects(false)
+            .with_elevation_shading(false)
+            .render(&scen);
+        assert_eq!(image.width(), scen.map().width() * 2);
+        assert_eq!(image.height(), scen.map().height() * 2);
+        assert_eq!(image.to_rgb_bytes().len(), image.pixels().len() * 3);
+
+        let tile = scen.map().tile(0, 0).unwrap();
+        let terrain = dat.get_terrain(tile.terrain).unwrap();
+        let expected = terrain.minimap_color_medium;
+        assert_eq!(
+            image.pixel(1, 1),
+            Some(RGB8::new(expected, expected, expected))
+        );
+        assert_eq!(image.pixel(image.width(), 0), None);
+        Ok(())
+    }
+
+    #[test]
+    fn render_isometric_with_objects() -> anyhow::Result<()> {
+        let dat = DatFile::read_from(File::open("../genie-dat/fixtures/aoc1.0c.dat")?)?;
+        let palette = grey_palette();
+        let scen = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let renderer = MinimapRenderer::new(&dat, &palette)
+            .with_projection(MinimapProjection::Isometric)
+            .with_objects(false);
+        let terrain_only = renderer.clone().render(&scen);
+        let with_objects = renderer.with_objects(true).render(&scen);
+
+        let size = scen.map().width() + scen.map().height();
+        assert_eq!(terrain_only.width(), size);
+        assert_eq!(terrain_only.height(), size / 2);
+        // The corners are outside the diamond.
+        assert_eq!(terrain_only.pixel(0, 0), Some(RGB8::default()));
+        assert_ne!(terrain_only.pixels(), with_objects.pixels());
+        Ok(())
+    }
+}
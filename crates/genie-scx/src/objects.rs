@@ -0,0 +1,328 @@
+//! Adding, removing, and moving scenario objects.
+
+use crate::format::SCXFormat;
+use crate::ScenarioObject;
+
+/// Errors that may occur while editing scenario objects.
+#[derive(Debug, thiserror::Error)]
+pub enum ObjectError {
+    /// The scenario does not have an object list for this player.
+    #[error("player {} does not exist in this scenario", .0)]
+    InvalidPlayer(u8),
+    /// There is no object with this ID.
+    #[error("no object with ID {}", .0)]
+    MissingObject(i32),
+    /// An object can not be garrisoned in itself.
+    #[error("object {} can not be garrisoned in itself", .0)]
+    SelfGarrison(i32),
+    /// Objects can only be garrisoned in objects that belong to the same player.
+    #[error("object {} and object {} belong to different players", .0, .1)]
+    DifferentOwners(i32, i32),
+    /// Garrisons can not be nested: the container can not itself be garrisoned, and the object
+    /// can not hold other objects.
+    #[error("object {} can not be garrisoned in object {}, garrisons can not be nested", .0, .1)]
+    NestedGarrison(i32, i32),
+}
+
+/// Find the player that owns an object, and its index in that player's object list.
+pub(crate) fn find(format: &SCXFormat, id: i32) -> Option<(u8, usize)> {
+    format
+        .player_objects
+        .iter()
+        .enumerate()
+        .find_map(|(player, objects)| {
+            let index = objects.iter().position(|object| object.id == id)?;
+            Some((player as u8, index))
+        })
+}
+
+fn find_or_err(format: &SCXFormat, id: i32) -> Result<(u8, usize), ObjectError> {
+    find(format, id).ok_or(ObjectError::MissingObject(id))
+}
+
+fn check_player(format: &SCXFormat, player: u8) -> Result<(), ObjectError> {
+    if usize::from(player) < format.player_objects.len() {
+        Ok(())
+    } else {
+        Err(ObjectError::InvalidPlayer(player))
+    }
+}
+
+/// Get an unused object ID, and reserve it.
+///
+/// ID 0 is never used, because some game versions store "not garrisoned" as 0.
+pub(crate) fn allocate_id(format: &mut SCXFormat) -> i32 {
+    let max_id = format
+        .player_objects
+        .iter()
+        .flatten()
+        .map(|object| object.id)
+        .max()
+        .unwrap_or(0);
+    let id = format.next_object_id.max(max_id + 1).max(1);
+    format.next_object_id = id + 1;
+    id
+}
+
+pub(crate) fn add(
+    format: &mut SCXFormat,
+    player: u8,
+    mut object: ScenarioObject,
+) -> Result<i32, ObjectError> {
+    check_player(format, player)?;
+    let next_object_id = format.next_object_id;
+    let container = object.garrisoned_in.take();
+    object.id = allocate_id(format);
+    let id = object.id;
+    format.player_objects[usize::from(player)].push(object);
+    if let Some(container) = container {
+        if let Err(err) = garrison(format, id, container) {
+            format.player_objects[usize::from(player)].pop();
+            format.next_object_id = next_object_id;
+            return Err(err);
+        }
+    }
+    Ok(id)
+}
+
+/// Clear references to objects that were removed: objects garrisoned in them are ungarrisoned,
+/// and triggers no longer select them.
+pub(crate) fn clear_references(format: &mut SCXFormat, ids: &[i32]) {
+    if ids.is_empty() {
+        return;
+    }
+
+    for other in format.player_objects.iter_mut().flatten() {
+        if other
+            .garrisoned_in
+            .map_or(false, |container| ids.contains(&container))
+        {
+            other.garrisoned_in = None;
+        }
+    }
+
+    if let Some(triggers) = format.triggers.as_mut() {
+        for trigger in triggers.triggers_unordered_mut() {
+            for condition in trigger.conditions_unordered_mut() {
+                if ids.contains(&condition.primary_object()) {
+                    condition.set_primary_object(-1);
+                }
+                if ids.contains(&condition.secondary_object()) {
+                    condition.set_secondary_object(-1);
+                }
+            }
+            for effect in trigger.effects_unordered_mut() {
+                if ids.contains(&effect.object_id()) {
+                    effect.set_object_id(-1);
+                }
+                if effect.objects().iter().any(|object| ids.contains(object)) {
+                    let objects = effect
+                        .objects()
+                        .iter()
+                        .copied()
+                        .filter(|object| !ids.contains(object))
+                        .collect();
+                    effect.set_objects(objects);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn remove(format: &mut SCXFormat, id: i32) -> Result<ScenarioObject, ObjectError> {
+    let (player, index) = find_or_err(format, id)?;
+    let object = format.player_objects[usize::from(player)].remove(index);
+
+    clear_references(format, &[id]);
+
+    Ok(object)
+}
+
+pub(crate) fn move_to_player(
+    format: &mut SCXFormat,
+    id: i32,
+    player: u8,
+) -> Result<(), ObjectError> {
+    check_player(format, player)?;
+    let (owner, index) = find_or_err(format, id)?;
+    if owner == player {
+        return Ok(());
+    }
+    let mut object = format.player_objects[usize::from(owner)].remove(index);
+    // Units can not stay inside a building that now belongs to someone else.
+    object.garrisoned_in = None;
+    format.player_objects[usize::from(player)].push(object);
+
+    let (inside, rest) = std::mem::take(&mut format.player_objects[usize::from(owner)])
+        .into_iter()
+        .partition(|other| other.garrisoned_in == Some(id));
+    format.player_objects[usize::from(owner)] = rest;
+    format.player_objects[usize::from(player)].extend(inside);
+    Ok(())
+}
+
+pub(crate) fn garrison(format: &mut SCXFormat, id: i32, container: i32) -> Result<(), ObjectError> {
+    if id == container {
+        return Err(ObjectError::SelfGarrison(id));
+    }
+    let (owner, index) = find_or_err(format, id)?;
+    let (container_owner, container_index) = find_or_err(format, container)?;
+    if owner != container_owner {
+        return Err(ObjectError::DifferentOwners(id, container));
+    }
+    let objects = &mut format.player_objects[usize::from(owner)];
+    // This also rules out cycles, as every object in a cycle would be garrisoned.
+    let holds_objects = objects
+        .iter()
+        .any(|object| object.garrisoned_in == Some(id));
+    if objects[container_index].garrisoned_in.is_some() || holds_objects {
+        return Err(ObjectError::NestedGarrison(id, container));
+    }
+    let position = objects[container_index].position;
+    objects[index].garrisoned_in = Some(container);
+    objects[index].position = position;
+    Ok(())
+}
+
+pub(crate) fn ungarrison(format: &mut SCXFormat, id: i32) -> Result<(), ObjectError> {
+    let (owner, index) = find_or_err(format, id)?;
+    format.player_objects[usize::from(owner)][index].garrisoned_in = None;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ObjectError, Scenario, ScenarioObject};
+    use std::fs::File;
+
+    #[test]
+    fn add_and_remove_objects() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let count = scen.objects().count();
+        let max_id = scen.objects().map(|object| object.id).max().unwrap_or(0);
+
+        let castle = scen.add_object(
+            1,
+            ScenarioObject {
+                object_type: 82.into(),
+                position: (10.5, 10.5, 0.0),
+                ..Default::default()
+            },
+        )?;
+        assert!(castle > max_id);
+        let monk = scen.add_object(
+            1,
+            ScenarioObject {
+                object_type: 125.into(),
+                position: (20.5, 20.5, 0.0),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(monk, castle + 1);
+        assert_eq!(scen.objects().count(), count + 2);
+        assert_eq!(scen.object_owner(monk), Some(1));
+
+        scen.garrison(monk, castle)?;
+        assert_eq!(scen.object(monk).unwrap().garrisoned_in, Some(castle));
+        assert_eq!(scen.object(monk).unwrap().position, (10.5, 10.5, 0.0));
+
+        let removed = scen.remove_object(castle)?;
+        assert_eq!(removed.id, castle);
+        assert_eq!(scen.object(monk).unwrap().garrisoned_in, None);
+        assert!(matches!(
+            scen.remove_object(castle),
+            Err(ObjectError::MissingObject(_))
+        ));
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.objects().count(), count + 1);
+        assert!(scen.object(monk).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn move_garrisoned_objects() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let tower = scen.add_object(1, Default::default())?;
+        let archer = scen.add_object(1, Default::default())?;
+        let spy = scen.add_object(2, Default::default())?;
+        scen.garrison(archer, tower)?;
+        assert!(matches!(
+            scen.garrison(spy, tower),
+            Err(ObjectError::DifferentOwners(_, _))
+        ));
+        assert!(matches!(
+            scen.garrison(tower, tower),
+            Err(ObjectError::SelfGarrison(_))
+        ));
+        let ram = scen.add_object(1, Default::default())?;
+        assert!(matches!(
+            scen.garrison(tower, archer),
+            Err(ObjectError::NestedGarrison(_, _))
+        ));
+        assert!(matches!(
+            scen.garrison(ram, archer),
+            Err(ObjectError::NestedGarrison(_, _))
+        ));
+        assert!(matches!(
+            scen.garrison(tower, ram),
+            Err(ObjectError::NestedGarrison(_, _))
+        ));
+        scen.garrison(archer, ram)?;
+        assert!(matches!(
+            scen.garrison(ram, archer),
+            Err(ObjectError::NestedGarrison(_, _))
+        ));
+        scen.garrison(archer, tower)?;
+
+        scen.move_object(tower, 2)?;
+        assert_eq!(scen.object_owner(tower), Some(2));
+        assert_eq!(scen.object_owner(archer), Some(2));
+        assert_eq!(scen.object(archer).unwrap().garrisoned_in, Some(tower));
+
+        scen.move_object(archer, 1)?;
+        assert_eq!(scen.object(archer).unwrap().garrisoned_in, None);
+        assert!(matches!(
+            scen.move_object(archer, 100),
+            Err(ObjectError::InvalidPlayer(100))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn remove_trigger_references() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let id = scen.add_object(1, Default::default())?;
+        let effect = scen
+            .triggers_mut()
+            .unwrap()
+            .triggers_unordered_mut()
+            .flat_map(|trigger| trigger.effects_unordered_mut())
+            .next()
+            .unwrap();
+        effect.set_objects(vec![id, 12345]);
+        effect.set_object_id(id);
+
+        scen.remove_object(id)?;
+        let effect = scen
+            .triggers()
+            .unwrap()
+            .triggers_unordered()
+            .flat_map(|trigger| trigger.effects_unordered())
+            .next()
+            .unwrap();
+        assert_eq!(effect.objects(), &[12345]);
+        assert_eq!(effect.num_objects(), 1);
+        assert_eq!(effect.object_id(), -1);
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        Scenario::read_from(&out[..])?;
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::types::supports_ore_and_goods;
 use crate::victory::VictoryConditions;
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
@@ -22,14 +23,21 @@ pub struct PlayerFiles {
     pub(crate) ai_rules: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+/// Starting resources for a player.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PlayerStartResources {
-    pub(crate) gold: i32,
-    pub(crate) wood: i32,
-    pub(crate) food: i32,
-    pub(crate) stone: i32,
-    pub(crate) ore: i32,
-    pub(crate) goods: i32,
+    /// Initial gold count.
+    pub gold: i32,
+    /// Initial wood count.
+    pub wood: i32,
+    /// Initial food count.
+    pub food: i32,
+    /// Initial stone count.
+    pub stone: i32,
+    /// Initial ore count. Only stored in data version 1.17 and later.
+    pub ore: i32,
+    /// Initial trade goods count. Only stored in data version 1.17 and later.
+    pub goods: i32,
     pub(crate) player_color: Option<i32>,
 }
 
@@ -48,18 +56,26 @@ impl Default for PlayerStartResources {
 }
 
 impl PlayerStartResources {
+    /// Can these resources be stored in scenarios for the given data version?
+    ///
+    /// Versions before 1.17 do not store ore and trade goods, and always use the defaults.
+    pub fn is_supported(&self, version: f32) -> bool {
+        let default = Self::default();
+        supports_ore_and_goods(version) || (self.ore == default.ore && self.goods == default.goods)
+    }
+
     pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
         Ok(Self {
             gold: input.read_i32::<LE>()?,
             wood: input.read_i32::<LE>()?,
             food: input.read_i32::<LE>()?,
             stone: input.read_i32::<LE>()?,
-            ore: if version >= 1.17 {
+            ore: if supports_ore_and_goods(version) {
                 input.read_i32::<LE>()?
             } else {
                 100
             },
-            goods: if version >= 1.17 {
+            goods: if supports_ore_and_goods(version) {
                 input.read_i32::<LE>()?
             } else {
                 0
@@ -77,7 +93,7 @@ impl PlayerStartResources {
         output.write_i32::<LE>(self.wood)?;
         output.write_i32::<LE>(self.food)?;
         output.write_i32::<LE>(self.stone)?;
-        if version >= 1.17 {
+        if supports_ore_and_goods(version) {
             output.write_i32::<LE>(self.ore)?;
             output.write_i32::<LE>(self.goods)?;
         }
@@ -218,6 +234,11 @@ impl ScenarioPlayerData {
 
         Ok(())
     }
+
+    /// Get all the strings in the player data, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.name.iter_mut()
+    }
 }
 
 /// Initial player attributes.
@@ -0,0 +1,235 @@
+//! Reordering, removing, and adding players.
+
+use crate::format::SCXFormat;
+use crate::objects;
+use crate::ScenarioObject;
+
+/// Errors that may occur while remapping players.
+#[derive(Debug, thiserror::Error)]
+pub enum RemapError {
+    /// The scenario does not have this many player slots.
+    #[error("scenario supports up to {} players, but got {}", .0, .1)]
+    TooManyPlayers(usize, usize),
+    /// The player number is not a valid player in this scenario.
+    #[error("player {} does not exist in this scenario", .0)]
+    InvalidPlayer(u8),
+    /// The player was mapped to multiple new player numbers.
+    #[error("player {} is used more than once", .0)]
+    DuplicatePlayer(u8),
+}
+
+/// Reorder a per-player list, so that entry `i` gets the value of entry `sources[i]`.
+///
+/// Entries whose source is out of bounds are left unchanged.
+pub(crate) fn permute<T: Clone>(list: &mut [T], sources: &[usize]) {
+    let old = list.to_vec();
+    for (item, &source) in list.iter_mut().zip(sources) {
+        if let Some(value) = old.get(source) {
+            *item = value.clone();
+        }
+    }
+}
+
+/// Remap players so that player `i + 1` becomes the old player `players[i]`.
+///
+/// Slots that are not assigned an old player keep their settings, and are marked as active.
+/// Slots after the end of `players` are marked as inactive. Returns the objects of players that
+/// were removed.
+pub(crate) fn remap_players(
+    format: &mut SCXFormat,
+    players: &[Option<u8>],
+) -> Result<Vec<ScenarioObject>, RemapError> {
+    let max_players = format.player_objects.len().saturating_sub(1);
+    if players.len() > max_players {
+        return Err(RemapError::TooManyPlayers(max_players, players.len()));
+    }
+    let mut new_numbers = vec![None; max_players];
+    for (new, &old) in players.iter().enumerate() {
+        if let Some(old) = old {
+            let index = usize::from(old).wrapping_sub(1);
+            match new_numbers.get_mut(index) {
+                None => return Err(RemapError::InvalidPlayer(old)),
+                Some(Some(_)) => return Err(RemapError::DuplicatePlayer(old)),
+                Some(slot) => *slot = Some(new as i32 + 1),
+            }
+        }
+    }
+
+    // Slots that are not assigned a player keep their own settings.
+    let sources: Vec<usize> = (0..16)
+        .map(|new| match players.get(new) {
+            Some(&Some(old)) => usize::from(old) - 1,
+            _ => new,
+        })
+        .collect();
+    let map_player = |player: i32| -> i32 {
+        if player <= 0 {
+            return player;
+        }
+        match new_numbers.get(player as usize - 1) {
+            Some(Some(new)) => *new,
+            Some(None) => -1,
+            None => player,
+        }
+    };
+
+    format.tribe_scen.remap_players(&sources, map_player);
+    for (index, player) in players.iter().enumerate() {
+        if player.is_none() {
+            format.tribe_scen.set_player_active(index, true);
+        }
+    }
+    for index in players.len()..16 {
+        format.tribe_scen.set_player_active(index, false);
+    }
+    format.header.active_player_count = format
+        .tribe_scen
+        .player_civilizations()
+        .filter(|(active, _)| *active)
+        .count() as u32;
+
+    permute(&mut format.world_players, &sources);
+    permute(&mut format.scenario_players, &sources);
+    for player in format.scenario_players.iter_mut() {
+        // These lists are indexed by player number, including GAIA.
+        if let Some((_, relations)) = player.relations.split_first_mut() {
+            permute(relations, &sources);
+        }
+        if let Some((_, unit_diplomacy)) = player.unit_diplomacy.split_first_mut() {
+            permute(unit_diplomacy, &sources);
+        }
+        player.victory.map_players(map_player);
+    }
+
+    let mut old_objects = std::mem::take(&mut format.player_objects);
+    let mut new_objects = vec![vec![]; old_objects.len()];
+    new_objects[0] = std::mem::take(&mut old_objects[0]);
+    for (new, &old) in players.iter().enumerate() {
+        if let Some(old) = old {
+            new_objects[new + 1] = std::mem::take(&mut old_objects[usize::from(old)]);
+        }
+    }
+    let removed: Vec<ScenarioObject> = old_objects.into_iter().flatten().collect();
+    format.player_objects = new_objects;
+    let removed_ids: Vec<i32> = removed.iter().map(|object| object.id).collect();
+    objects::clear_references(format, &removed_ids);
+
+    if let Some(triggers) = format.triggers.as_mut() {
+        for trigger in triggers.triggers_unordered_mut() {
+            for condition in trigger.conditions_unordered_mut() {
+                condition.set_player_id(map_player(condition.player_id()));
+            }
+            for effect in trigger.effects_unordered_mut() {
+                effect.set_source_player_id(map_player(effect.source_player_id()));
+                effect.set_target_player_id(map_player(effect.target_player_id()));
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RemapError, Scenario};
+    use std::fs::File;
+
+    #[test]
+    fn swap_players() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let original = Scenario::read_from(&mut f)?;
+        let mut scen = original.clone();
+        scen.swap_players(1, 2)?;
+
+        let players = original.scenario_players();
+        assert_eq!(scen.scenario_players()[0].name, players[1].name);
+        assert_eq!(scen.scenario_players()[1].name, players[0].name);
+        assert_eq!(scen.scenario_players()[0].location, players[1].location);
+        assert_eq!(
+            scen.format.player_objects[1].len(),
+            original.format.player_objects[2].len()
+        );
+        assert_eq!(
+            scen.format.player_objects[2].len(),
+            original.format.player_objects[1].len()
+        );
+
+        let old_triggers = original.triggers().unwrap().triggers_unordered();
+        let new_triggers = scen.triggers().unwrap().triggers_unordered();
+        for (old, new) in old_triggers.zip(new_triggers) {
+            for (old, new) in old.effects_unordered().zip(new.effects_unordered()) {
+                let expected = match old.source_player_id() {
+                    1 => 2,
+                    2 => 1,
+                    other => other,
+                };
+                assert_eq!(new.source_player_id(), expected);
+            }
+        }
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        Scenario::read_from(&out[..])?;
+        Ok(())
+    }
+
+    #[test]
+    fn reduce_players() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let original = Scenario::read_from(&mut f)?;
+        let mut scen = original.clone();
+        // Point a trigger at one of the objects that will be removed.
+        let doomed = original.format.player_objects[1][0].id;
+        let trigger = scen
+            .format
+            .triggers
+            .as_mut()
+            .unwrap()
+            .triggers_unordered_mut()
+            .find(|trigger| trigger.effects_unordered().next().is_some())
+            .unwrap();
+        trigger
+            .effects_unordered_mut()
+            .next()
+            .unwrap()
+            .set_object_id(doomed);
+        let removed = scen.remap_players(&[Some(2), None])?;
+        assert!(removed.iter().any(|object| object.id == doomed));
+
+        let kept =
+            original.format.player_objects[0].len() + original.format.player_objects[2].len();
+        assert_eq!(scen.objects().count(), kept);
+        assert_eq!(removed.len(), original.objects().count() - kept);
+        assert_eq!(scen.header().active_player_count, 2);
+        let active: Vec<_> = scen
+            .format
+            .tribe_scen
+            .player_civilizations()
+            .map(|(active, _)| active)
+            .collect();
+        assert_eq!(&active[..3], &[true, true, false]);
+
+        for trigger in scen.triggers().unwrap().triggers_unordered() {
+            for condition in trigger.conditions_unordered() {
+                assert!(condition.player_id() <= 1);
+            }
+            for effect in trigger.effects_unordered() {
+                assert_ne!(effect.object_id(), doomed);
+            }
+        }
+
+        assert!(matches!(
+            scen.remap_players(&[Some(1), Some(1)]),
+            Err(RemapError::DuplicatePlayer(1))
+        ));
+        assert!(matches!(
+            scen.remap_players(&[Some(9)]),
+            Err(RemapError::InvalidPlayer(9))
+        ));
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        Scenario::read_from(&out[..])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,560 @@
+//! Game setup: civilizations, player types, starting resources and ages, diplomacy, teams,
+//! victory conditions, and disabled techs, units, and buildings.
+
+use crate::format::{LostInformation, SCXFormat};
+use crate::player::PlayerStartResources;
+use crate::types::{stored_team_settings, DiplomaticStance, StartingAge, StoredTeamSettings};
+use crate::victory::{VictoryEntry, VictorySettings};
+use crate::VersionBundle;
+use genie_support::CivilizationID;
+
+/// Errors that may occur while changing player settings.
+#[derive(Debug, thiserror::Error)]
+pub enum SetupError {
+    /// The player number is not a valid player in this scenario.
+    #[error("player {} does not exist in this scenario", .0)]
+    InvalidPlayer(u8),
+    /// A player's diplomatic stance toward itself can not be changed.
+    #[error("player {} can not have a diplomatic stance toward itself", .0)]
+    SelfDiplomacy(u8),
+    /// The setting can not be stored in the scenario's game version.
+    #[error("the scenario's game version can not store the {}", .0)]
+    Unsupported(LostInformation),
+}
+
+/// Who controls a player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerType {
+    /// The player is controlled by the computer.
+    Computer,
+    /// The player can be controlled by a human.
+    Human,
+}
+
+impl From<i32> for PlayerType {
+    fn from(n: i32) -> Self {
+        match n {
+            0 => PlayerType::Computer,
+            _ => PlayerType::Human,
+        }
+    }
+}
+
+impl From<PlayerType> for i32 {
+    fn from(player_type: PlayerType) -> i32 {
+        match player_type {
+            PlayerType::Computer => 0,
+            PlayerType::Human => 1,
+        }
+    }
+}
+
+/// Lobby settings for teams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TeamSettings {
+    /// Whether players are locked into the teams set by the scenario.
+    pub locked: bool,
+    /// Whether players can change teams in the lobby.
+    pub can_change_teams: bool,
+    /// Whether players start in random locations.
+    pub random_start_locations: bool,
+    /// The maximum number of teams.
+    pub max_teams: u8,
+}
+
+impl Default for TeamSettings {
+    fn default() -> Self {
+        Self {
+            locked: false,
+            can_change_teams: true,
+            random_start_locations: true,
+            max_teams: 4,
+        }
+    }
+}
+
+impl TeamSettings {
+    /// Can these settings be stored in scenarios for the given data version?
+    ///
+    /// Version 1.23 only stores whether teams are locked, and older versions always use the
+    /// default settings.
+    pub fn is_supported(&self, version: f32) -> bool {
+        match stored_team_settings(version) {
+            StoredTeamSettings::All => true,
+            StoredTeamSettings::LockedOnly => {
+                *self
+                    == Self {
+                        locked: self.locked,
+                        ..Self::default()
+                    }
+            }
+            StoredTeamSettings::None => *self == Self::default(),
+        }
+    }
+}
+
+/// Get the index into per-player lists for a player number.
+pub(crate) fn player_index(format: &SCXFormat, player: u8) -> Option<usize> {
+    let num_players = format.player_objects.len().saturating_sub(1);
+    if player == 0 || usize::from(player) > num_players {
+        None
+    } else {
+        Some(usize::from(player) - 1)
+    }
+}
+
+fn player_index_or_err(format: &SCXFormat, player: u8) -> Result<usize, SetupError> {
+    player_index(format, player).ok_or(SetupError::InvalidPlayer(player))
+}
+
+pub(crate) fn civilization(format: &SCXFormat, player: u8) -> Option<CivilizationID> {
+    let index = player_index(format, player)?;
+    let civilization = format.tribe_scen.base.player_base_properties[index].civilization;
+    u8::try_from(civilization).ok().map(CivilizationID::from)
+}
+
+pub(crate) fn set_civilization(
+    format: &mut SCXFormat,
+    player: u8,
+    civilization: CivilizationID,
+) -> Result<(), SetupError> {
+    let index = player_index_or_err(format, player)?;
+    format.tribe_scen.base.player_base_properties[index].civilization =
+        i32::from(u8::from(civilization));
+    Ok(())
+}
+
+pub(crate) fn player_type(format: &SCXFormat, player: u8) -> Option<PlayerType> {
+    let index = player_index(format, player)?;
+    let player_type = format.tribe_scen.base.player_base_properties[index].player_type;
+    Some(PlayerType::from(player_type))
+}
+
+pub(crate) fn set_player_type(
+    format: &mut SCXFormat,
+    player: u8,
+    player_type: PlayerType,
+) -> Result<(), SetupError> {
+    let index = player_index_or_err(format, player)?;
+    format.tribe_scen.base.player_base_properties[index].player_type = i32::from(player_type);
+    Ok(())
+}
+
+pub(crate) fn resources(format: &SCXFormat, player: u8) -> Option<&PlayerStartResources> {
+    let index = player_index(format, player)?;
+    Some(&format.tribe_scen.player_start_resources[index])
+}
+
+/// Set a player's starting resources. The resources are also stored in the world player data,
+/// which newer game versions read instead.
+pub(crate) fn set_resources(
+    format: &mut SCXFormat,
+    version: f32,
+    player: u8,
+    resources: PlayerStartResources,
+) -> Result<(), SetupError> {
+    let index = player_index_or_err(format, player)?;
+    if !resources.is_supported(version) {
+        return Err(SetupError::Unsupported(LostInformation::StartingResources(
+            i32::from(player),
+        )));
+    }
+    let stored = &mut format.tribe_scen.player_start_resources[index];
+    *stored = PlayerStartResources {
+        player_color: stored.player_color,
+        ..resources
+    };
+    if let Some(world_player) = format.world_players.get_mut(index) {
+        world_player.gold = resources.gold as f32;
+        world_player.wood = resources.wood as f32;
+        world_player.food = resources.food as f32;
+        world_player.stone = resources.stone as f32;
+        world_player.ore = resources.ore as f32;
+        world_player.goods = resources.goods as f32;
+    }
+    Ok(())
+}
+
+pub(crate) fn starting_age(format: &SCXFormat, player: u8) -> Option<StartingAge> {
+    let index = player_index(format, player)?;
+    Some(format.tribe_scen.player_start_ages[index])
+}
+
+pub(crate) fn set_starting_age(
+    format: &mut SCXFormat,
+    version: f32,
+    player: u8,
+    age: StartingAge,
+) -> Result<(), SetupError> {
+    let index = player_index_or_err(format, player)?;
+    if !age.is_supported(version) {
+        return Err(SetupError::Unsupported(LostInformation::StartingAge(
+            i32::from(player),
+        )));
+    }
+    format.tribe_scen.player_start_ages[index] = age;
+    Ok(())
+}
+
+pub(crate) fn diplomacy(format: &SCXFormat, player: u8, other: u8) -> Option<DiplomaticStance> {
+    let index = player_index(format, player)?;
+    let other_index = player_index(format, other)?;
+    Some(format.tribe_scen.diplomacy[index][other_index])
+}
+
+/// Set a player's stance toward another player. The stance is also stored in the scenario
+/// player data, which is indexed by player number including GAIA.
+pub(crate) fn set_diplomacy(
+    format: &mut SCXFormat,
+    player: u8,
+    other: u8,
+    stance: DiplomaticStance,
+) -> Result<(), SetupError> {
+    let index = player_index_or_err(format, player)?;
+    let other_index = player_index_or_err(format, other)?;
+    if index == other_index {
+        return Err(SetupError::SelfDiplomacy(player));
+    }
+    format.tribe_scen.diplomacy[index][other_index] = stance;
+    if let Some(scenario_player) = format.scenario_players.get_mut(index) {
+        if let Some(relation) = scenario_player.relations.get_mut(usize::from(other)) {
+            *relation = i32::from(stance) as i8;
+        }
+        if let Some(unit_diplomacy) = scenario_player.unit_diplomacy.get_mut(usize::from(other)) {
+            *unit_diplomacy = match stance {
+                DiplomaticStance::Ally => 2,
+                DiplomaticStance::Neutral => 3,
+                DiplomaticStance::Enemy => 4,
+            };
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn set_team_settings(
+    format: &mut SCXFormat,
+    version: f32,
+    settings: TeamSettings,
+) -> Result<(), SetupError> {
+    if !settings.is_supported(version) {
+        return Err(SetupError::Unsupported(LostInformation::TeamSettings));
+    }
+    format.tribe_scen.set_team_settings(settings);
+    Ok(())
+}
+
+pub(crate) fn allied_victory(format: &SCXFormat, player: u8) -> Option<bool> {
+    let index = player_index(format, player)?;
+    Some(format.tribe_scen.allied_victory[index] != 0)
+}
+
+pub(crate) fn set_allied_victory(
+    format: &mut SCXFormat,
+    player: u8,
+    enabled: bool,
+) -> Result<(), SetupError> {
+    let index = player_index_or_err(format, player)?;
+    format.tribe_scen.allied_victory[index] = i32::from(enabled);
+    if let Some(scenario_player) = format.scenario_players.get_mut(index) {
+        scenario_player.allied_victory = enabled;
+    }
+    Ok(())
+}
+
+pub(crate) fn set_victory_settings(
+    format: &mut SCXFormat,
+    version: f32,
+    settings: VictorySettings,
+) -> Result<(), SetupError> {
+    if !settings.is_supported(version) {
+        return Err(SetupError::Unsupported(LostInformation::VictorySettings));
+    }
+    format.tribe_scen.set_victory_settings(settings);
+    Ok(())
+}
+
+pub(crate) fn player_victory(format: &SCXFormat, player: u8) -> Option<&[VictoryEntry]> {
+    let index = player_index(format, player)?;
+    let scenario_player = format.scenario_players.get(index)?;
+    Some(&scenario_player.victory.entries)
+}
+
+pub(crate) fn set_player_victory(
+    format: &mut SCXFormat,
+    player: u8,
+    entries: Vec<VictoryEntry>,
+) -> Result<(), SetupError> {
+    let index = player_index_or_err(format, player)?;
+    let scenario_player = format
+        .scenario_players
+        .get_mut(index)
+        .ok_or(SetupError::InvalidPlayer(player))?;
+    scenario_player.victory.entries = entries;
+    Ok(())
+}
+
+/// A list of disabled techs, units, or buildings.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum DisabledList {
+    Techs,
+    Units,
+    Buildings,
+}
+
+pub(crate) fn disabled(format: &SCXFormat, player: u8, list: DisabledList) -> Option<&[i32]> {
+    let index = player_index(format, player)?;
+    let tribe_scen = &format.tribe_scen;
+    Some(match list {
+        DisabledList::Techs => tribe_scen.player_disabled_techs(index),
+        DisabledList::Units => tribe_scen.player_disabled_units(index),
+        DisabledList::Buildings => tribe_scen.player_disabled_buildings(index),
+    })
+}
+
+/// Disable a tech, unit, or building for a player. Fails if the list is already as long as the
+/// given version supports.
+pub(crate) fn disable(
+    format: &mut SCXFormat,
+    version: &VersionBundle,
+    player: u8,
+    list: DisabledList,
+    id: i32,
+) -> Result<(), SetupError> {
+    let index = player_index_or_err(format, player)?;
+    let tribe_scen = &mut format.tribe_scen;
+    let (ids, max, lost): (_, _, fn(i32, i32) -> LostInformation) = match list {
+        DisabledList::Techs => (
+            tribe_scen.player_disabled_techs_mut(index),
+            version.max_disabled_techs(),
+            LostInformation::DisabledTechs,
+        ),
+        DisabledList::Units => (
+            tribe_scen.player_disabled_units_mut(index),
+            version.max_disabled_units(),
+            LostInformation::DisabledUnits,
+        ),
+        DisabledList::Buildings => (
+            tribe_scen.player_disabled_buildings_mut(index),
+            version.max_disabled_buildings(),
+            LostInformation::DisabledBuildings,
+        ),
+    };
+    if ids.contains(&id) {
+        return Ok(());
+    }
+    if ids.len() >= max {
+        return Err(SetupError::Unsupported(lost(i32::from(player), 1)));
+    }
+    ids.push(id);
+    Ok(())
+}
+
+/// Enable a tech, unit, or building for a player. Returns whether it was disabled before.
+pub(crate) fn enable(
+    format: &mut SCXFormat,
+    player: u8,
+    list: DisabledList,
+    id: i32,
+) -> Result<bool, SetupError> {
+    let index = player_index_or_err(format, player)?;
+    let tribe_scen = &mut format.tribe_scen;
+    let ids = match list {
+        DisabledList::Techs => tribe_scen.player_disabled_techs_mut(index),
+        DisabledList::Units => tribe_scen.player_disabled_units_mut(index),
+        DisabledList::Buildings => tribe_scen.player_disabled_buildings_mut(index),
+    };
+    let len = ids.len();
+    ids.retain(|&other| other != id);
+    Ok(ids.len() != len)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DiplomaticStance, LostInformation, PlayerType, Scenario, SetupError, StartingAge,
+        TeamSettings, VictoryCondition, VictoryEntry, VictoryMode, VictorySettings,
+    };
+    use std::fs::File;
+
+    #[test]
+    fn edit_player_setup() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+
+        scen.set_player_civilization(2, 5.into())?;
+        scen.set_player_type(2, PlayerType::Human)?;
+        let mut resources = scen.player_resources(2).unwrap().clone();
+        resources.gold = 1234;
+        scen.set_player_resources(2, resources.clone())?;
+        scen.set_player_starting_age(2, StartingAge::CastleAge)?;
+        scen.set_diplomacy(2, 3, DiplomaticStance::Enemy)?;
+        scen.set_allied_victory(2, true)?;
+
+        assert!(matches!(
+            scen.set_player_starting_age(2, StartingAge::Nomad),
+            Err(SetupError::Unsupported(LostInformation::StartingAge(2)))
+        ));
+        assert!(matches!(
+            scen.set_diplomacy(2, 2, DiplomaticStance::Ally),
+            Err(SetupError::SelfDiplomacy(2))
+        ));
+        assert!(matches!(
+            scen.set_player_civilization(0, 1.into()),
+            Err(SetupError::InvalidPlayer(0))
+        ));
+        assert!(scen.player_type(17).is_none());
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.player_civilization(2), Some(5.into()));
+        assert_eq!(scen.player_type(2), Some(PlayerType::Human));
+        assert_eq!(scen.player_resources(2), Some(&resources));
+        assert_eq!(scen.player_starting_age(2), Some(StartingAge::CastleAge));
+        assert_eq!(scen.diplomacy(2, 3), Some(DiplomaticStance::Enemy));
+        assert_eq!(scen.scenario_players()[1].relations[3], 3);
+        assert_eq!(scen.allied_victory(2), Some(true));
+        assert!(scen.scenario_players()[1].allied_victory);
+        Ok(())
+    }
+
+    #[test]
+    fn team_settings_depend_on_version() -> anyhow::Result<()> {
+        let locked = TeamSettings {
+            locked: true,
+            max_teams: 2,
+            ..Default::default()
+        };
+
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.team_settings(), TeamSettings::default());
+        assert!(matches!(
+            scen.set_team_settings(locked),
+            Err(SetupError::Unsupported(LostInformation::TeamSettings))
+        ));
+
+        let mut f = File::open("test/scenarios/Hotkey Trainer Buildings.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.set_team_settings(locked)?;
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.team_settings(), locked);
+        Ok(())
+    }
+
+    #[test]
+    fn resources_depend_on_version() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/A New Emporer.scn")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert!(!scen.version().supports_ore_and_goods());
+        let mut resources = scen.player_resources(1).unwrap().clone();
+        resources.gold = 500;
+        scen.set_player_resources(1, resources.clone())?;
+        resources.ore = 500;
+        assert!(matches!(
+            scen.set_player_resources(1, resources),
+            Err(SetupError::Unsupported(LostInformation::StartingResources(
+                1
+            )))
+        ));
+        assert_eq!(scen.player_resources(1).unwrap().gold, 500);
+        assert_eq!(scen.player_resources(1).unwrap().ore, 100);
+        Ok(())
+    }
+
+    #[test]
+    fn edit_victory_settings() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let relics = VictorySettings {
+            mode: VictoryMode::Custom,
+            conquest: false,
+            relics: 5,
+            ..scen.victory_settings()
+        };
+        scen.set_victory_settings(relics.clone())?;
+        scen.set_player_victory(
+            1,
+            vec![
+                VictoryEntry::destroy_all(434, 2),
+                VictoryEntry::destroy_all(434, 3),
+            ],
+        )?;
+        assert!(scen.set_player_victory(9, vec![]).is_err());
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.victory_settings(), relics);
+        let entries = scen.player_victory(1).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].condition(), VictoryCondition::DestroyAll);
+        assert_eq!(entries[1].object_type(), 434);
+        assert_eq!(entries[1].player_id(), 3);
+
+        let mut f = File::open("test/scenarios/Dawn of a New Age.scn")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert!(matches!(
+            scen.set_victory_settings(VictorySettings {
+                mode: VictoryMode::Conquest,
+                ..Default::default()
+            }),
+            Err(SetupError::Unsupported(LostInformation::VictorySettings))
+        ));
+        scen.set_victory_settings(VictorySettings {
+            relics: 3,
+            ..Default::default()
+        })?;
+        Ok(())
+    }
+
+    #[test]
+    fn disable_units() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.version().max_disabled_units(), 30);
+        assert_eq!(scen.version().max_disabled_buildings(), 20);
+
+        let existing = scen.disabled_units(2).unwrap().len();
+        for unit in 0..(30 - existing as u16) {
+            scen.disable_unit(2, (1000 + unit).into())?;
+        }
+        assert!(matches!(
+            scen.disable_unit(2, 999.into()),
+            Err(SetupError::Unsupported(LostInformation::DisabledUnits(
+                2, 1
+            )))
+        ));
+        // Disabling something twice is fine.
+        scen.disable_unit(2, 1000.into())?;
+        scen.disable_tech(2, 100.into())?;
+        scen.disable_building(3, 82.into())?;
+        assert!(scen.enable_unit(2, 1001.into())?);
+        assert!(!scen.enable_unit(2, 1001.into())?);
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        let units = scen.disabled_units(2).unwrap();
+        assert_eq!(units.len(), 29);
+        assert!(units.contains(&1000.into()));
+        assert!(!units.contains(&1001.into()));
+        assert!(scen.disabled_techs(2).unwrap().contains(&100.into()));
+        assert_eq!(scen.disabled_buildings(3).unwrap(), vec![82.into()]);
+        assert!(scen.disabled_techs(0).is_none());
+        Ok(())
+    }
+
+    /// Disabled tech lists in old scenarios end at the first empty entry, which is not itself a
+    /// disabled tech. Full lists have no empty entry.
+    #[test]
+    fn old_disabled_techs() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/A New Emporer.scn")?;
+        let scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.disabled_techs(6), Some(vec![1.into()]));
+        assert_eq!(scen.format.tribe_scen.player_disabled_techs(8).len(), 20);
+        Ok(())
+    }
+}
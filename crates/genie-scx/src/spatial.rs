@@ -0,0 +1,340 @@
+//! Spatial queries over scenario objects.
+
+use crate::format::SCXFormat;
+use crate::{ScenarioObject, UnitTypeID};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Size of a grid cell, in tiles.
+const CELL_SIZE: f32 = 8.0;
+
+/// Selects objects by owner and unit type.
+///
+/// The default filter selects every object.
+///
+/// ## Usage
+///
+/// ```rust,no_run
+/// # use genie_scx::{ObjectFilter, Scenario};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let scen = Scenario::read_from(std::fs::File::open("scenario.scx")?)?;
+/// // Find the gold mine closest to player 2's Town Center.
+/// let town_centers = scen.objects_in_area(
+///     (0.0, 0.0),
+///     (f32::MAX, f32::MAX),
+///     &ObjectFilter::new().with_player(2).with_object_type(109.into()),
+/// );
+/// if let Some(town_center) = town_centers.first() {
+///     let gold = scen.nearest_object(
+///         (town_center.position.0, town_center.position.1),
+///         &ObjectFilter::new().with_player(0).with_object_type(66.into()),
+///     );
+///     println!("{:?}", gold.map(|mine| mine.position));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ObjectFilter {
+    player: Option<u8>,
+    object_types: Vec<UnitTypeID>,
+}
+
+impl ObjectFilter {
+    /// Create a filter that selects every object.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only select objects owned by this player. Player 0 is GAIA.
+    pub fn with_player(mut self, player: u8) -> Self {
+        self.player = Some(player);
+        self
+    }
+
+    /// Only select objects of this unit type. This can be called multiple times to select
+    /// objects of any of the given unit types.
+    pub fn with_object_type(mut self, object_type: UnitTypeID) -> Self {
+        self.object_types.push(object_type);
+        self
+    }
+
+    /// Does this filter select the object?
+    pub fn matches(&self, player: u8, object: &ScenarioObject) -> bool {
+        self.player.map_or(true, |wanted| wanted == player)
+            && (self.object_types.is_empty() || self.object_types.contains(&object.object_type))
+    }
+}
+
+/// An object in the index, identified by its owner and its index in the owner's object list.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    player: u8,
+    index: usize,
+    position: (f32, f32),
+}
+
+/// A grid of object locations.
+#[derive(Debug)]
+pub(crate) struct SpatialIndex {
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<Entry>>,
+}
+
+impl SpatialIndex {
+    fn new(format: &SCXFormat) -> Self {
+        let columns = (format.map.width() as f32 / CELL_SIZE).ceil().max(1.0) as usize;
+        let rows = (format.map.height() as f32 / CELL_SIZE).ceil().max(1.0) as usize;
+        let mut index = Self {
+            columns,
+            rows,
+            cells: vec![vec![]; columns * rows],
+        };
+        for (player, objects) in format.player_objects.iter().enumerate() {
+            for (i, object) in objects.iter().enumerate() {
+                let position = (object.position.0, object.position.1);
+                let cell = index.cell(position);
+                index.cells[cell.1 * columns + cell.0].push(Entry {
+                    player: player as u8,
+                    index: i,
+                    position,
+                });
+            }
+        }
+        index
+    }
+
+    /// Get the grid cell containing a point. Points outside the map are put in the closest
+    /// cell.
+    fn cell(&self, (x, y): (f32, f32)) -> (usize, usize) {
+        let column = (x / CELL_SIZE)
+            .floor()
+            .clamp(0.0, (self.columns - 1) as f32);
+        let row = (y / CELL_SIZE).floor().clamp(0.0, (self.rows - 1) as f32);
+        (column as usize, row as usize)
+    }
+
+    /// Iterate over the entries in the cells that overlap with an area.
+    fn entries_in(&self, from: (f32, f32), to: (f32, f32)) -> impl Iterator<Item = &Entry> {
+        let (left, top) = self.cell(from);
+        let (right, bottom) = self.cell(to);
+        (top..=bottom)
+            .flat_map(move |row| (left..=right).map(move |column| row * self.columns + column))
+            .flat_map(move |cell| self.cells[cell].iter())
+    }
+
+    fn resolve<'a>(format: &'a SCXFormat, entry: &Entry) -> &'a ScenarioObject {
+        &format.player_objects[usize::from(entry.player)][entry.index]
+    }
+
+    /// Find objects with `from.0 <= x < to.0` and `from.1 <= y < to.1`.
+    pub(crate) fn in_area<'a>(
+        &self,
+        format: &'a SCXFormat,
+        from: (f32, f32),
+        to: (f32, f32),
+        filter: &ObjectFilter,
+    ) -> Vec<&'a ScenarioObject> {
+        if from.0 >= to.0 || from.1 >= to.1 {
+            return vec![];
+        }
+        self.entries_in(from, to)
+            .filter(|entry| {
+                let (x, y) = entry.position;
+                x >= from.0 && x < to.0 && y >= from.1 && y < to.1
+            })
+            .filter(|entry| filter.matches(entry.player, Self::resolve(format, entry)))
+            .map(|entry| Self::resolve(format, entry))
+            .collect()
+    }
+
+    /// Find objects within a radius, sorted by distance.
+    pub(crate) fn in_radius<'a>(
+        &self,
+        format: &'a SCXFormat,
+        center: (f32, f32),
+        radius: f32,
+        filter: &ObjectFilter,
+    ) -> Vec<&'a ScenarioObject> {
+        let from = (center.0 - radius, center.1 - radius);
+        let to = (center.0 + radius, center.1 + radius);
+        let mut found: Vec<_> = self
+            .entries_in(from, to)
+            .map(|entry| (distance(center, entry.position), entry))
+            .filter(|(distance, _)| *distance <= radius)
+            .filter(|(_, entry)| filter.matches(entry.player, Self::resolve(format, entry)))
+            .collect();
+        found.sort_by(|a, b| a.0.total_cmp(&b.0));
+        found
+            .into_iter()
+            .map(|(_, entry)| Self::resolve(format, entry))
+            .collect()
+    }
+
+    /// Find the object closest to a point.
+    pub(crate) fn nearest<'a>(
+        &self,
+        format: &'a SCXFormat,
+        point: (f32, f32),
+        filter: &ObjectFilter,
+    ) -> Option<&'a ScenarioObject> {
+        let (column, row) = self.cell(point);
+        let mut best: Option<(f32, &Entry)> = None;
+        // Search rings of cells around the point's cell. Objects in ring `r + 1` and beyond
+        // are at least `r` cells away, so we can stop once we have found something closer.
+        for ring in 0..self.columns.max(self.rows) {
+            if let Some((best_distance, _)) = best {
+                if best_distance <= (ring as f32 - 1.0) * CELL_SIZE {
+                    break;
+                }
+            }
+            for cell in self.ring(column, row, ring) {
+                for entry in &self.cells[cell] {
+                    let entry_distance = distance(point, entry.position);
+                    if best.map_or(true, |(best_distance, _)| entry_distance < best_distance)
+                        && filter.matches(entry.player, Self::resolve(format, entry))
+                    {
+                        best = Some((entry_distance, entry));
+                    }
+                }
+            }
+        }
+        best.map(|(_, entry)| Self::resolve(format, entry))
+    }
+
+    /// Get the indices of the cells that are exactly `ring` cells away from a cell.
+    fn ring(&self, column: usize, row: usize, ring: usize) -> Vec<usize> {
+        let (column, row, ring) = (column as i64, row as i64, ring as i64);
+        let mut cells = vec![];
+        for y in row - ring..=row + ring {
+            for x in column - ring..=column + ring {
+                let on_edge = (x - column).abs() == ring || (y - row).abs() == ring;
+                if on_edge && x >= 0 && y >= 0 && x < self.columns as i64 && y < self.rows as i64 {
+                    cells.push(y as usize * self.columns + x as usize);
+                }
+            }
+        }
+        cells
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Lazily built spatial index for a scenario.
+///
+/// The index is built on the first query, and must be cleared whenever objects are added,
+/// removed, or moved.
+#[derive(Debug, Default)]
+pub(crate) struct SpatialCache(Mutex<Option<Arc<SpatialIndex>>>);
+
+impl Clone for SpatialCache {
+    fn clone(&self) -> Self {
+        let cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        Self(Mutex::new(cached.clone()))
+    }
+}
+
+impl SpatialCache {
+    /// Get the index, building it if necessary.
+    pub(crate) fn get(&self, format: &SCXFormat) -> Arc<SpatialIndex> {
+        let mut cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(cached.get_or_insert_with(|| Arc::new(SpatialIndex::new(format))))
+    }
+
+    /// Throw away the index after objects were changed.
+    pub(crate) fn clear(&mut self) {
+        *self.0.get_mut().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::distance;
+    use crate::{ObjectFilter, Scenario, ScenarioObject};
+    use std::fs::File;
+
+    fn position(object: &ScenarioObject) -> (f32, f32) {
+        (object.position.0, object.position.1)
+    }
+
+    #[test]
+    fn queries_match_linear_scan() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+        let everything = ObjectFilter::new();
+
+        let found = scen.objects_in_area((20.0, 30.0), (60.0, 45.0), &everything);
+        let expected = scen
+            .objects()
+            .filter(|object| {
+                let (x, y) = position(object);
+                (20.0..60.0).contains(&x) && (30.0..45.0).contains(&y)
+            })
+            .count();
+        assert_eq!(found.len(), expected);
+
+        let center = (100.0, 100.0);
+        let found = scen.objects_in_radius(center, 25.0, &everything);
+        let expected = scen
+            .objects()
+            .filter(|object| distance(center, position(object)) <= 25.0)
+            .count();
+        assert_eq!(found.len(), expected);
+        assert!(
+            found
+                .windows(2)
+                .all(|pair| distance(center, position(pair[0]))
+                    <= distance(center, position(pair[1])))
+        );
+
+        for point in [(0.0, 0.0), (100.0, 100.0), (199.0, 3.0), (-20.0, 500.0)] {
+            let nearest = scen.nearest_object(point, &everything).unwrap();
+            let expected = scen
+                .objects()
+                .map(|object| distance(point, position(object)))
+                .fold(f32::INFINITY, f32::min);
+            assert_eq!(distance(point, position(nearest)), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn index_follows_edits() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let filter = ObjectFilter::new()
+            .with_player(3)
+            .with_object_type(4.into());
+        assert!(scen
+            .objects_in_area((50.0, 50.0), (51.0, 51.0), &filter)
+            .is_empty());
+
+        let id = scen.add_object(
+            3,
+            ScenarioObject {
+                object_type: 4.into(),
+                position: (50.5, 50.5, 0.0),
+                ..Default::default()
+            },
+        )?;
+        let found = scen.objects_in_area((50.0, 50.0), (51.0, 51.0), &filter);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, id);
+        assert_eq!(scen.nearest_object((50.0, 50.0), &filter).unwrap().id, id);
+
+        scen.object_mut(id).unwrap().position = (150.5, 150.5, 0.0);
+        assert!(scen
+            .objects_in_area((50.0, 50.0), (51.0, 51.0), &filter)
+            .is_empty());
+        assert_eq!(
+            scen.objects_in_radius((150.0, 150.0), 1.0, &filter).len(),
+            1
+        );
+
+        scen.move_object(id, 4)?;
+        assert!(scen.nearest_object((150.0, 150.0), &filter).is_none());
+        Ok(())
+    }
+}
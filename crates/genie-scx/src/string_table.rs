@@ -0,0 +1,271 @@
+//! Exporting the texts shown to players into a string table for translation, and importing them
+//! again afterwards.
+//!
+//! This includes player names and trigger messages, as well as the texts in [`ScenarioText`].
+
+use crate::format::SCXFormat;
+use crate::text::ScenarioText;
+use crate::types::{supports_player_name_keys, supports_scout_text, supports_text_keys};
+use genie_lang::LangFile;
+use genie_support::StringKey;
+
+impl ScenarioText {
+    /// The name of this text in exported string keys.
+    fn key_name(self) -> &'static str {
+        match self {
+            ScenarioText::Description => "description",
+            ScenarioText::Hints => "hints",
+            ScenarioText::WinMessage => "win_message",
+            ScenarioText::LossMessage => "loss_message",
+            ScenarioText::History => "history",
+            ScenarioText::Scout => "scout",
+        }
+    }
+
+    /// Is a string table key stored for this text in the given data version?
+    fn has_string_key(self, version: f32) -> bool {
+        match self {
+            ScenarioText::Scout => supports_scout_text(version),
+            _ => supports_text_keys(version),
+        }
+    }
+}
+
+/// How imported strings are written back into a scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringImport {
+    /// Store the strings in the scenario, replacing the text that is there.
+    Inline,
+    /// Store the strings in a string table, and refer to them from the scenario.
+    ///
+    /// Strings get consecutive numeric keys. Strings that cannot refer to a string table in the
+    /// scenario's version are stored in the scenario instead.
+    StringTable {
+        /// The key for the first string.
+        first_id: u32,
+    },
+}
+
+/// Writes imported strings into a scenario, and collects the new string table entries.
+struct Importer<'a> {
+    lang: &'a LangFile,
+    next_id: Option<u32>,
+    table: LangFile,
+}
+
+impl Importer<'_> {
+    /// Import the string for `key` into `value`.
+    ///
+    /// Returns None if there is no string for `key`. Otherwise, returns the string table ID that
+    /// should be referred to, or None if the string was stored inline.
+    fn import(
+        &mut self,
+        key: String,
+        value: &mut Option<String>,
+        can_refer: bool,
+    ) -> Option<Option<u32>> {
+        let string = self.lang.get(&StringKey::from(key))?;
+        match self.next_id {
+            Some(id) if can_refer => {
+                self.next_id = id.checked_add(1);
+                self.table.insert(StringKey::from(id), string.clone());
+                *value = None;
+                Some(Some(id))
+            }
+            _ => {
+                *value = Some(string.clone());
+                Some(None)
+            }
+        }
+    }
+}
+
+/// Collect the texts shown to players into a string table.
+pub(crate) fn export_strings(format: &SCXFormat) -> LangFile {
+    let mut lang = LangFile::new();
+    let mut add = |key: String, value: Option<&str>| {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            lang.insert(StringKey::from(key), value.to_string());
+        }
+    };
+
+    for text in ScenarioText::ALL {
+        add(
+            format!("scenario.{}", text.key_name()),
+            format.tribe_scen.text(text).0,
+        );
+    }
+
+    let num_players = format.player_objects.len().saturating_sub(1);
+    let player_names = &format.tribe_scen.base.player_names;
+    for (index, name) in player_names.iter().take(num_players).enumerate() {
+        add(format!("player.{}.name", index + 1), name.as_deref());
+    }
+
+    if let Some(triggers) = &format.triggers {
+        for (i, trigger) in triggers.triggers_unordered().enumerate() {
+            add(format!("trigger.{}.description", i), trigger.description());
+            add(
+                format!("trigger.{}.short_description", i),
+                trigger.short_description(),
+            );
+            for (j, effect) in trigger.effects_unordered().enumerate() {
+                add(
+                    format!("trigger.{}.effect.{}.message", i, j),
+                    effect.message(),
+                );
+            }
+        }
+    }
+
+    lang
+}
+
+/// Write translated strings back into the scenario, returning the new string table entries.
+pub(crate) fn import_strings(
+    format: &mut SCXFormat,
+    lang: &LangFile,
+    import: StringImport,
+) -> LangFile {
+    let mut importer = Importer {
+        lang,
+        next_id: match import {
+            StringImport::Inline => None,
+            StringImport::StringTable { first_id } => Some(first_id),
+        },
+        table: LangFile::new(),
+    };
+
+    let version = format.tribe_scen.version();
+    for text in ScenarioText::ALL {
+        let (value, string_key) = format.tribe_scen.text_mut(text);
+        let key = format!("scenario.{}", text.key_name());
+        if let Some(id) = importer.import(key, value, text.has_string_key(version)) {
+            *string_key = id.map(StringKey::from);
+        }
+    }
+
+    let num_players = format.player_objects.len().saturating_sub(1);
+    let base = &mut format.tribe_scen.base;
+    let players = base
+        .player_names
+        .iter_mut()
+        .zip(base.player_string_table.iter_mut())
+        .take(num_players);
+    for (index, (name, string_key)) in players.enumerate() {
+        let key = format!("player.{}.name", index + 1);
+        if let Some(id) = importer.import(key, name, supports_player_name_keys(version)) {
+            *string_key = id.map(StringKey::from);
+        }
+    }
+
+    if let Some(triggers) = &mut format.triggers {
+        let has_short_description = triggers.version() >= 1.8;
+        for (i, trigger) in triggers.triggers_unordered_mut().enumerate() {
+            let mut description = trigger.description().map(str::to_string);
+            let key = format!("trigger.{}.description", i);
+            if importer.import(key, &mut description, false).is_some() {
+                trigger.set_description(description);
+            }
+
+            let mut short_description = trigger.short_description().map(str::to_string);
+            let key = format!("trigger.{}.short_description", i);
+            if let Some(id) = importer.import(key, &mut short_description, has_short_description) {
+                trigger.set_short_description(short_description);
+                trigger.set_short_description_id(id.map(StringKey::from));
+            }
+
+            for (j, effect) in trigger.effects_unordered_mut().enumerate() {
+                let mut message = effect.message().map(str::to_string);
+                let key = format!("trigger.{}.effect.{}.message", i, j);
+                let can_refer = importer
+                    .next_id
+                    .map_or(false, |id| i32::try_from(id).is_ok());
+                if let Some(id) = importer.import(key, &mut message, can_refer) {
+                    effect.set_message(message);
+                    effect.set_text_id(id.and_then(|id| i32::try_from(id).ok()).unwrap_or(-1));
+                }
+            }
+        }
+    }
+
+    importer.table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StringImport;
+    use crate::{Scenario, ScenarioText, StringKey};
+    use genie_lang::LangFile;
+    use std::fs::File;
+
+    #[test]
+    fn resolve_string_keys() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.set_text(ScenarioText::History, Some("Inline".to_string()));
+        scen.set_text_string_key(ScenarioText::History, Some(StringKey::from(1u32)));
+
+        let mut lang = LangFile::new();
+        assert_eq!(
+            scen.resolve_text(ScenarioText::History, &lang),
+            Some("Inline")
+        );
+        lang.insert(StringKey::from(1u32), "From the string table".to_string());
+        assert_eq!(
+            scen.resolve_text(ScenarioText::History, &lang),
+            Some("From the string table")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn translate_inline() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let mut lang = scen.export_strings();
+        assert_eq!(
+            lang.get(&StringKey::from("player.1.name"))
+                .map(String::as_str),
+            Some("Radiant")
+        );
+        assert!(lang
+            .iter()
+            .any(|(key, _)| key.to_string().ends_with(".message")));
+
+        for string in lang.values_mut() {
+            *string = string.to_uppercase();
+        }
+        assert!(scen.import_strings(&lang, StringImport::Inline).is_empty());
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.export_strings(), lang);
+        Ok(())
+    }
+
+    #[test]
+    fn translate_to_string_table() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let lang = scen.export_strings();
+        let table = scen.import_strings(&lang, StringImport::StringTable { first_id: 100_000 });
+        assert_eq!(table.len(), lang.len());
+
+        let mut exported = scen.export_strings();
+        exported.retain(|key, _| !key.to_string().ends_with(".description"));
+        assert!(exported.is_empty());
+        let key = scen.text_string_key(ScenarioText::WinMessage).unwrap();
+        assert!(table.contains_key(key));
+        let effect = scen
+            .triggers()
+            .unwrap()
+            .triggers_unordered()
+            .flat_map(|trigger| trigger.effects_unordered())
+            .find(|effect| effect.text_id() >= 100_000)
+            .unwrap();
+        assert_eq!(effect.message(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! Reading scenario metadata without parsing the whole file.
+
+use crate::encoding::{self, TextEncoding};
+use crate::format::TribeScen;
+use crate::header::SCXHeader;
+use crate::map::Map;
+use crate::types::{SCXVersion, VersionBundle};
+use crate::{Error, Result};
+use byteorder::{ReadBytesExt, LE};
+use encoding_rs::Encoding;
+use flate2::read::DeflateDecoder;
+use genie_support::CivilizationID;
+use std::convert::TryFrom;
+use std::io::Read;
+
+/// Metadata about a scenario, for listing many scenarios quickly.
+///
+/// Reading a summary stops after the map size, so terrain, objects, and triggers are never
+/// parsed.
+#[derive(Debug, Clone)]
+pub struct ScenarioSummary {
+    format_version: SCXVersion,
+    header: SCXHeader,
+    filename: String,
+    map_size: (u32, u32),
+    /// The civilization of each player, starting at player 1.
+    civilizations: Vec<i32>,
+    encoding: &'static Encoding,
+}
+
+impl ScenarioSummary {
+    /// Read a scenario summary from an input stream.
+    ///
+    /// The text encoding of the strings in the scenario is detected automatically.
+    pub fn read_from(input: impl Read) -> Result<Self> {
+        Self::read_from_with_encoding(input, TextEncoding::Detect)
+    }
+
+    /// Read a scenario summary from an input stream, decoding its strings with the given text
+    /// encoding.
+    pub fn read_from_with_encoding(mut input: impl Read, encoding: TextEncoding) -> Result<Self> {
+        let mut format_version = [0; 4];
+        input.read_exact(&mut format_version)?;
+        let format_version = SCXVersion(format_version);
+        if format_version.to_player_version().is_none() {
+            return Err(Error::UnsupportedFormatVersionError(format_version));
+        }
+
+        let mut header = SCXHeader::read_from(&mut input, format_version)?;
+
+        let mut input = DeflateDecoder::new(&mut input);
+        let _next_object_id = input.read_i32::<LE>()?;
+        let mut tribe_scen = TribeScen::read_from(&mut input)?;
+        let map = Map::read_header(&mut input)?;
+        map.skip_tiles(&mut input)?;
+        // This includes GAIA.
+        let num_players = input.read_u32::<LE>()?;
+
+        // The summary does not read the triggers or the map, but their versions do not affect
+        // the text encoding.
+        let version = VersionBundle {
+            format: format_version,
+            header: header.version,
+            data: tribe_scen.version(),
+            ..VersionBundle::aoc()
+        };
+        let mut strings: Vec<&mut String> = header.strings_mut().collect();
+        strings.extend(tribe_scen.strings_mut());
+        let num_strings = strings.len();
+        let encoding = encoding::decode_strings(strings, num_strings, encoding, &version)?;
+
+        let civilizations = tribe_scen
+            .base
+            .player_base_properties
+            .iter()
+            .take(num_players.saturating_sub(1) as usize)
+            .map(|properties| properties.civilization)
+            .collect();
+
+        Ok(Self {
+            format_version,
+            header,
+            filename: std::mem::take(&mut tribe_scen.base.name),
+            map_size: (map.width(), map.height()),
+            civilizations,
+            encoding,
+        })
+    }
+
+    /// Get the format version of this SCX file.
+    pub fn format_version(&self) -> SCXVersion {
+        self.format_version
+    }
+
+    /// Get the header, which contains the description, player count, and DLC options.
+    pub fn header(&self) -> &SCXHeader {
+        &self.header
+    }
+
+    /// Get the scenario filename.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Get the width and height of the map, in tiles.
+    pub fn map_size(&self) -> (u32, u32) {
+        self.map_size
+    }
+
+    /// Get the number of player slots in this scenario, not including GAIA.
+    pub fn num_players(&self) -> usize {
+        self.civilizations.len()
+    }
+
+    /// Get a player's civilization. Player numbers start at 1.
+    pub fn player_civilization(&self, player: u8) -> Option<CivilizationID> {
+        let index = usize::from(player).checked_sub(1)?;
+        let civilization = *self.civilizations.get(index)?;
+        u8::try_from(civilization).ok().map(CivilizationID::from)
+    }
+
+    /// Get the text encoding that strings are stored in.
+    ///
+    /// This is detected from the same strings as when reading the full scenario, so it is usually
+    /// the same as [`Scenario::encoding`]. It can only differ if the scenario's other strings,
+    /// like trigger texts, can not be decoded with it; the full read then falls back to
+    /// WINDOWS-1252.
+    ///
+    /// [`Scenario::encoding`]: crate::Scenario::encoding
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScenarioSummary;
+    use crate::Scenario;
+    use encoding_rs::WINDOWS_1251;
+    use std::fs::File;
+
+    #[test]
+    fn matches_full_read() -> anyhow::Result<()> {
+        for name in [
+            "Dawn of a New Age.scn",
+            "El advenimiento de los hunos_.scx",
+            "Age of Heroes b1-3-5.scx",
+            "Year_of_the_Pig.aoe2scenario",
+        ] {
+            let path = format!("test/scenarios/{}", name);
+            let summary = ScenarioSummary::read_from(File::open(&path)?)?;
+            let scen = Scenario::read_from(File::open(&path)?)?;
+            assert_eq!(summary.format_version(), scen.format_version());
+            assert_eq!(summary.header().description, scen.header().description);
+            assert_eq!(summary.filename(), scen.filename());
+            assert_eq!(
+                summary.map_size(),
+                (scen.map().width(), scen.map().height())
+            );
+            assert_eq!(summary.encoding(), scen.encoding());
+            assert_eq!(summary.num_players(), 8);
+            for player in 1..=8 {
+                assert_eq!(
+                    summary.player_civilization(player),
+                    scen.player_civilization(player)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn encoding_ignores_trigger_strings() -> anyhow::Result<()> {
+        let source = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let mut trigger = source
+            .triggers()
+            .unwrap()
+            .triggers_unordered()
+            .next()
+            .unwrap()
+            .clone();
+        trigger.set_description(Some(
+            "Защищайте замок, пока не прибудет подкрепление с севера.".to_string(),
+        ));
+
+        let mut scen = Scenario::read_from(File::open("test/scenarios/real_world_amazon.scx")?)?;
+        scen.set_encoding(WINDOWS_1251);
+        let triggers = scen.format.triggers.get_or_insert_with(Default::default);
+        triggers.push(trigger);
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+
+        let summary = ScenarioSummary::read_from(&out[..])?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(summary.encoding(), scen.encoding());
+        Ok(())
+    }
+}
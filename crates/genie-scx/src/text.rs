@@ -0,0 +1,73 @@
+//! Texts shown to players, and the cinematics played during a scenario.
+//!
+//! Texts can be stored inline, or as a key into the game's string table. Games show the string
+//! table entry if the key is set and exists, and the inline text otherwise.
+
+/// A text shown to players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScenarioText {
+    /// The scenario instructions.
+    Description,
+    /// Hints, shown in the Objectives window.
+    Hints,
+    /// The message shown when the player wins.
+    WinMessage,
+    /// The message shown when the player loses.
+    LossMessage,
+    /// The history, shown in the Objectives window.
+    History,
+    /// The scouting report, shown in the Objectives window. Only stored in AoC and later.
+    Scout,
+}
+
+impl ScenarioText {
+    /// All the texts, in the order they are stored.
+    pub const ALL: [ScenarioText; 6] = [
+        ScenarioText::Description,
+        ScenarioText::Hints,
+        ScenarioText::WinMessage,
+        ScenarioText::LossMessage,
+        ScenarioText::History,
+        ScenarioText::Scout,
+    ];
+}
+
+/// A cinematic that is played during the scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cinematic {
+    /// Played before the scenario starts.
+    Pregame,
+    /// Played when the player wins.
+    Victory,
+    /// Played when the player loses.
+    Loss,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScenarioText;
+    use crate::{Cinematic, Scenario, StringKey};
+    use std::fs::File;
+
+    #[test]
+    fn edit_texts() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.set_text(ScenarioText::Hints, Some("Build a Wonder".to_string()));
+        scen.set_text(ScenarioText::Scout, None);
+        scen.set_text_string_key(ScenarioText::WinMessage, Some(StringKey::from(12345u32)));
+        scen.set_cinematic(Cinematic::Victory, Some("win.avi".to_string()));
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(&out[..])?;
+        assert_eq!(scen.text(ScenarioText::Hints), Some("Build a Wonder"));
+        assert_eq!(scen.text(ScenarioText::Scout), None);
+        assert_eq!(
+            scen.text_string_key(ScenarioText::WinMessage),
+            Some(&StringKey::from(12345u32))
+        );
+        assert_eq!(scen.cinematic(Cinematic::Victory), Some("win.avi"));
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+//! Whole-map transformations: resizing, cropping, rotating, and mirroring.
+
+use crate::format::SCXFormat;
+use crate::objects;
+use crate::ScenarioObject;
+use std::f32::consts::PI;
+
+/// A rotation of the map by a multiple of 90°.
+///
+/// The formulas show where the tile at `(x, y)` ends up on a map that is `w` tiles wide and `h`
+/// tiles high.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// Rotate by 90°: `(x, y)` → `(h - 1 - y, x)`. The width and height are swapped.
+    Quarter,
+    /// Rotate by 180°: `(x, y)` → `(w - 1 - x, h - 1 - y)`.
+    Half,
+    /// Rotate by 270°: `(x, y)` → `(y, w - 1 - x)`. The width and height are swapped.
+    ThreeQuarters,
+}
+
+/// An axis to mirror the map across.
+///
+/// The formulas show where the tile at `(x, y)` ends up on a map that is `w` tiles wide and `h`
+/// tiles high. On square maps, mirroring across one of the diagonals swaps the positions of
+/// players in opposite corners, which is the usual way to make a fair 1v1 map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+    /// Flip the x coordinates: `(x, y)` → `(w - 1 - x, y)`.
+    X,
+    /// Flip the y coordinates: `(x, y)` → `(x, h - 1 - y)`.
+    Y,
+    /// Mirror across the diagonal through `(0, 0)`: `(x, y)` → `(y, x)`. The width and height are
+    /// swapped.
+    Diagonal,
+    /// Mirror across the other diagonal: `(x, y)` → `(h - 1 - y, w - 1 - x)`. The width and
+    /// height are swapped.
+    AntiDiagonal,
+}
+
+/// A transformation of map coordinates.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Transform {
+    /// Keep the area of the given size that starts at the given tile. Parts of the area that are
+    /// outside the old map are filled with default tiles.
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    Rotate(Rotation),
+    Mirror(Mirror),
+}
+
+impl Transform {
+    /// Get the size of a map after the transformation.
+    pub(crate) fn size(self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Transform::Crop { width, height, .. } => (width, height),
+            Transform::Rotate(Rotation::Half) | Transform::Mirror(Mirror::X | Mirror::Y) => {
+                (width, height)
+            }
+            Transform::Rotate(_) | Transform::Mirror(_) => (height, width),
+        }
+    }
+
+    /// Transform a point in continuous map coordinates, where the tile `(x, y)` covers the area
+    /// from `(x, y)` to `(x + 1, y + 1)`.
+    pub(crate) fn point(self, (x, y): (f32, f32), width: u32, height: u32) -> (f32, f32) {
+        let (w, h) = (width as f32, height as f32);
+        match self {
+            Transform::Crop {
+                x: left, y: top, ..
+            } => (x - left as f32, y - top as f32),
+            Transform::Rotate(Rotation::Quarter) => (h - y, x),
+            Transform::Rotate(Rotation::Half) => (w - x, h - y),
+            Transform::Rotate(Rotation::ThreeQuarters) => (y, w - x),
+            Transform::Mirror(Mirror::X) => (w - x, y),
+            Transform::Mirror(Mirror::Y) => (x, h - y),
+            Transform::Mirror(Mirror::Diagonal) => (y, x),
+            Transform::Mirror(Mirror::AntiDiagonal) => (h - y, w - x),
+        }
+    }
+
+    /// Transform tile coordinates. Returns None if the tile is not on the new map.
+    pub(crate) fn tile(self, (x, y): (u32, u32), width: u32, height: u32) -> Option<(u32, u32)> {
+        let (new_x, new_y) = self.point((x as f32 + 0.5, y as f32 + 0.5), width, height);
+        let (new_width, new_height) = self.size(width, height);
+        if new_x < 0.0 || new_y < 0.0 {
+            return None;
+        }
+        let (new_x, new_y) = (new_x as u32, new_y as u32);
+        if new_x < new_width && new_y < new_height {
+            Some((new_x, new_y))
+        } else {
+            None
+        }
+    }
+
+    /// Transform tile coordinates as stored in triggers and player data, where negative values
+    /// mean that no tile is set. Tiles that are not on the new map are moved to its closest edge.
+    fn tile_i32(self, (x, y): (i32, i32), width: u32, height: u32) -> (i32, i32) {
+        if x < 0 || y < 0 {
+            return (x, y);
+        }
+        let (new_x, new_y) = self.point((x as f32 + 0.5, y as f32 + 0.5), width, height);
+        let (new_width, new_height) = self.size(width, height);
+        let clamp = |value: f32, max: u32| (value.floor() as i32).clamp(0, max as i32 - 1);
+        (clamp(new_x, new_width), clamp(new_y, new_height))
+    }
+
+    /// Transform an area given by two corner tiles, so that the first corner is the one with the
+    /// lowest coordinates.
+    fn area(self, area: (i32, i32, i32, i32), width: u32, height: u32) -> (i32, i32, i32, i32) {
+        if area.0 < 0 || area.1 < 0 || area.2 < 0 || area.3 < 0 {
+            return area;
+        }
+        let (x1, y1) = self.tile_i32((area.0, area.1), width, height);
+        let (x2, y2) = self.tile_i32((area.2, area.3), width, height);
+        (x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2))
+    }
+
+    /// Transform the angle an object is facing.
+    fn angle(self, angle: f32, width: u32, height: u32) -> f32 {
+        if let Transform::Crop { .. } = self {
+            return angle;
+        }
+        let origin = self.point((0.0, 0.0), width, height);
+        let direction = self.point((angle.cos(), angle.sin()), width, height);
+        let new_angle = (direction.1 - origin.1).atan2(direction.0 - origin.0);
+        if new_angle < 0.0 {
+            new_angle + 2.0 * PI
+        } else {
+            new_angle
+        }
+    }
+}
+
+/// Apply a transformation to the map, objects, triggers, and player data of a scenario.
+///
+/// Returns the objects that are no longer on the map.
+pub(crate) fn transform(format: &mut SCXFormat, transform: Transform) -> Vec<ScenarioObject> {
+    let (width, height) = (format.map.width(), format.map.height());
+    let (new_width, new_height) = transform.size(width, height);
+    format.map.transform(transform);
+
+    let mut removed = vec![];
+    for objects in format.player_objects.iter_mut() {
+        for mut object in std::mem::take(objects) {
+            let (x, y) = transform.point((object.position.0, object.position.1), width, height);
+            if x < 0.0 || y < 0.0 || x >= new_width as f32 || y >= new_height as f32 {
+                removed.push(object);
+                continue;
+            }
+            object.position = (x, y, object.position.2);
+            object.angle = transform.angle(object.angle, width, height);
+            objects.push(object);
+        }
+    }
+    let removed_ids: Vec<i32> = removed.iter().map(|object| object.id).collect();
+    objects::clear_references(format, &removed_ids);
+
+    if let Some(triggers) = format.triggers.as_mut() {
+        for trigger in triggers.triggers_unordered_mut() {
+            for condition in trigger.conditions_unordered_mut() {
+                condition.set_area(transform.area(condition.area(), width, height));
+            }
+            for effect in trigger.effects_unordered_mut() {
+                effect.set_location(transform.tile_i32(effect.location(), width, height));
+                effect.set_area(transform.area(effect.area(), width, height));
+            }
+        }
+    }
+
+    let view = format.tribe_scen.view_mut();
+    *view = transform.tile_i32(*view, width, height);
+    for player in format.scenario_players.iter_mut() {
+        if player.view.0 >= 0.0 && player.view.1 >= 0.0 {
+            let (x, y) = transform.point(player.view, width, height);
+            player.view = (
+                x.clamp(0.0, new_width as f32),
+                y.clamp(0.0, new_height as f32),
+            );
+        }
+        let location = (i32::from(player.location.0), i32::from(player.location.1));
+        let (x, y) = transform.tile_i32(location, width, height);
+        player.location = (x as i16, y as i16);
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Mirror, Rotation, Scenario, ScenarioObject};
+    use std::fs::File;
+
+    #[test]
+    fn rotate_full_circle() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let original = Scenario::read_from(&mut f)?;
+        let mut scen = original.clone();
+        scen.rotate(Rotation::Quarter);
+        scen.rotate(Rotation::Half);
+        scen.rotate(Rotation::Quarter);
+
+        for (old, new) in original.map().tiles().zip(scen.map().tiles()) {
+            assert_eq!(old.terrain, new.terrain);
+            assert_eq!(old.elevation, new.elevation);
+        }
+        for (old, new) in original.objects().zip(scen.objects()) {
+            assert!((old.position.0 - new.position.0).abs() < 0.001);
+            assert!((old.position.1 - new.position.1).abs() < 0.001);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn mirror_diagonal() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let original = Scenario::read_from(&mut f)?;
+        let mut scen = original.clone();
+        scen.mirror(Mirror::Diagonal);
+
+        let map = original.map();
+        assert_eq!(scen.map().width(), map.height());
+        assert_eq!(scen.map().height(), map.width());
+        assert_eq!(
+            scen.map().tile(5, 2).unwrap().terrain,
+            map.tile(2, 5).unwrap().terrain
+        );
+        for (old, new) in original.objects().zip(scen.objects()) {
+            assert_eq!(old.position.0, new.position.1);
+            assert_eq!(old.position.1, new.position.0);
+        }
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        Scenario::read_from(&out[..])?;
+        Ok(())
+    }
+
+    #[test]
+    fn crop_and_resize() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let original = Scenario::read_from(&mut f)?;
+        let mut scen = original.clone();
+        let removed = scen.crop(10, 20, 30, 40);
+        assert_eq!(scen.map().width(), 30);
+        assert_eq!(scen.map().height(), 40);
+        assert_eq!(
+            scen.map().tile(0, 0).unwrap().terrain,
+            original.map().tile(10, 20).unwrap().terrain
+        );
+        assert_eq!(
+            scen.objects().count() + removed.len(),
+            original.objects().count()
+        );
+        assert!(scen.objects().all(|object| object.position.0 < 30.0
+            && object.position.1 < 40.0
+            && object.position.0 >= 0.0
+            && object.position.1 >= 0.0));
+
+        let removed = scen.resize(50, 50);
+        assert!(removed.is_empty());
+        assert_eq!(scen.map().tile(49, 49).unwrap().terrain, 0);
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        Scenario::read_from(&out[..])?;
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "map size must not be zero")]
+    fn resize_to_zero() {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx").unwrap();
+        let mut scen = Scenario::read_from(&mut f).unwrap();
+        scen.resize(0, 0);
+    }
+
+    #[test]
+    fn crop_ungarrisons() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let container = scen.add_object(
+            1,
+            ScenarioObject {
+                position: (50.5, 50.5, 0.0),
+                ..Default::default()
+            },
+        )?;
+        let unit = scen.add_object(
+            1,
+            ScenarioObject {
+                garrisoned_in: Some(container),
+                ..Default::default()
+            },
+        )?;
+        // Scenario files do not always store garrisoned objects at their container's position.
+        scen.object_mut(unit).unwrap().position = (5.5, 5.5, 0.0);
+
+        let removed = scen.crop(0, 0, 20, 20);
+        assert!(removed.iter().any(|object| object.id == container));
+        assert_eq!(scen.object(unit).unwrap().garrisoned_in, None);
+        Ok(())
+    }
+}
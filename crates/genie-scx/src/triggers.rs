@@ -6,7 +6,7 @@ use std::convert::TryInto;
 use std::io::{Read, Write};
 
 /// A trigger condition, describing when a trigger can fire.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct TriggerCondition {
     condition_type: i32,
     properties: Vec<i32>,
@@ -53,6 +53,17 @@ impl TriggerCondition {
         Ok(())
     }
 
+    /// Get the type of this trigger condition.
+    pub fn condition_type(&self) -> i32 {
+        self.condition_type
+    }
+
+    /// Drop or add properties, for trigger system versions that store a different number of them.
+    /// New properties are set to -1.
+    pub(crate) fn resize_properties(&mut self, len: usize) {
+        self.properties.resize(len, -1);
+    }
+
     /// Get the "amount" value for this trigger condition.
     pub fn amount(&self) -> i32 {
         self.properties[0]
@@ -185,6 +196,19 @@ impl TriggerCondition {
         self.properties[13] = unit_group;
     }
 
+    /// Get the raw "Object Type" value for this trigger condition.
+    ///
+    /// This is the class of objects that the condition applies to (1 for other objects, 2 for
+    /// buildings, 3 for civilians, and 4 for military units), or -1 for any. It is not a unit type.
+    pub fn raw_object_type(&self) -> i32 {
+        self.properties[14]
+    }
+
+    /// Set the raw "Object Type" value for this trigger condition.
+    pub fn set_raw_object_type(&mut self, object_type: i32) {
+        self.properties[14] = object_type;
+    }
+
     /// Get the "Object Type" value for this trigger condition.
     pub fn object_type(&self) -> UnitTypeID {
         self.properties[14].try_into().unwrap()
@@ -217,7 +241,7 @@ impl TriggerCondition {
 }
 
 /// A trigger effect, describing the response when a trigger fires.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct TriggerEffect {
     effect_type: i32,
     properties: Vec<i32>,
@@ -286,6 +310,33 @@ impl TriggerEffect {
         Ok(())
     }
 
+    /// Get the type of this trigger effect.
+    pub fn effect_type(&self) -> i32 {
+        self.effect_type
+    }
+
+    /// Set the type of this trigger effect.
+    pub fn set_effect_type(&mut self, effect_type: i32) {
+        self.effect_type = effect_type;
+    }
+
+    /// Get the message text for this trigger effect, used by eg. Display Instructions and Send
+    /// Chat.
+    pub fn message(&self) -> Option<&str> {
+        self.chat_text.as_deref()
+    }
+
+    /// Set the message text for this trigger effect.
+    pub fn set_message(&mut self, message: Option<String>) {
+        self.chat_text = message;
+    }
+
+    /// Drop or add properties, for trigger system versions that store a different number of them.
+    /// New properties are set to -1.
+    pub(crate) fn resize_properties(&mut self, len: usize) {
+        self.properties.resize(len, -1);
+    }
+
     /// Get the "AI Goal" value for this trigger effect.
     pub fn ai_goal(&self) -> i32 {
         self.properties[0]
@@ -336,6 +387,18 @@ impl TriggerEffect {
         self.properties[4] = num_objects;
     }
 
+    /// Get the IDs of the objects selected for this trigger effect.
+    pub fn objects(&self) -> &[i32] {
+        &self.objects
+    }
+
+    /// Set the objects selected for this trigger effect. This also updates the "Number of
+    /// Objects" value.
+    pub fn set_objects(&mut self, objects: Vec<i32>) {
+        self.properties[4] = objects.len() as i32;
+        self.objects = objects;
+    }
+
     /// Get the "Object ID" value for this trigger effect.
     pub fn object_id(&self) -> i32 {
         self.properties[5]
@@ -346,6 +409,16 @@ impl TriggerEffect {
         self.properties[5] = object_id;
     }
 
+    /// Get the raw "Unit Type" value for this trigger effect.
+    pub fn raw_unit_type(&self) -> i32 {
+        self.properties[6]
+    }
+
+    /// Set the raw "Unit Type" value for this trigger effect.
+    pub fn set_raw_unit_type(&mut self, unit_type: i32) {
+        self.properties[6] = unit_type;
+    }
+
     /// Get the "Unit Type" value for this trigger effect.
     pub fn unit_type(&self) -> UnitTypeID {
         self.properties[6].try_into().unwrap()
@@ -465,6 +538,19 @@ impl TriggerEffect {
         self.properties[20] = object_group;
     }
 
+    /// Get the raw "Object Type" value for this trigger effect.
+    ///
+    /// This is the class of objects that the effect applies to (1 for other objects, 2 for
+    /// buildings, 3 for civilians, and 4 for military units), or -1 for any. It is not a unit type.
+    pub fn raw_object_type(&self) -> i32 {
+        self.properties[21]
+    }
+
+    /// Set the raw "Object Type" value for this trigger effect.
+    pub fn set_raw_object_type(&mut self, object_type: i32) {
+        self.properties[21] = object_type;
+    }
+
     /// Get the "Object Type" value for this trigger effect.
     pub fn object_type(&self) -> UnitTypeID {
         self.properties[21].try_into().unwrap()
@@ -494,10 +580,27 @@ impl TriggerEffect {
     pub fn set_stance(&mut self, stance: i32) {
         self.properties[23] = stance;
     }
+
+    /// Get the "Enabled" value for this trigger effect. This value only exists in Definitive
+    /// Edition trigger effects, and is `true` for other effects.
+    pub(crate) fn enabled(&self) -> bool {
+        self.properties.get(25) != Some(&0)
+    }
+
+    /// Set the "Enabled" value for this trigger effect. This value only exists in Definitive
+    /// Edition trigger effects.
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.properties[25] = i32::from(enabled);
+    }
+
+    /// Get all the strings in this effect, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.chat_text.iter_mut().chain(self.audio_file.iter_mut())
+    }
 }
 
 /// A trigger, describing automatic interactive behaviours in a scenario.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Trigger {
     enabled: bool,
     looping: bool,
@@ -638,6 +741,58 @@ impl Trigger {
         Ok(())
     }
 
+    /// Is this trigger enabled when the scenario starts?
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Set whether this trigger is enabled when the scenario starts.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Get the name of this trigger, shown in the scenario editor.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Set the name of this trigger.
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    /// Get the description of this trigger, shown as an objective if the trigger is set to
+    /// display one.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Set the description of this trigger.
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.description = description;
+    }
+
+    /// Get the short description of this trigger, shown on screen during the game. Only stored in
+    /// trigger system version 1.8 and later.
+    pub fn short_description(&self) -> Option<&str> {
+        self.short_description.as_deref()
+    }
+
+    /// Set the short description of this trigger.
+    pub fn set_short_description(&mut self, short_description: Option<String>) {
+        self.short_description = short_description;
+    }
+
+    /// Get the string table key for the short description of this trigger.
+    pub fn short_description_id(&self) -> Option<&StringKey> {
+        self.short_description_id.as_ref()
+    }
+
+    /// Set the string table key for the short description of this trigger.
+    pub fn set_short_description_id(&mut self, short_description_id: Option<StringKey>) {
+        self.short_description_id = short_description_id;
+    }
+
     /// Get the conditions in this trigger, in display order.
     pub fn conditions(&self) -> impl Iterator<Item = &TriggerCondition> {
         self.condition_order
@@ -645,6 +800,11 @@ impl Trigger {
             .map(move |index| &self.conditions[*index as usize])
     }
 
+    /// Get the conditions in this trigger, unordered.
+    pub fn conditions_unordered(&self) -> impl Iterator<Item = &TriggerCondition> {
+        self.conditions.iter()
+    }
+
     /// Get the conditions in this trigger, unordered.
     pub fn conditions_unordered_mut(&mut self) -> impl Iterator<Item = &mut TriggerCondition> {
         self.conditions.iter_mut()
@@ -657,10 +817,81 @@ impl Trigger {
             .map(move |index| &self.effects[*index as usize])
     }
 
+    /// Get the effects in this trigger, unordered.
+    pub fn effects_unordered(&self) -> impl Iterator<Item = &TriggerEffect> {
+        self.effects.iter()
+    }
+
+    /// Does this trigger use any of the fields that were added in trigger system version 1.8?
+    pub(crate) fn uses_display_fields(&self) -> bool {
+        self.make_header
+            || self.short_description_id.is_some()
+            || self.short_description.is_some()
+            || self.display_short_description
+            || self.short_description_state != 0
+            || self.mute_objective
+    }
+
+    /// Reset the fields that were added in trigger system version 1.8.
+    pub(crate) fn clear_display_fields(&mut self) {
+        self.make_header = false;
+        self.short_description_id = None;
+        self.short_description = None;
+        self.display_short_description = false;
+        self.short_description_state = 0;
+        self.mute_objective = false;
+    }
+
     /// Get the effects in this trigger, unordered.
     pub fn effects_unordered_mut(&mut self) -> impl Iterator<Item = &mut TriggerEffect> {
         self.effects.iter_mut()
     }
+
+    /// Remove the conditions for which `keep` returns false.
+    pub fn retain_conditions(&mut self, keep: impl FnMut(&TriggerCondition) -> bool) {
+        retain_ordered(&mut self.conditions, &mut self.condition_order, keep);
+    }
+
+    /// Remove the effects for which `keep` returns false.
+    pub fn retain_effects(&mut self, keep: impl FnMut(&TriggerEffect) -> bool) {
+        retain_ordered(&mut self.effects, &mut self.effect_order, keep);
+    }
+
+    /// Get all the strings in this trigger, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.description
+            .iter_mut()
+            .chain(self.short_description.iter_mut())
+            .chain(self.name.iter_mut())
+            .chain(self.effects.iter_mut().flat_map(TriggerEffect::strings_mut))
+    }
+}
+
+/// Remove items from a list, and update the display order that refers to them by index.
+fn retain_ordered<T>(items: &mut Vec<T>, order: &mut Vec<i32>, mut keep: impl FnMut(&T) -> bool) {
+    let mut next_index = 0;
+    let new_indices: Vec<Option<i32>> = items
+        .iter()
+        .map(|item| {
+            if keep(item) {
+                next_index += 1;
+                Some(next_index - 1)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let mut kept = new_indices.iter();
+    items.retain(|_| kept.next().copied().flatten().is_some());
+    *order = order
+        .iter()
+        .filter_map(|&index| {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| new_indices.get(index).copied().flatten())
+        })
+        .collect();
 }
 
 /// The trigger system maintains an ordered list  of triggers.
@@ -816,10 +1047,58 @@ impl TriggerSystem {
             .map(move |index| &self.triggers[*index as usize])
     }
 
+    /// Iterate over the IDs of all triggers, in order.
+    pub(crate) fn trigger_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.trigger_order.iter().map(|&index| index as usize)
+    }
+
+    /// Get the display order of the triggers, mutably.
+    #[cfg(test)]
+    pub(crate) fn trigger_order_mut(&mut self) -> &mut Vec<i32> {
+        &mut self.trigger_order
+    }
+
+    /// Add a trigger after all other triggers, and return its ID.
+    pub(crate) fn push(&mut self, trigger: Trigger) -> i32 {
+        let id = self.triggers.len() as i32;
+        self.triggers.push(trigger);
+        self.trigger_order.push(id);
+        id
+    }
+
+    /// Does this trigger system use variables or enabled techs, which were added in trigger system
+    /// version 2.2?
+    pub(crate) fn uses_variables(&self) -> bool {
+        self.variable_values.iter().any(|value| *value != 0)
+            || !self.enabled_techs.is_empty()
+            || self.variable_names.iter().any(|name| !name.is_empty())
+    }
+
+    /// Reset the variables and enabled techs, which were added in trigger system version 2.2.
+    pub(crate) fn clear_variables(&mut self) {
+        self.enabled_techs.clear();
+        self.variable_values.iter_mut().for_each(|value| *value = 0);
+        self.variable_names.iter_mut().for_each(String::clear);
+    }
+
+    /// Iterate over all triggers, unordered.
+    ///
+    /// The position of a trigger in this list is the ID that effects use to refer to it.
+    pub fn triggers_unordered(&self) -> impl Iterator<Item = &Trigger> {
+        self.triggers.iter()
+    }
+
     /// Iterate over all triggers, mutably and unordered.
     pub fn triggers_unordered_mut(&mut self) -> impl Iterator<Item = &mut Trigger> {
         self.triggers.iter_mut()
     }
+
+    /// Get all the strings in the trigger system, for converting them between text encodings.
+    pub(crate) fn strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.variable_names
+            .iter_mut()
+            .chain(self.triggers.iter_mut().flat_map(Trigger::strings_mut))
+    }
 }
 
 fn write_opt_string_key(mut output: impl Write, opt_key: &Option<StringKey>) -> Result<()> {
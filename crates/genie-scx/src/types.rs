@@ -1,6 +1,7 @@
 //! Contains pure types, no IO.
 //!
 //! Most of these are more descriptive wrappers around integers.
+use genie_support::f32_eq;
 use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::fmt::{self, Debug, Display};
@@ -15,6 +16,38 @@ impl SCXVersion {
         &self.0
     }
 
+    /// Does this format version store the animation frame of each object?
+    pub fn has_object_frames(self) -> bool {
+        self > SCXVersion(*b"1.14")
+    }
+
+    /// Can this format version store objects that are garrisoned inside other objects?
+    pub fn supports_garrisoned_objects(self) -> bool {
+        self > SCXVersion(*b"1.12")
+    }
+
+    /// Can this format version store triggers?
+    pub fn supports_triggers(self) -> bool {
+        self > SCXVersion(*b"1.13")
+    }
+
+    /// Can this format version store AI files bundled with the scenario?
+    pub fn supports_ai_files(self) -> bool {
+        self > SCXVersion(*b"1.17") && self < SCXVersion(*b"2.00")
+    }
+
+    /// Does this format version store the player settings before the player objects, instead of
+    /// after?
+    pub(crate) fn has_players_before_objects(self) -> bool {
+        self >= SCXVersion(*b"1.36")
+    }
+
+    /// Does this format version store the scenario description in the header, instead of DLC
+    /// options?
+    pub(crate) fn has_hd_style_description(self) -> bool {
+        self == *b"3.13"
+    }
+
     pub(crate) fn to_player_version(self) -> Option<f32> {
         match self.as_bytes() {
             b"1.07" => Some(1.07),
@@ -263,6 +296,15 @@ impl StartingAge {
         }
     }
 
+    /// Can this starting age be stored in scenarios for the given data version?
+    pub fn is_supported(self, version: f32) -> bool {
+        match self {
+            StartingAge::Default => true,
+            StartingAge::Nomad => version >= 1.25,
+            _ => version > 1.05,
+        }
+    }
+
     /// Serialize the age identifier to an integer that is understood by the given game version.
     pub fn to_i32(self, version: f32) -> i32 {
         if version < 1.25 {
@@ -360,6 +402,182 @@ impl From<VictoryCondition> for u8 {
     }
 }
 
+/// The first data version used by AoE2: Definitive Edition.
+const FIRST_DE_DATA_VERSION: f32 = 1.28;
+
+/// Can this data version store string table keys for the scenario name of each player?
+pub(crate) fn supports_player_name_keys(version: f32) -> bool {
+    version > 1.16
+}
+
+/// Can this data version store string table keys for the scenario texts, other than the scout
+/// text?
+pub(crate) fn supports_text_keys(version: f32) -> bool {
+    version >= 1.16
+}
+
+/// Can this data version store the hints, victory, defeat, and history texts?
+pub(crate) fn supports_instruction_messages(version: f32) -> bool {
+    version >= 1.11
+}
+
+/// Can this data version store the scout text, and its string table key?
+pub(crate) fn supports_scout_text(version: f32) -> bool {
+    version >= 1.22
+}
+
+/// Can this data version store the civilizations that players are locked to?
+pub(crate) fn supports_civ_lock(version: f32) -> bool {
+    version >= FIRST_DE_DATA_VERSION
+}
+
+/// Can this data version store the victory mode, score, and time limit?
+pub(crate) fn supports_victory_settings(version: f32) -> bool {
+    version >= 1.13
+}
+
+/// How much of the team settings a data version stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StoredTeamSettings {
+    /// Nothing is stored, the defaults are always used.
+    None,
+    /// Only whether teams are locked is stored.
+    LockedOnly,
+    /// All the team settings are stored.
+    All,
+}
+
+/// Get how much of the team settings a data version stores.
+pub(crate) fn stored_team_settings(version: f32) -> StoredTeamSettings {
+    if version >= 1.24 {
+        StoredTeamSettings::All
+    } else if f32_eq!(version, 1.23) {
+        StoredTeamSettings::LockedOnly
+    } else {
+        StoredTeamSettings::None
+    }
+}
+
+/// Can this data version store starting ore and trade goods for players?
+pub(crate) fn supports_ore_and_goods(version: f32) -> bool {
+    version >= 1.17
+}
+
+/// Can this data version store the map type that was used to generate the map?
+pub(crate) fn supports_map_type(version: f32) -> bool {
+    version >= 1.21
+}
+
+/// Does this data version store the number of triggers in the scenario data, as well as in the
+/// trigger system?
+pub(crate) fn stores_trigger_count(version: f32) -> bool {
+    version >= 1.35
+}
+
+/// Can this data version store the Definitive Edition water definition?
+pub(crate) fn supports_water_definition(version: f32) -> bool {
+    version >= 1.30
+}
+
+/// Can this data version store the Definitive Edition colour mood?
+pub(crate) fn supports_color_mood(version: f32) -> bool {
+    version >= 1.32
+}
+
+/// Can this data version store the collide-and-correct pathing setting?
+pub(crate) fn supports_collide_and_correct(version: f32) -> bool {
+    version >= 1.36
+}
+
+/// Can this data version store the villager force drop setting?
+pub(crate) fn supports_villager_force_drop(version: f32) -> bool {
+    version >= 1.37
+}
+
+/// Can this data version store the file name of the instructions picture?
+pub(crate) fn supports_bitmap_names(version: f32) -> bool {
+    version >= 1.09
+}
+
+/// Can this data version store the picture shown on the instructions screen?
+pub(crate) fn supports_bitmaps(version: f32) -> bool {
+    version >= 1.10
+}
+
+/// Can this data version store instructions pictures without a palette?
+pub(crate) fn supports_true_color_bitmaps(version: f32) -> bool {
+    version >= FIRST_DE_DATA_VERSION
+}
+
+/// Get the number of properties that trigger conditions and effects have in a trigger system
+/// version, as `(conditions, effects)`.
+pub(crate) fn trigger_properties(version: f64) -> (usize, usize) {
+    if version >= 2.2 {
+        (21, 46)
+    } else {
+        (18, 24)
+    }
+}
+
+/// Can this data version store AI rules scripts for players?
+pub(crate) fn supports_ai_rules(version: f32) -> bool {
+    version >= 1.08
+}
+
+/// How a data version stores the disabled techs, units, and buildings of each player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DisabledLists {
+    /// Nothing can be disabled.
+    Unsupported,
+    /// Only techs can be disabled, in a list of this length padded with -1, without a count.
+    TechsOnly(usize),
+    /// Counts, followed by lists of these lengths padded with -1.
+    Padded {
+        techs: usize,
+        units: usize,
+        buildings: usize,
+    },
+    /// Counts, followed by lists of exactly that length. Used by Definitive Edition.
+    Exact,
+}
+
+/// Get the way a data version stores the disabled techs, units, and buildings.
+pub(crate) fn disabled_lists(version: f32) -> DisabledLists {
+    if version >= FIRST_DE_DATA_VERSION {
+        DisabledLists::Exact
+    } else if version >= 1.18 {
+        DisabledLists::Padded {
+            techs: 30,
+            units: 30,
+            buildings: if version >= 1.25 { 30 } else { 20 },
+        }
+    } else if version > 1.03 {
+        DisabledLists::TechsOnly(20)
+    } else {
+        DisabledLists::Unsupported
+    }
+}
+
+/// Get the maximum number of disabled techs, units, and buildings per player that can be stored in
+/// a data version.
+pub(crate) fn max_disabled(version: f32) -> (usize, usize, usize) {
+    match disabled_lists(version) {
+        DisabledLists::Exact => (usize::MAX, usize::MAX, usize::MAX),
+        DisabledLists::Padded {
+            techs,
+            units,
+            buildings,
+        } => (techs, units, buildings),
+        DisabledLists::TechsOnly(techs) => (techs, 0, 0),
+        DisabledLists::Unsupported => (0, 0, 0),
+    }
+}
+
+/// Can a header of this version store HD Edition DLC options?
+pub(crate) fn has_dlc_options(format: SCXVersion, header: u32) -> bool {
+    header > 2 && !format.has_hd_style_description()
+}
+
 /// All the versions an SCX file uses in a single struct.
 #[derive(Debug, Clone, PartialEq)]
 pub struct VersionBundle {
@@ -469,6 +687,11 @@ impl VersionBundle {
         }
     }
 
+    /// Returns whether this version is (likely) for an AoE1 or Rise of Rome scenario.
+    pub fn is_aoe(&self) -> bool {
+        self.format < SCXVersion(*b"1.18")
+    }
+
     /// Returns whether this version is (likely) for an AoK scenario.
     pub fn is_aok(&self) -> bool {
         matches!(self.format.as_bytes(), b"1.18" | b"1.19" | b"1.20")
@@ -480,12 +703,218 @@ impl VersionBundle {
     }
 
     /// Returns whether this version is (likely) for an HD Edition scenario.
+    ///
+    /// HD Edition uses the same format versions as AoC, so this is decided by the data version:
+    /// AoC scenarios, with a data version up to 1.22, are not HD Edition scenarios.
     pub fn is_hd_edition(&self) -> bool {
-        self.format == *b"1.21" || self.format == *b"1.22" && self.data > 1.22
+        (self.format == *b"1.21" || self.format == *b"1.22") && self.data > 1.22
     }
 
     /// Returns whether this version is (likely) for an AoE2: Definitive Edition scenario.
     pub fn is_age2_de(&self) -> bool {
-        self.data >= 1.28
+        self.data >= FIRST_DE_DATA_VERSION
+    }
+
+    /// Can this version store triggers?
+    pub fn supports_triggers(&self) -> bool {
+        self.format.supports_triggers()
+    }
+
+    /// Can this version store the order in which triggers are shown in the editor? Without it,
+    /// triggers are shown in the order of their IDs.
+    pub fn has_trigger_display_order(&self) -> bool {
+        self.triggers.map_or(false, |version| version >= 1.4)
+    }
+
+    /// Can this version store short descriptions, and header and mute settings on triggers?
+    pub fn supports_trigger_display_fields(&self) -> bool {
+        self.triggers.map_or(false, |version| version >= 1.8)
+    }
+
+    /// Can this version store trigger variables and enabled techs?
+    pub fn supports_trigger_variables(&self) -> bool {
+        self.triggers.map_or(false, |version| version >= 2.2)
+    }
+
+    /// Can this version store objects that are garrisoned inside other objects?
+    pub fn supports_garrisoned_objects(&self) -> bool {
+        self.format.supports_garrisoned_objects()
+    }
+
+    /// Can this version store HD Edition DLC options?
+    pub fn has_dlc_options(&self) -> bool {
+        has_dlc_options(self.format, self.header)
+    }
+
+    /// Can this version store the name of the scenario author?
+    pub fn supports_author_name(&self) -> bool {
+        self.header >= 5
+    }
+
+    /// Can this version store terrain that is layered on top of other terrain?
+    pub fn supports_layered_terrain(&self) -> bool {
+        self.map >= 1
+    }
+
+    /// Can this version store the setting to disable wave rendering?
+    pub fn supports_render_waves(&self) -> bool {
+        self.map >= 2
+    }
+
+    /// Can this version store the picture shown on the instructions screen?
+    pub fn supports_bitmaps(&self) -> bool {
+        supports_bitmaps(self.data)
+    }
+
+    /// Can this version store instructions pictures without a palette? Other pictures are
+    /// quantized to 256 colours.
+    pub fn supports_true_color_bitmaps(&self) -> bool {
+        supports_true_color_bitmaps(self.data)
+    }
+
+    /// Can this version store AI rules scripts for players?
+    pub fn supports_ai_rules(&self) -> bool {
+        supports_ai_rules(self.data)
+    }
+
+    /// Can this version store starting ore and trade goods for players?
+    pub fn supports_ore_and_goods(&self) -> bool {
+        supports_ore_and_goods(self.data)
+    }
+
+    /// Can this version store the map type that was used to generate the map?
+    pub fn supports_map_type(&self) -> bool {
+        supports_map_type(self.data)
+    }
+
+    /// Can this version store the victory mode, score, and time limit?
+    pub fn supports_victory_settings(&self) -> bool {
+        supports_victory_settings(self.data)
+    }
+
+    /// Can this version store the Definitive Edition water definition?
+    pub fn supports_water_definition(&self) -> bool {
+        supports_water_definition(self.data)
+    }
+
+    /// Can this version store the Definitive Edition colour mood?
+    pub fn supports_color_mood(&self) -> bool {
+        supports_color_mood(self.data)
+    }
+
+    /// Can this version store the collide-and-correct pathing setting?
+    pub fn supports_collide_and_correct(&self) -> bool {
+        supports_collide_and_correct(self.data)
+    }
+
+    /// Can this version store the villager force drop setting?
+    pub fn supports_villager_force_drop(&self) -> bool {
+        supports_villager_force_drop(self.data)
+    }
+
+    /// Can this version store AI files bundled with the scenario?
+    pub fn supports_ai_files(&self) -> bool {
+        self.format.supports_ai_files()
+    }
+
+    /// How many techs can be disabled per player. This is `usize::MAX` if there is no limit.
+    pub fn max_disabled_techs(&self) -> usize {
+        max_disabled(self.data).0
+    }
+
+    /// How many units can be disabled per player. This is `usize::MAX` if there is no limit.
+    pub fn max_disabled_units(&self) -> usize {
+        max_disabled(self.data).1
+    }
+
+    /// How many buildings can be disabled per player. This is `usize::MAX` if there is no limit.
+    pub fn max_disabled_buildings(&self) -> usize {
+        max_disabled(self.data).2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SCXVersion, VersionBundle};
+    use crate::{LostInformation, Scenario};
+    use std::fs::File;
+
+    #[test]
+    fn capabilities() -> anyhow::Result<()> {
+        let aoc = VersionBundle::aoc();
+        let de = VersionBundle::aoe2_de();
+        assert!(aoc.supports_triggers() && de.supports_triggers());
+        assert!(!VersionBundle::ror().supports_triggers());
+        assert!(!aoc.has_dlc_options() && VersionBundle::hd_edition().has_dlc_options());
+        assert!(!aoc.supports_layered_terrain() && de.supports_layered_terrain());
+        assert!(!aoc.supports_trigger_variables() && de.supports_trigger_variables());
+        assert!(!aoc.supports_true_color_bitmaps() && de.supports_true_color_bitmaps());
+        assert!(aoc.supports_ai_files() && aoc.supports_ai_rules());
+
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let scen = Scenario::read_from(&mut f)?;
+        let lost = scen.lost_information(&aoc);
+        assert!(lost
+            .iter()
+            .any(|info| matches!(info, LostInformation::LayeredTerrain(_))));
+        assert!(scen.lost_information(&de).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn detect_game() -> anyhow::Result<()> {
+        let aoc = VersionBundle::aoc();
+        assert!(aoc.is_aoc() && !aoc.is_hd_edition() && !aoc.is_age2_de());
+        let aoc_122 = VersionBundle {
+            format: SCXVersion(*b"1.22"),
+            ..VersionBundle::aoc()
+        };
+        assert!(!aoc_122.is_hd_edition());
+        let hd = VersionBundle::hd_edition();
+        assert!(!hd.is_aoc() && hd.is_hd_edition() && !hd.is_age2_de());
+        let de = VersionBundle::aoe2_de();
+        assert!(!de.is_aoc() && !de.is_hd_edition() && de.is_age2_de());
+
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+        assert!(scen.version().is_aoc() && !scen.version().is_hd_edition());
+        Ok(())
+    }
+
+    #[test]
+    fn de_data_versions() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let scen = Scenario::read_from(&mut f)?;
+        let hd = VersionBundle::hd_edition();
+        for data in [1.28, 1.30, 1.32, 1.35, 1.36, 1.37] {
+            let version = VersionBundle {
+                data,
+                ..VersionBundle::aoe2_de()
+            };
+            let lost = scen.lost_information(&version);
+            assert_eq!(
+                lost.contains(&LostInformation::WaterDefinition),
+                !version.supports_water_definition()
+            );
+            assert_eq!(
+                lost.contains(&LostInformation::ColorMood),
+                !version.supports_color_mood()
+            );
+
+            let mut out = vec![];
+            scen.write_to_version(&mut out, &version)?;
+            let written = Scenario::read_from(&out[..])?;
+            assert_eq!(written.objects().count(), scen.objects().count());
+            let lost = written.lost_information(&hd);
+            assert_eq!(
+                lost.contains(&LostInformation::WaterDefinition),
+                version.supports_water_definition()
+            );
+            assert_eq!(
+                lost.contains(&LostInformation::ColorMood),
+                version.supports_color_mood()
+            );
+        }
+        Ok(())
     }
 }
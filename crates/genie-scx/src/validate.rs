@@ -0,0 +1,321 @@
+//! Checks scenario contents against the game data they will be played with.
+
+use crate::format::SCXFormat;
+use genie_dat::DatFile;
+use genie_support::UnitTypeID;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Identifies a condition or effect inside a trigger, by its unordered index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerItem {
+    /// The condition at this index.
+    Condition(usize),
+    /// The effect at this index.
+    Effect(usize),
+}
+
+impl fmt::Display for TriggerItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerItem::Condition(index) => write!(f, "condition #{}", index),
+            TriggerItem::Effect(index) => write!(f, "effect #{}", index),
+        }
+    }
+}
+
+/// A reference from a scenario to something that does not exist in a data file.
+///
+/// Player numbers start at 0 for GAIA. Trigger numbers are the unordered trigger indices, which
+/// are also the IDs used by "Activate Trigger" and "Deactivate Trigger" effects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// A placed object has a unit type that no civilization defines.
+    UnknownObjectType {
+        player: usize,
+        object_id: i32,
+        unit_type: UnitTypeID,
+    },
+    /// A map tile has a terrain type that is not defined.
+    UnknownTerrain { x: u32, y: u32, terrain: u8 },
+    /// A map tile has a layered terrain type that is not defined.
+    UnknownLayeredTerrain { x: u32, y: u32, terrain: u16 },
+    /// A trigger condition or effect refers to a unit type that no civilization defines.
+    UnknownTriggerUnitType {
+        trigger: usize,
+        item: TriggerItem,
+        unit_type: i32,
+    },
+    /// A trigger condition or effect refers to a tech that is not defined.
+    UnknownTriggerTech {
+        trigger: usize,
+        item: TriggerItem,
+        tech: i32,
+    },
+    /// A player has a disabled tech that is not defined.
+    UnknownDisabledTech { player: usize, tech: i32 },
+    /// A player has a disabled unit that no civilization defines.
+    UnknownDisabledUnit { player: usize, unit_type: i32 },
+    /// A player has a disabled building that no civilization defines.
+    UnknownDisabledBuilding { player: usize, unit_type: i32 },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::UnknownObjectType {
+                player,
+                object_id,
+                unit_type,
+            } => write!(
+                f,
+                "object {} of player {} has unknown unit type {}",
+                object_id,
+                player,
+                u16::from(*unit_type)
+            ),
+            ValidationIssue::UnknownTerrain { x, y, terrain } => {
+                write!(f, "tile ({}, {}) has unknown terrain {}", x, y, terrain)
+            }
+            ValidationIssue::UnknownLayeredTerrain { x, y, terrain } => write!(
+                f,
+                "tile ({}, {}) has unknown layered terrain {}",
+                x, y, terrain
+            ),
+            ValidationIssue::UnknownTriggerUnitType {
+                trigger,
+                item,
+                unit_type,
+            } => write!(
+                f,
+                "{} of trigger #{} refers to unknown unit type {}",
+                item, trigger, unit_type
+            ),
+            ValidationIssue::UnknownTriggerTech {
+                trigger,
+                item,
+                tech,
+            } => write!(
+                f,
+                "{} of trigger #{} refers to unknown tech {}",
+                item, trigger, tech
+            ),
+            ValidationIssue::UnknownDisabledTech { player, tech } => {
+                write!(f, "player {} has unknown disabled tech {}", player, tech)
+            }
+            ValidationIssue::UnknownDisabledUnit { player, unit_type } => write!(
+                f,
+                "player {} has unknown disabled unit {}",
+                player, unit_type
+            ),
+            ValidationIssue::UnknownDisabledBuilding { player, unit_type } => write!(
+                f,
+                "player {} has unknown disabled building {}",
+                player, unit_type
+            ),
+        }
+    }
+}
+
+/// Does any civilization define this unit type?
+pub(crate) fn has_unit_type(dat: &DatFile, id: UnitTypeID) -> bool {
+    dat.civilizations
+        .iter()
+        .any(|civ| civ.get_unit_type(id).is_some())
+}
+
+/// Check a raw unit type ID as stored in triggers and disabled lists. Negative values mean "none".
+pub(crate) fn is_unknown_unit_type(dat: &DatFile, id: i32) -> bool {
+    if id < 0 {
+        return false;
+    }
+    match u16::try_from(id) {
+        Ok(id) => !has_unit_type(dat, id.into()),
+        Err(_) => true,
+    }
+}
+
+/// Check a raw tech ID as stored in triggers and disabled lists. Negative values mean "none".
+pub(crate) fn is_unknown_tech(dat: &DatFile, id: i32) -> bool {
+    if id < 0 {
+        return false;
+    }
+    match u16::try_from(id) {
+        Ok(id) => dat.get_tech(id).is_none(),
+        Err(_) => true,
+    }
+}
+
+pub(crate) fn validate(format: &SCXFormat, dat: &DatFile) -> Vec<ValidationIssue> {
+    let mut issues = vec![];
+
+    for (player, objects) in format.player_objects.iter().enumerate() {
+        for object in objects {
+            if !has_unit_type(dat, object.object_type) {
+                issues.push(ValidationIssue::UnknownObjectType {
+                    player,
+                    object_id: object.id,
+                    unit_type: object.object_type,
+                });
+            }
+        }
+    }
+
+    for (y, row) in format.map.rows().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let (x, y) = (x as u32, y as u32);
+            if dat.get_terrain(tile.terrain).is_none() {
+                issues.push(ValidationIssue::UnknownTerrain {
+                    x,
+                    y,
+                    terrain: tile.terrain,
+                });
+            }
+            if let Some(terrain) = tile.layered_terrain {
+                if dat.get_terrain(terrain).is_none() {
+                    issues.push(ValidationIssue::UnknownLayeredTerrain { x, y, terrain });
+                }
+            }
+        }
+    }
+
+    if let Some(triggers) = &format.triggers {
+        for (trigger, trig) in triggers.triggers_unordered().enumerate() {
+            for (index, condition) in trig.conditions_unordered().enumerate() {
+                let item = TriggerItem::Condition(index);
+                if is_unknown_unit_type(dat, condition.raw_unit_type()) {
+                    issues.push(ValidationIssue::UnknownTriggerUnitType {
+                        trigger,
+                        item,
+                        unit_type: condition.raw_unit_type(),
+                    });
+                }
+                if is_unknown_tech(dat, condition.technology_id()) {
+                    issues.push(ValidationIssue::UnknownTriggerTech {
+                        trigger,
+                        item,
+                        tech: condition.technology_id(),
+                    });
+                }
+            }
+            for (index, effect) in trig.effects_unordered().enumerate() {
+                let item = TriggerItem::Effect(index);
+                if is_unknown_unit_type(dat, effect.raw_unit_type()) {
+                    issues.push(ValidationIssue::UnknownTriggerUnitType {
+                        trigger,
+                        item,
+                        unit_type: effect.raw_unit_type(),
+                    });
+                }
+                if is_unknown_tech(dat, effect.technology_id()) {
+                    issues.push(ValidationIssue::UnknownTriggerTech {
+                        trigger,
+                        item,
+                        tech: effect.technology_id(),
+                    });
+                }
+            }
+        }
+    }
+
+    let tribe_scen = &format.tribe_scen;
+    for index in 0..16 {
+        let player = index + 1;
+        for &tech in tribe_scen.player_disabled_techs(index) {
+            if is_unknown_tech(dat, tech) {
+                issues.push(ValidationIssue::UnknownDisabledTech { player, tech });
+            }
+        }
+        for &unit_type in tribe_scen.player_disabled_units(index) {
+            if is_unknown_unit_type(dat, unit_type) {
+                issues.push(ValidationIssue::UnknownDisabledUnit { player, unit_type });
+            }
+        }
+        for &unit_type in tribe_scen.player_disabled_buildings(index) {
+            if is_unknown_unit_type(dat, unit_type) {
+                issues.push(ValidationIssue::UnknownDisabledBuilding { player, unit_type });
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scenario;
+    use std::fs::File;
+
+    #[test]
+    fn validate_aoc_scenario() -> anyhow::Result<()> {
+        let dat = DatFile::read_from(File::open("../genie-dat/fixtures/aoc1.0c.dat")?)?;
+        let mut scen =
+            Scenario::read_from(File::open("test/scenarios/Jeremiah Johnson (Update).scx")?)?;
+        assert_eq!(scen.validate(&dat), vec![]);
+
+        scen.map_mut().tile_mut(3, 4).unwrap().terrain = 200;
+        assert_eq!(
+            scen.validate(&dat),
+            vec![ValidationIssue::UnknownTerrain {
+                x: 3,
+                y: 4,
+                terrain: 200
+            }]
+        );
+
+        // Copy a trigger into a scenario with a trigger list, pointing it at unknown units.
+        let source = Scenario::read_from(File::open("test/scenarios/Age of Heroes b1-3-5.scx")?)?;
+        let mut trigger = source
+            .triggers()
+            .unwrap()
+            .triggers_unordered()
+            .find(|trigger| {
+                trigger.conditions_unordered().count() == 1
+                    && trigger.effects_unordered().count() == 1
+            })
+            .unwrap()
+            .clone();
+        for condition in trigger.conditions_unordered_mut() {
+            condition.set_raw_unit_type(-1);
+            condition.set_technology_id(-1);
+            // The object class (Building) is not a unit type.
+            condition.set_raw_object_type(2);
+        }
+        for effect in trigger.effects_unordered_mut() {
+            effect.set_raw_unit_type(9001);
+            effect.set_technology_id(9002);
+            effect.set_raw_object_type(2);
+        }
+        let mut scen = Scenario::read_from(File::open("test/scenarios/real_world_amazon.scx")?)?;
+        let triggers = scen.format.triggers.get_or_insert_with(Default::default);
+        let trigger = triggers.push(trigger) as usize;
+        let issues: Vec<_> = scen
+            .validate(&dat)
+            .into_iter()
+            .filter(|issue| {
+                matches!(
+                    issue,
+                    ValidationIssue::UnknownTriggerUnitType { .. }
+                        | ValidationIssue::UnknownTriggerTech { .. }
+                )
+            })
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::UnknownTriggerUnitType {
+                    trigger,
+                    item: TriggerItem::Effect(0),
+                    unit_type: 9001
+                },
+                ValidationIssue::UnknownTriggerTech {
+                    trigger,
+                    item: TriggerItem::Effect(0),
+                    tech: 9002
+                },
+            ]
+        );
+        Ok(())
+    }
+}
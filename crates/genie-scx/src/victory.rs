@@ -1,4 +1,4 @@
-use crate::types::VictoryCondition;
+use crate::types::{supports_victory_settings, VictoryCondition};
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -79,7 +79,7 @@ impl LegacyVictoryInfo {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VictoryEntry {
     command: VictoryCondition,
     object_type: i32,
@@ -98,6 +98,159 @@ pub struct VictoryEntry {
 }
 
 impl VictoryEntry {
+    /// Create a victory entry with no parameters set, like the scenario editor does.
+    fn new(command: VictoryCondition) -> Self {
+        Self {
+            command,
+            object_type: 0,
+            player_id: -1,
+            x0: 0.0,
+            y0: 0.0,
+            x1: 0.0,
+            y1: 0.0,
+            number: 0,
+            count: 0,
+            source_object: -1,
+            target_object: -1,
+            victory_group: 1,
+            ally_flag: 0,
+            state: 0,
+        }
+    }
+
+    /// Capture the object with the given ID.
+    pub fn capture(object: i32) -> Self {
+        Self {
+            target_object: object,
+            ..Self::new(VictoryCondition::Capture)
+        }
+    }
+
+    /// Have `count` objects of a type.
+    pub fn create(object_type: i32, count: i32) -> Self {
+        Self {
+            object_type,
+            count,
+            ..Self::new(VictoryCondition::Create)
+        }
+    }
+
+    /// Destroy the object with the given ID.
+    pub fn destroy(object: i32) -> Self {
+        Self {
+            target_object: object,
+            ..Self::new(VictoryCondition::Destroy)
+        }
+    }
+
+    /// Destroy `count` objects of a type, owned by a player.
+    pub fn destroy_multiple(object_type: i32, player: i32, count: i32) -> Self {
+        Self {
+            object_type,
+            player_id: player,
+            count,
+            ..Self::new(VictoryCondition::DestroyMultiple)
+        }
+    }
+
+    /// Bring the object with the given ID into an area, given as `(x0, y0, x1, y1)`.
+    pub fn bring_to_area(object: i32, area: (f32, f32, f32, f32)) -> Self {
+        Self {
+            source_object: object,
+            x0: area.0,
+            y0: area.1,
+            x1: area.2,
+            y1: area.3,
+            ..Self::new(VictoryCondition::BringToArea)
+        }
+    }
+
+    /// Bring the object with the given ID close to another object.
+    pub fn bring_to_object(object: i32, target: i32) -> Self {
+        Self {
+            source_object: object,
+            target_object: target,
+            ..Self::new(VictoryCondition::BringToObject)
+        }
+    }
+
+    /// Collect an amount of a resource (attribute).
+    pub fn attribute(attribute: i32, amount: i32) -> Self {
+        Self {
+            number: attribute,
+            count: amount,
+            ..Self::new(VictoryCondition::Attribute)
+        }
+    }
+
+    /// Collect a number of relics.
+    pub fn relics(count: i32) -> Self {
+        Self::attribute(7, count)
+    }
+
+    /// Explore a percentage of the map.
+    pub fn exploration(percent: i32) -> Self {
+        Self::attribute(22, percent)
+    }
+
+    /// Destroy all objects of a type, owned by a player. For example, destroying a player's
+    /// King for regicide.
+    pub fn destroy_all(object_type: i32, player: i32) -> Self {
+        Self {
+            object_type,
+            player_id: player,
+            ..Self::new(VictoryCondition::DestroyAll)
+        }
+    }
+
+    /// Defeat a player.
+    pub fn destroy_player(player: i32) -> Self {
+        Self {
+            player_id: player,
+            ..Self::new(VictoryCondition::DestroyPlayer)
+        }
+    }
+
+    /// Get the kind of victory condition.
+    pub fn condition(&self) -> VictoryCondition {
+        self.command
+    }
+
+    /// Get the object type this condition refers to.
+    pub fn object_type(&self) -> i32 {
+        self.object_type
+    }
+
+    /// Get the player this condition refers to, or -1.
+    pub fn player_id(&self) -> i32 {
+        self.player_id
+    }
+
+    /// Get the area this condition refers to, as `(x0, y0, x1, y1)`.
+    pub fn area(&self) -> (f32, f32, f32, f32) {
+        (self.x0, self.y0, self.x1, self.y1)
+    }
+
+    /// Get the attribute that must be collected, for [`VictoryCondition::Attribute`].
+    pub fn attribute_id(&self) -> i32 {
+        self.number
+    }
+
+    /// Get the number of objects, or the amount of an attribute, that is required.
+    pub fn count(&self) -> i32 {
+        self.count
+    }
+
+    /// Get the ID of the object that must be moved, or -1.
+    pub fn source_object(&self) -> i32 {
+        self.source_object
+    }
+
+    /// Get the ID of the object that must be captured or destroyed, or -1.
+    pub fn target_object(&self) -> i32 {
+        self.target_object
+    }
+
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let command = input.read_u8()?.into();
         let object_type = input.read_i32::<LE>()?;
@@ -344,6 +497,13 @@ impl VictoryConditions {
 
         Ok(())
     }
+
+    /// Change the player numbers that victory entries refer to.
+    pub(crate) fn map_players(&mut self, map_player: impl Fn(i32) -> i32) {
+        for entry in self.entries.iter_mut() {
+            entry.player_id = map_player(entry.player_id);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -384,3 +544,102 @@ impl VictoryInfo {
         Ok(())
     }
 }
+
+/// How a multiplayer game is won.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VictoryMode {
+    /// Destroy all enemy units and buildings, build a Wonder, or collect all relics.
+    Standard,
+    /// Destroy all enemy units and buildings.
+    Conquest,
+    /// Reach a score.
+    Score,
+    /// Have the highest score when the time runs out.
+    TimeLimit,
+    /// Meet the custom conditions in [`VictorySettings`].
+    Custom,
+    /// Some unknown value, probably from a newer game version.
+    Other(i32),
+}
+
+impl From<i32> for VictoryMode {
+    fn from(n: i32) -> Self {
+        match n {
+            0 => VictoryMode::Standard,
+            1 => VictoryMode::Conquest,
+            2 => VictoryMode::Score,
+            3 => VictoryMode::TimeLimit,
+            4 => VictoryMode::Custom,
+            n => VictoryMode::Other(n),
+        }
+    }
+}
+
+impl From<VictoryMode> for i32 {
+    fn from(mode: VictoryMode) -> Self {
+        match mode {
+            VictoryMode::Standard => 0,
+            VictoryMode::Conquest => 1,
+            VictoryMode::Score => 2,
+            VictoryMode::TimeLimit => 3,
+            VictoryMode::Custom => 4,
+            VictoryMode::Other(n) => n,
+        }
+    }
+}
+
+/// Scenario-wide victory settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VictorySettings {
+    /// How the game is won.
+    pub mode: VictoryMode,
+    /// The score to reach, for [`VictoryMode::Score`].
+    pub score: i32,
+    /// The time limit, for [`VictoryMode::TimeLimit`].
+    pub time: i32,
+    /// Whether conquest is required, for [`VictoryMode::Custom`].
+    pub conquest: bool,
+    /// How many relics must be collected, for [`VictoryMode::Custom`].
+    pub relics: i32,
+    /// What percentage of the map must be explored, for [`VictoryMode::Custom`].
+    pub exploration: i32,
+    /// Whether all custom conditions must be met, instead of any one of them.
+    pub all_required: bool,
+    /// How many ruins must be captured. Only used by AoE1.
+    pub ruins: i32,
+    /// How many discoveries must be made. Only used by AoE1.
+    pub discoveries: i32,
+    /// How much gold must be collected. Only used by AoE1.
+    pub gold: i32,
+}
+
+impl Default for VictorySettings {
+    fn default() -> Self {
+        Self {
+            mode: VictoryMode::Custom,
+            score: 900,
+            time: 9000,
+            conquest: true,
+            relics: 0,
+            exploration: 0,
+            all_required: false,
+            ruins: 0,
+            discoveries: 0,
+            gold: 0,
+        }
+    }
+}
+
+impl VictorySettings {
+    /// Can these settings be stored in scenarios for the given data version?
+    ///
+    /// Versions before 1.13 do not store the victory mode, score, and time limit, and always use
+    /// custom victory.
+    pub fn is_supported(&self, version: f32) -> bool {
+        let default = Self::default();
+        supports_victory_settings(version)
+            || (self.mode == default.mode
+                && self.score == default.score
+                && self.time == default.time)
+    }
+}
@@ -112,6 +112,76 @@ fallible_try_from!(SpriteID, i16);
 fallible_try_from!(SpriteID, i32);
 fallible_try_from!(SpriteID, u32);
 
+/// An ID identifying a civilization
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CivilizationID(u8);
+
+impl From<u8> for CivilizationID {
+    fn from(n: u8) -> Self {
+        CivilizationID(n)
+    }
+}
+
+impl From<CivilizationID> for u8 {
+    fn from(n: CivilizationID) -> Self {
+        n.0
+    }
+}
+
+impl From<CivilizationID> for u16 {
+    fn from(n: CivilizationID) -> Self {
+        n.0.into()
+    }
+}
+
+impl From<CivilizationID> for u32 {
+    fn from(n: CivilizationID) -> Self {
+        n.0.into()
+    }
+}
+
+impl From<CivilizationID> for usize {
+    fn from(n: CivilizationID) -> Self {
+        n.0.into()
+    }
+}
+
+infallible_try_into!(CivilizationID, i16);
+infallible_try_into!(CivilizationID, i32);
+fallible_try_from!(CivilizationID, i8);
+fallible_try_from!(CivilizationID, i16);
+fallible_try_from!(CivilizationID, u16);
+fallible_try_from!(CivilizationID, i32);
+fallible_try_from!(CivilizationID, u32);
+
+/// An ID identifying a sound.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SoundID(u16);
+impl From<u16> for SoundID {
+    fn from(n: u16) -> Self {
+        SoundID(n)
+    }
+}
+
+impl From<SoundID> for u16 {
+    fn from(n: SoundID) -> Self {
+        n.0
+    }
+}
+
+impl From<SoundID> for usize {
+    fn from(n: SoundID) -> Self {
+        n.0.into()
+    }
+}
+
+fallible_try_into!(SoundID, i16);
+infallible_try_into!(SoundID, i32);
+infallible_try_into!(SoundID, u32);
+fallible_try_from!(SoundID, i16);
+fallible_try_from!(SoundID, i32);
+fallible_try_from!(SoundID, u32);
+
 /// A key in a language file.
 ///
 /// A key may be either a nonnegative integer or an arbitrary string.
@@ -1,20 +1,22 @@
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use encoding_rs::WINDOWS_1252;
+use std::borrow::Cow;
 use std::io::{self, Read, Write};
 
-/// Failed to decode a string as WINDOWS-1252.
+/// Failed to decode a string.
 ///
-/// This means that the scenario file contained a string that could not be decoded using the
-/// WINDOWS-1252 code page. In the future, genie-scx will support other encodings.
+/// This means that a file contained a string that could not be decoded using the expected text
+/// encoding, usually the WINDOWS-1252 code page.
 #[derive(Debug, Clone, Copy, thiserror::Error)]
-#[error("could not decode string as WINDOWS-1252")]
+#[error("could not decode string")]
 pub struct DecodeStringError;
 
-/// Failed to encode a string as WINDOWS-1252.
+/// Failed to encode a string.
 ///
-/// This means that a string could not be encoded using the WINDOWS-1252 code page. In the future, genie-scx will support other encodings.
+/// This means that a string could not be encoded using the expected text encoding, usually the
+/// WINDOWS-1252 code page.
 #[derive(Debug, Clone, Copy, thiserror::Error)]
-#[error("could not encode string as WINDOWS-1252")]
+#[error("could not encode string")]
 pub struct EncodeStringError;
 
 /// Failed to read a string.
@@ -39,15 +41,23 @@ pub enum WriteStringError {
     IoError(#[from] std::io::Error),
 }
 
+/// Encode a string using the WINDOWS-1252 code page, for strings that are written without a
+/// length prefix.
+pub fn encode_str(string: &str) -> Result<Cow<'_, [u8]>, EncodeStringError> {
+    let (bytes, _enc, failed) = WINDOWS_1252.encode(string);
+    if failed {
+        Err(EncodeStringError)
+    } else {
+        Ok(bytes)
+    }
+}
+
 /// Write a string to an output stream, using code page 1252, using a `u16` for the length prefix.
 ///
 /// This writes the length of the string (including NULL terminator) as a little-endian u16,
 /// followed by the encoded bytes, followed by a NULL terminator.
 pub fn write_str<W: Write>(output: &mut W, string: &str) -> Result<(), WriteStringError> {
-    let (bytes, _enc, failed) = WINDOWS_1252.encode(string);
-    if failed {
-        return Err(WriteStringError::EncodeStringError(EncodeStringError));
-    }
+    let bytes = encode_str(string)?;
     assert!(bytes.len() < std::i16::MAX as usize);
     output.write_i16::<LE>(bytes.len() as i16 + 1)?;
     output.write_all(&bytes)?;
@@ -60,10 +70,7 @@ pub fn write_str<W: Write>(output: &mut W, string: &str) -> Result<(), WriteStri
 /// This writes the length of the string (including NULL terminator) as a little-endian u177,
 /// followed by the encoded bytes, followed by a NULL terminator.
 pub fn write_i32_str<W: Write>(output: &mut W, string: &str) -> Result<(), WriteStringError> {
-    let (bytes, _enc, failed) = WINDOWS_1252.encode(string);
-    if failed {
-        return Err(WriteStringError::EncodeStringError(EncodeStringError));
-    }
+    let bytes = encode_str(string)?;
     assert!(bytes.len() < std::i32::MAX as usize);
     output.write_i32::<LE>(bytes.len() as i32 + 1)?;
     output.write_all(&bytes)?;
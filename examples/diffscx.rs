@@ -0,0 +1,59 @@
+extern crate genie;
+extern crate structopt;
+
+use genie::scx::{DiffSection, ScenarioChange};
+use genie::Scenario;
+use std::{fs::File, path::PathBuf};
+use structopt::StructOpt;
+
+/// Show what changed between two versions of a scenario file.
+#[derive(Debug, StructOpt)]
+struct Cli {
+    /// The old scenario file.
+    #[structopt(parse(from_os_str))]
+    before: PathBuf,
+    /// The new scenario file.
+    #[structopt(parse(from_os_str))]
+    after: PathBuf,
+    /// List every changed map tile, instead of only counting them.
+    #[structopt(long)]
+    tiles: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let Cli {
+        before,
+        after,
+        tiles,
+    } = Cli::from_args();
+
+    let before = Scenario::read_from(File::open(before)?)?;
+    let after = Scenario::read_from(File::open(after)?)?;
+    let changes = before.diff(&after);
+
+    if changes.is_empty() {
+        println!("No changes.");
+        return Ok(());
+    }
+
+    let changed_tiles = changes
+        .iter()
+        .filter(|change| matches!(change, ScenarioChange::TileChanged { .. }))
+        .count();
+    let mut section = None;
+    for change in &changes {
+        if section != Some(change.section()) {
+            section = Some(change.section());
+            println!("{}:", change.section());
+            if change.section() == DiffSection::Map && !tiles && changed_tiles > 0 {
+                println!("  {} tiles changed", changed_tiles);
+            }
+        }
+        if !tiles && matches!(change, ScenarioChange::TileChanged { .. }) {
+            continue;
+        }
+        println!("  {}", change);
+    }
+
+    Ok(())
+}